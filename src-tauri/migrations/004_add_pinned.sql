@@ -0,0 +1,7 @@
+-- Add pinned flag for favorites
+-- Pinned favorites get their own submenu in the tray so the go-to reactions
+-- are one click away without opening the picker.
+
+ALTER TABLE favorites ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_favorites_pinned ON favorites(pinned) WHERE pinned = 1;
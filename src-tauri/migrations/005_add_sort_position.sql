@@ -0,0 +1,16 @@
+-- Add manual sort position for favorites
+-- Lower positions list first (after pinned items). Existing rows are numbered
+-- newest-first so the current order is preserved; new favorites are inserted
+-- just above the current minimum.
+
+ALTER TABLE favorites ADD COLUMN sort_position INTEGER NOT NULL DEFAULT 0;
+
+UPDATE favorites
+SET sort_position = (
+    SELECT COUNT(*)
+    FROM favorites AS newer
+    WHERE newer.created_at > favorites.created_at
+       OR (newer.created_at = favorites.created_at AND newer.id > favorites.id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_favorites_order ON favorites(pinned DESC, sort_position ASC);
@@ -0,0 +1,62 @@
+-- Move tags out of the JSON columns into a tags table plus a join table
+-- Tag names are stored normalized: trimmed, lowercased, inner whitespace
+-- collapsed to single spaces. `is_custom` keeps the split between tags that
+-- came with the media (0) and ones the user added (1).
+
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS favorite_tags (
+    favorite_id INTEGER NOT NULL REFERENCES favorites(id) ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    is_custom INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (favorite_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_favorite_tags_tag ON favorite_tags(tag_id);
+
+-- Flatten the existing JSON arrays. Malformed JSON can't be recovered, so
+-- those rows contribute no tags. Custom tags go first so they win when the
+-- same tag appears in both lists.
+CREATE TEMP TABLE legacy_tags AS
+SELECT favorite_id, name, is_custom, ord
+FROM (
+    SELECT f.id AS favorite_id,
+           LOWER(TRIM(
+               REPLACE(REPLACE(REPLACE(REPLACE(
+                   REPLACE(REPLACE(REPLACE(j.value, char(9), ' '), char(10), ' '), char(13), ' '),
+               '  ', ' '), '  ', ' '), '  ', ' '), '  ', ' ')
+           )) AS name,
+           1 AS is_custom,
+           j.key AS ord
+    FROM favorites f, json_each(CASE WHEN json_valid(f.custom_tags) THEN f.custom_tags ELSE '[]' END) j
+    WHERE j.type = 'text'
+    UNION ALL
+    SELECT f.id,
+           LOWER(TRIM(
+               REPLACE(REPLACE(REPLACE(REPLACE(
+                   REPLACE(REPLACE(REPLACE(j.value, char(9), ' '), char(10), ' '), char(13), ' '),
+               '  ', ' '), '  ', ' '), '  ', ' '), '  ', ' ')
+           )),
+           0,
+           j.key
+    FROM favorites f, json_each(CASE WHEN json_valid(f.tags) THEN f.tags ELSE '[]' END) j
+    WHERE j.type = 'text'
+)
+WHERE name <> '';
+
+INSERT OR IGNORE INTO tags (name)
+SELECT name FROM legacy_tags ORDER BY favorite_id, is_custom DESC, ord;
+
+INSERT OR IGNORE INTO favorite_tags (favorite_id, tag_id, is_custom)
+SELECT l.favorite_id, t.id, l.is_custom
+FROM legacy_tags l
+JOIN tags t ON t.name = l.name
+ORDER BY l.favorite_id, l.is_custom DESC, l.ord;
+
+DROP TABLE legacy_tags;
+
+ALTER TABLE favorites DROP COLUMN tags;
+ALTER TABLE favorites DROP COLUMN custom_tags;
@@ -0,0 +1,4 @@
+-- Remember what the user searched for when they saved a Klipy favorite, so
+-- it can be found again by those words even if they aren't in the title.
+
+ALTER TABLE favorites ADD COLUMN source_query TEXT;
@@ -0,0 +1,13 @@
+-- Trigram index over the words of each favorite's filename, description,
+-- originating search and tags, used for typo-tolerant search ("hapy cat").
+-- Rows are computed in Rust (db/search_index.rs) and rewritten whenever a
+-- favorite or its tags change; existing favorites are indexed on startup.
+
+CREATE TABLE IF NOT EXISTS search_grams (
+    gram TEXT NOT NULL,
+    word TEXT NOT NULL,
+    favorite_id INTEGER NOT NULL REFERENCES favorites(id) ON DELETE CASCADE,
+    PRIMARY KEY (gram, word, favorite_id)
+) WITHOUT ROWID;
+
+CREATE INDEX IF NOT EXISTS idx_search_grams_favorite ON search_grams(favorite_id);
@@ -0,0 +1,5 @@
+-- Perceptual hash of each favorite's media, used to find the same reaction
+-- saved more than once (different Klipy slugs, local copies, resizes).
+-- Hex-encoded dHashes of a few representative frames; NULL until computed.
+
+ALTER TABLE favorites ADD COLUMN phash TEXT;
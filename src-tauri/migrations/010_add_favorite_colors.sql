@@ -0,0 +1,17 @@
+-- Dominant-color palette of each favorite, for "the red one" searches.
+-- HSL is stored alongside RGB so named colors can be matched by hue range
+-- in SQL. Rows are written from Rust when a favorite is added; existing
+-- favorites are filled in by a background pass on startup.
+
+CREATE TABLE IF NOT EXISTS favorite_colors (
+    favorite_id INTEGER NOT NULL REFERENCES favorites(id) ON DELETE CASCADE,
+    rank INTEGER NOT NULL,
+    r INTEGER NOT NULL,
+    g INTEGER NOT NULL,
+    b INTEGER NOT NULL,
+    hue REAL NOT NULL,
+    saturation REAL NOT NULL,
+    lightness REAL NOT NULL,
+    weight REAL NOT NULL,
+    PRIMARY KEY (favorite_id, rank)
+);
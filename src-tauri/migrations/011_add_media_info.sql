@@ -0,0 +1,10 @@
+-- Timing and looping probed from each favorite's file, for filters like
+-- "short loops under 2s". NULL until probed; existing favorites are filled
+-- in by a background pass on startup.
+-- loop_count: times the animation plays, 0 = forever; NULL for stills/video.
+
+ALTER TABLE favorites ADD COLUMN frame_count INTEGER;
+ALTER TABLE favorites ADD COLUMN duration_ms INTEGER;
+ALTER TABLE favorites ADD COLUMN fps REAL;
+ALTER TABLE favorites ADD COLUMN loop_count INTEGER;
+ALTER TABLE favorites ADD COLUMN has_alpha INTEGER;
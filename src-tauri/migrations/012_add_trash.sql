@@ -0,0 +1,7 @@
+-- Soft delete. Deleting a favorite stamps deleted_at and moves its files to
+-- the trash folder; every listing skips trashed rows. Restoring clears the
+-- stamp, and rows trashed longer than the retention period are purged.
+
+ALTER TABLE favorites ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_favorites_deleted_at ON favorites(deleted_at);
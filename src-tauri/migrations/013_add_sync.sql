@@ -0,0 +1,37 @@
+-- Folder sync between devices. Every favorite gets an id that's the same on
+-- all devices: Klipy favorites derive it from the Klipy id, so two devices
+-- saving the same GIF end up with one favorite; everything else gets a
+-- random one.
+
+ALTER TABLE favorites ADD COLUMN sync_id TEXT;
+
+UPDATE favorites
+SET sync_id = CASE
+    WHEN source IS NOT NULL AND source_id IS NOT NULL THEN source || ':' || source_id
+    ELSE lower(hex(randomblob(16)))
+END
+WHERE sync_id IS NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_favorites_sync_id ON favorites(sync_id);
+
+-- Each favorite as of the last sync: the synced fields with when and by
+-- which device they were last written (JSON), and the use count already
+-- accounted for. Local edits show up as differences from it.
+CREATE TABLE IF NOT EXISTS sync_shadow (
+    sync_id TEXT PRIMARY KEY,
+    fields TEXT NOT NULL,
+    use_count INTEGER NOT NULL DEFAULT 0
+);
+
+-- How far into each other device's change log this device has read.
+CREATE TABLE IF NOT EXISTS sync_cursors (
+    device_id TEXT PRIMARY KEY,
+    seq INTEGER NOT NULL
+);
+
+-- Favorites deleted for good, so a late create from another device doesn't
+-- bring them back.
+CREATE TABLE IF NOT EXISTS sync_tombstones (
+    sync_id TEXT PRIMARY KEY,
+    deleted_at TEXT NOT NULL
+);
@@ -0,0 +1,12 @@
+-- Named sets of settings to switch between (work, personal, ...). The live
+-- settings stay in the settings table; a profile holds its per-profile keys
+-- as a JSON object and is written back from the live settings when another
+-- profile is switched to. The active profile's name is the `active_profile`
+-- row of the settings table.
+CREATE TABLE IF NOT EXISTS settings_profiles (
+    name TEXT PRIMARY KEY,
+    settings TEXT NOT NULL,
+    -- Local search query the favorites view opens with, e.g. `tag:work`.
+    default_collection TEXT,
+    created_at TEXT NOT NULL
+);
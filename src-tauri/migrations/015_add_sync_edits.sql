@@ -0,0 +1,9 @@
+-- When each synced field of a favorite was last edited on this device. Sync
+-- stamps a local change with this rather than the time it was found, so the
+-- later of two devices' edits wins whichever of them syncs first.
+CREATE TABLE IF NOT EXISTS sync_edits (
+    favorite_id INTEGER NOT NULL REFERENCES favorites(id) ON DELETE CASCADE,
+    field TEXT NOT NULL,
+    edited_at TEXT NOT NULL,
+    PRIMARY KEY (favorite_id, field)
+);
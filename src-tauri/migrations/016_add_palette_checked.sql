@@ -0,0 +1,4 @@
+-- When the background pass last extracted a favorite's palette. Set even when
+-- nothing came out (undecodable or fully transparent files), so those are
+-- tried once rather than on every startup.
+ALTER TABLE favorites ADD COLUMN palette_checked_at TEXT;
@@ -0,0 +1,114 @@
+use crate::commands::{AppState, CommandError, CommandResult};
+use crate::models::BackupInfo;
+use crate::services::missing_media;
+use chrono::{Duration, Utc};
+use tauri::{AppHandle, Manager};
+
+/// How often to check whether a scheduled backup is due. The interval
+/// itself comes from settings, so changes apply without a restart.
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Backups on disk, newest first.
+#[tauri::command]
+pub async fn list_backups(state: tauri::State<'_, AppState>) -> CommandResult<Vec<BackupInfo>> {
+    Ok(state.backups.list()?.iter().map(BackupInfo::from).collect())
+}
+
+/// Back up now, then rotate like a scheduled backup would.
+#[tauri::command]
+pub async fn create_backup(state: tauri::State<'_, AppState>) -> CommandResult<BackupInfo> {
+    let info = state
+        .backups
+        .create(&state.db, state.downloader.media_dir())
+        .await?;
+    rotate(&state);
+    Ok(info)
+}
+
+/// Replace the database with a backup and restart. The backup is validated
+/// first, and the current database is backed up so the restore can itself
+/// be undone.
+#[tauri::command]
+pub async fn restore_backup(
+    id: String,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<()> {
+    let manifest = state
+        .backups
+        .validate(&id)
+        .await
+        .map_err(|e| CommandError::InvalidArgument(format!("{:#}", e)))?;
+
+    let missing = missing_media(&manifest, state.downloader.media_dir());
+    if !missing.is_empty() {
+        eprintln!(
+            "Warning: {} media file(s) from backup {} are no longer on disk",
+            missing.len(),
+            id
+        );
+    }
+
+    state
+        .backups
+        .create(&state.db, state.downloader.media_dir())
+        .await?;
+    state.backups.stage_restore(&id, state.db.path())?;
+
+    // The database is swapped in before it's opened again.
+    app.restart();
+}
+
+fn rotate(state: &AppState) {
+    let settings = state.settings();
+    if let Err(e) = state
+        .backups
+        .rotate(settings.backup_keep_count, settings.backup_max_age_days)
+    {
+        eprintln!("Warning: failed to rotate backups: {:#}", e);
+    }
+}
+
+/// Back up if the newest backup is older than `backup_interval_hours`.
+pub async fn run_scheduled_backup(state: &AppState) {
+    let interval_hours = state.settings().backup_interval_hours;
+    if interval_hours == 0 {
+        return;
+    }
+
+    let newest = match state.backups.list() {
+        Ok(backups) => backups.first().map(|b| b.created_at),
+        Err(e) => {
+            eprintln!("Warning: {:#}", e);
+            return;
+        }
+    };
+    let due = newest.is_none_or(|at| Utc::now() - at >= Duration::hours(interval_hours.into()));
+    if !due {
+        return;
+    }
+
+    match state
+        .backups
+        .create(&state.db, state.downloader.media_dir())
+        .await
+    {
+        Ok(info) => {
+            eprintln!("Backed up {} favorites as {}", info.favorite_count, info.id);
+            rotate(state);
+        }
+        Err(e) => eprintln!("Warning: scheduled backup failed: {:#}", e),
+    }
+}
+
+/// Run `run_scheduled_backup` at startup and every hour after. Never
+/// returns; spawn it.
+pub async fn back_up_periodically(app: AppHandle) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Some(state) = app.try_state::<AppState>() {
+            run_scheduled_backup(&state).await;
+        }
+    }
+}
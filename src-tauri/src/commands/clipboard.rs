@@ -1,6 +1,9 @@
-use crate::commands::{CommandError, CommandResult};
-use crate::services::ClipboardManager;
+use crate::commands::tray::refresh_tray_menu;
+use crate::commands::{AppState, CommandError, CommandResult};
+use crate::models::{Favorite, Settings};
+use crate::services::{AutoPaster, ClipboardManager};
 use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
 
 fn clipboard() -> CommandResult<ClipboardManager> {
     ClipboardManager::new().map_err(|e| CommandError::Clipboard(e.to_string()))
@@ -35,3 +38,66 @@ pub fn get_clipboard_text() -> CommandResult<String> {
         .get_text()
         .map_err(|e| CommandError::Clipboard(e.to_string()))
 }
+
+/// Copy the most recently used favorite straight to the clipboard, honoring
+/// the clipboard mode/format settings. Backs the "paste recent" hotkey, so it
+/// never touches the window.
+pub(crate) async fn copy_recent_favorite(app: &AppHandle) -> CommandResult<()> {
+    let state = app.state::<AppState>();
+    let Some(favorite) = state.db.favorites().get_most_recently_used().await? else {
+        return Ok(());
+    };
+
+    copy_favorite(app, &favorite).await?;
+    auto_paste_if_enabled(&state.settings()).await;
+    Ok(())
+}
+
+/// Copy a favorite by id without opening the window. Backs the tray's
+/// Recent/Pinned entries.
+pub(crate) async fn copy_favorite_by_id(app: &AppHandle, id: i64) -> CommandResult<()> {
+    let state = app.state::<AppState>();
+    let favorite = state
+        .db
+        .favorites()
+        .get_by_id(id)
+        .await?
+        .ok_or_else(|| CommandError::NotFound(format!("favorite {}", id)))?;
+
+    copy_favorite(app, &favorite).await
+}
+
+/// Copy per the clipboard settings and count it as a use, which reorders the
+/// tray's Recent submenu.
+async fn copy_favorite(app: &AppHandle, favorite: &Favorite) -> CommandResult<()> {
+    let state = app.state::<AppState>();
+    let settings = state.settings();
+    clipboard()?
+        .copy_favorite(favorite, &settings.clipboard_mode, &settings.clipboard_format)
+        .map_err(|e| CommandError::Clipboard(e.to_string()))?;
+
+    if let Some(id) = favorite.id {
+        state.db.favorites().increment_use_count(id).await?;
+        refresh_tray_menu(app);
+    }
+    Ok(())
+}
+
+/// Paste into the focused app if the user turned auto-paste on. Failures are
+/// logged rather than returned — the copy itself already succeeded.
+pub(crate) async fn auto_paste_if_enabled(settings: &Settings) {
+    if !settings.auto_paste {
+        return;
+    }
+
+    let paster = AutoPaster::new(
+        settings.auto_paste_delay_ms,
+        settings.auto_paste_command.clone(),
+        settings.auto_paste_denylist.clone(),
+    );
+    match paster.paste().await {
+        Ok(true) => {}
+        Ok(false) => eprintln!("Auto-paste skipped: focused app is denylisted or unknown"),
+        Err(e) => eprintln!("Auto-paste failed: {}", e),
+    }
+}
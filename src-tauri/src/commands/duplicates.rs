@@ -0,0 +1,147 @@
+use crate::commands::tray::refresh_tray_menu;
+use crate::commands::{AppState, CommandError, CommandResult};
+use crate::models::Favorite;
+use crate::services::{
+    best_quality, group_duplicates, perceptual_hash, Downloader, PerceptualHash,
+    DEFAULT_DUPLICATE_THRESHOLD,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+/// Favorites that look like the same reaction. `favorites[0]` is the copy
+/// `merge_duplicates` would keep by default.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub favorites: Vec<Favorite>,
+    /// How alike the least similar member is to the kept copy, 0–1.
+    pub similarity: f64,
+}
+
+/// Group near-identical favorites. `threshold` is the minimum similarity
+/// (0–1) to count as a duplicate. Favorites saved before hashing existed are
+/// hashed on the way, so the first run can take a while.
+#[tauri::command]
+pub async fn find_duplicates(
+    threshold: Option<f64>,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<Vec<DuplicateGroup>> {
+    let threshold = threshold.unwrap_or(DEFAULT_DUPLICATE_THRESHOLD);
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(CommandError::InvalidArgument(
+            "threshold must be between 0 and 1".into(),
+        ));
+    }
+
+    let favorites_db = state.db.favorites();
+    let mut favorites = favorites_db.get_all().await?;
+
+    for favorite in favorites.iter_mut().filter(|f| f.phash.is_none()) {
+        let (Some(id), Some(path)) = (favorite.id, favorite.filepath.as_deref()) else {
+            continue;
+        };
+        if let Some(hash) = perceptual_hash(PathBuf::from(path)).await {
+            favorites_db.set_phash(id, &hash.to_string()).await?;
+            favorite.phash = Some(hash.to_string());
+        }
+    }
+
+    let hashes: Vec<(i64, PerceptualHash)> = favorites
+        .iter()
+        .filter_map(|f| Some((f.id?, f.phash.as_deref()?.parse().ok()?)))
+        .collect();
+    let hash_of: HashMap<i64, PerceptualHash> = hashes.iter().copied().collect();
+    let mut by_id: HashMap<i64, Favorite> = favorites
+        .into_iter()
+        .filter_map(|f| Some((f.id?, f)))
+        .collect();
+
+    let groups = group_duplicates(&hashes, threshold)
+        .into_iter()
+        .map(|ids| {
+            let mut members: Vec<Favorite> = ids.iter().filter_map(|id| by_id.remove(id)).collect();
+            let keep = best_quality(&members).and_then(|f| f.id);
+            if let Some(index) = members.iter().position(|f| f.id == keep) {
+                members.swap(0, index);
+            }
+
+            let kept_hash = keep.and_then(|id| hash_of.get(&id));
+            let similarity = members[1..]
+                .iter()
+                .filter_map(|f| Some(kept_hash?.similarity(hash_of.get(&f.id?)?)))
+                .fold(1.0, f64::min);
+
+            DuplicateGroup {
+                favorites: members,
+                similarity,
+            }
+        })
+        .collect();
+
+    Ok(groups)
+}
+
+/// Merge duplicates into one favorite, keeping `keep_id` or, if omitted, the
+/// best-quality copy. Tags and use counts are combined and the other copies
+/// deleted along with any files the kept one doesn't share.
+#[tauri::command]
+pub async fn merge_duplicates(
+    ids: Vec<i64>,
+    keep_id: Option<i64>,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<Favorite> {
+    let favorites_db = state.db.favorites();
+
+    let mut favorites = Vec::with_capacity(ids.len());
+    for &id in &ids {
+        let favorite = favorites_db
+            .get_by_id(id)
+            .await?
+            .ok_or_else(|| CommandError::NotFound(format!("favorite {}", id)))?;
+        if !favorites.iter().any(|f: &Favorite| f.id == favorite.id) {
+            favorites.push(favorite);
+        }
+    }
+    if favorites.len() < 2 {
+        return Err(CommandError::InvalidArgument(
+            "need at least two favorites to merge".into(),
+        ));
+    }
+
+    let keep = match keep_id {
+        Some(id) => favorites.iter().find(|f| f.id == Some(id)).ok_or_else(|| {
+            CommandError::InvalidArgument(format!("favorite {} is not among those merged", id))
+        })?,
+        None => best_quality(&favorites).expect("at least two favorites"),
+    };
+    let keep_id = keep.id.expect("loaded favorites have ids");
+    let kept_files = [keep.filepath.clone(), keep.mp4_filepath.clone()];
+
+    let others: Vec<i64> = favorites
+        .iter()
+        .filter_map(|f| f.id)
+        .filter(|&id| id != keep_id)
+        .collect();
+    let merged = favorites_db.merge(keep_id, &others).await?;
+
+    // Best-effort, like delete_favorite: the rows are already gone.
+    for favorite in favorites.iter().filter(|f| f.id != Some(keep_id)) {
+        for path in [&favorite.filepath, &favorite.mp4_filepath]
+            .into_iter()
+            .flatten()
+            .filter(|path| !kept_files.iter().flatten().any(|kept| kept == *path))
+        {
+            let path = PathBuf::from(path);
+            if path.exists() {
+                if let Err(e) = Downloader::delete_file(&path).await {
+                    eprintln!("Warning: {:#}", e);
+                }
+            }
+        }
+    }
+
+    refresh_tray_menu(&app);
+    Ok(merged)
+}
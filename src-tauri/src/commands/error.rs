@@ -1,3 +1,4 @@
+use crate::db::QueryError;
 use serde::{Serialize, Serializer};
 use thiserror::Error;
 
@@ -14,6 +15,11 @@ pub enum CommandError {
     #[error("invalid argument: {0}")]
     InvalidArgument(String),
 
+    /// A search query that didn't parse; `position` is the char offset of
+    /// the offending term.
+    #[error("invalid query at position {position}: {message}")]
+    InvalidQuery { message: String, position: usize },
+
     #[error("not found: {0}")]
     NotFound(String),
 
@@ -23,6 +29,9 @@ pub enum CommandError {
     #[error("clipboard error: {0}")]
     Clipboard(String),
 
+    #[error("autostart error: {0}")]
+    Autostart(String),
+
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -36,4 +45,13 @@ impl Serialize for CommandError {
     }
 }
 
+impl From<QueryError> for CommandError {
+    fn from(err: QueryError) -> Self {
+        CommandError::InvalidQuery {
+            message: err.message,
+            position: err.position,
+        }
+    }
+}
+
 pub type CommandResult<T> = Result<T, CommandError>;
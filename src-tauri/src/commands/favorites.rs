@@ -1,7 +1,10 @@
+use crate::commands::tray::{refresh_tray_menu, set_tray_status};
 use crate::commands::{AppState, CommandError, CommandResult};
 use crate::models::{Favorite, MediaType, Source};
-use crate::services::Downloader;
-use image::GenericImageView;
+use crate::services::{
+    derive_tags, dominant_colors, media_info, perceptual_hash, Downloader, KlipyOrigin,
+};
+use tauri::AppHandle;
 
 #[tauri::command]
 pub async fn get_all_favorites(state: tauri::State<'_, AppState>) -> CommandResult<Vec<Favorite>> {
@@ -19,53 +22,171 @@ pub async fn get_favorite_by_id(
 #[tauri::command]
 pub async fn add_favorite(
     favorite: Favorite,
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> CommandResult<i64> {
-    Ok(state.db.favorites().create(&favorite).await?)
+    let mut favorite = favorite;
+    if let Some(path) = favorite.filepath.as_deref() {
+        if favorite.phash.is_none() {
+            favorite.phash = perceptual_hash(path.into()).await.map(|h| h.to_string());
+        }
+        if favorite.palette.is_empty() {
+            favorite.palette = dominant_colors(path.into()).await;
+        }
+        if favorite.frame_count.is_none() {
+            if let Some(info) = media_info(path.into()).await {
+                favorite = favorite.with_media_info(&info);
+            }
+        }
+    }
+    let id = state.db.favorites().create(&favorite).await?;
+    refresh_tray_menu(&app);
+    Ok(id)
+}
+
+/// Extract palettes for favorites saved before colors were tracked. Run once
+/// in the background at startup; each favorite is stored as it's done, so an
+/// interrupted run picks up where it stopped. An empty palette is stored too,
+/// so a file that yields none isn't decoded again next launch.
+pub async fn backfill_palettes(state: &AppState) {
+    let favorites = state.db.favorites();
+    let missing = match favorites.missing_palettes().await {
+        Ok(missing) => missing,
+        Err(e) => {
+            eprintln!("Warning: {:#}", e);
+            return;
+        }
+    };
+    for (id, path) in missing {
+        let palette = dominant_colors(path.into()).await;
+        if let Err(e) = favorites.set_palette(id, &palette).await {
+            eprintln!("Warning: {:#}", e);
+        }
+    }
+}
+
+/// Probe favorites saved before timing and looping were recorded. Like
+/// `backfill_palettes`, run once in the background at startup.
+pub async fn backfill_media_info(state: &AppState) {
+    let favorites = state.db.favorites();
+    let missing = match favorites.missing_media_info().await {
+        Ok(missing) => missing,
+        Err(e) => {
+            eprintln!("Warning: {:#}", e);
+            return;
+        }
+    };
+    for (id, path) in missing {
+        let Some(info) = media_info(path.into()).await else {
+            continue;
+        };
+        if let Err(e) = favorites.set_media_info(id, &info).await {
+            eprintln!("Warning: {:#}", e);
+        }
+    }
 }
 
 #[tauri::command]
 pub async fn update_favorite(
     favorite: Favorite,
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> CommandResult<()> {
     state.db.favorites().update(&favorite).await?;
+    refresh_tray_menu(&app);
     Ok(())
 }
 
+/// Move a favorite and its files to the trash. `restore_favorite` undoes
+/// it; see `commands::trash` for emptying and the retention period.
 #[tauri::command]
-pub async fn delete_favorite(id: i64, state: tauri::State<'_, AppState>) -> CommandResult<()> {
+pub async fn delete_favorite(
+    id: i64,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<()> {
     let favorites = state.db.favorites();
+    let favorite = favorites
+        .get_by_id(id)
+        .await?
+        .ok_or_else(|| CommandError::NotFound(format!("favorite {}", id)))?;
+    if favorite.deleted_at.is_some() {
+        return Err(CommandError::InvalidArgument(format!(
+            "favorite {} is already in the trash",
+            id
+        )));
+    }
 
-    // Best-effort: delete files we own before removing the row.
-    if let Some(favorite) = favorites.get_by_id(id).await? {
-        for path in [favorite.filepath.as_deref(), favorite.mp4_filepath.as_deref()]
-            .into_iter()
-            .flatten()
-        {
-            let path = std::path::PathBuf::from(path);
-            if path.exists() {
-                Downloader::delete_file(&path).await?;
+    // Best-effort: a file that won't move stays where it is, and restoring
+    // leaves it there.
+    for path in [
+        favorite.filepath.as_deref(),
+        favorite.mp4_filepath.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        let path = std::path::PathBuf::from(path);
+        if path.exists() {
+            if let Err(e) = state.downloader.move_to_trash(id, &path).await {
+                eprintln!("Warning: {:#}", e);
             }
         }
     }
 
-    favorites.delete(id).await?;
+    favorites.trash(id).await?;
+    refresh_tray_menu(&app);
     Ok(())
 }
 
 #[tauri::command]
 pub async fn increment_use_count(
     id: i64,
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> CommandResult<()> {
     state.db.favorites().increment_use_count(id).await?;
+    refresh_tray_menu(&app);
+    Ok(())
+}
+
+/// Pin or unpin a favorite. Pinned favorites list before everything else and
+/// get their own tray submenu.
+#[tauri::command]
+pub async fn set_favorite_pinned(
+    id: i64,
+    pinned: bool,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<()> {
+    state.db.favorites().set_pinned(id, pinned).await?;
+    refresh_tray_menu(&app);
+    Ok(())
+}
+
+/// Reorder: put `id` directly before `before_id`, or last when `before_id` is
+/// omitted. Matches what a drop target knows — the tile it landed on.
+#[tauri::command]
+pub async fn move_favorite(
+    id: i64,
+    before_id: Option<i64>,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<()> {
+    if before_id == Some(id) {
+        return Err(CommandError::InvalidArgument(
+            "cannot move a favorite before itself".into(),
+        ));
+    }
+    state.db.favorites().move_before(id, before_id).await?;
+    refresh_tray_menu(&app);
     Ok(())
 }
 
 #[tauri::command]
 pub async fn import_local_file(
     file_path: String,
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> CommandResult<Favorite> {
     let source_path = std::path::PathBuf::from(&file_path);
@@ -96,23 +217,25 @@ pub async fn import_local_file(
         .ok()
         .map(|s| s as i64);
 
-    let dimensions = image::open(&dest_path)
-        .ok()
-        .map(|img| img.dimensions());
-
     let mut favorite = Favorite::new(
         filename,
         Some(dest_path.to_string_lossy().into_owned()),
         media_type,
     );
 
-    if let Some((w, h)) = dimensions {
-        favorite = favorite.with_dimensions(w as i32, h as i32);
+    // Also gives videos their dimensions, which decoding as an image can't.
+    if let Some(info) = media_info(dest_path.clone()).await {
+        favorite = favorite.with_media_info(&info);
     }
     favorite.file_size = file_size;
+    favorite.phash = perceptual_hash(dest_path.clone())
+        .await
+        .map(|h| h.to_string());
+    favorite.palette = dominant_colors(dest_path).await;
 
     let id = state.db.favorites().create(&favorite).await?;
     favorite.id = Some(id);
+    refresh_tray_menu(&app);
     Ok(favorite)
 }
 
@@ -120,6 +243,12 @@ pub async fn import_local_file(
 ///
 /// Replaces the old `add_klipy_favorite` + `download_klipy_gif` pair, which
 /// were near-duplicates with different argument shapes.
+///
+/// `query` and `category` say where the GIF was found; together with the
+/// slug and title they seed the favorite's tags.
+///
+/// Stickers, clips and memes come through here too, with the `media_type`
+/// of their search result; a clip's `mp4_url` is the video with sound.
 #[tauri::command]
 pub async fn add_klipy_favorite(
     gif_url: String,
@@ -129,12 +258,27 @@ pub async fn add_klipy_favorite(
     title: String,
     width: i32,
     height: i32,
+    query: Option<String>,
+    category: Option<String>,
+    media_type: Option<MediaType>,
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> CommandResult<Favorite> {
-    let (gif_path, mp4_path) = state
+    set_tray_status(&app, Some(&format!("Downloading \"{}\"…", title)));
+    let downloaded = state
         .downloader
         .download_from_klipy(&gif_url, mp4_url.as_deref(), &source_id)
-        .await?;
+        .await;
+    let (gif_path, mp4_path) = match downloaded {
+        Ok(paths) => {
+            set_tray_status(&app, None);
+            paths
+        }
+        Err(e) => {
+            set_tray_status(&app, Some(&format!("Download failed: {}", e)));
+            return Err(e.into());
+        }
+    };
 
     let filename = gif_path
         .file_name()
@@ -147,20 +291,38 @@ pub async fn add_klipy_favorite(
         .ok()
         .map(|s| s as i64);
 
+    let query = query.filter(|q| !q.trim().is_empty());
+    let tags = derive_tags(&KlipyOrigin {
+        query: query.as_deref(),
+        category: category.as_deref(),
+        slug: &source_id,
+        title: &title,
+    });
+
     let mut favorite = Favorite::new(
         filename,
         Some(gif_path.to_string_lossy().into_owned()),
-        MediaType::Gif,
+        media_type.unwrap_or(MediaType::Gif),
     )
     .with_gif_url(gif_url)
     .with_dimensions(width, height)
-    .with_source(Source::Klipy, Some(source_id), source_url);
+    .with_source(Source::Klipy, Some(source_id), source_url)
+    .with_tags(tags);
 
     favorite.mp4_filepath = mp4_path.map(|p| p.to_string_lossy().into_owned());
     favorite.file_size = file_size;
     favorite.description = Some(title);
+    favorite.source_query = query.map(|q| q.trim().to_string());
+    if let Some(info) = media_info(gif_path.clone()).await {
+        favorite = favorite.with_media_info(&info);
+    }
+    favorite.phash = perceptual_hash(gif_path.clone())
+        .await
+        .map(|h| h.to_string());
+    favorite.palette = dominant_colors(gif_path).await;
 
     let id = state.db.favorites().create(&favorite).await?;
     favorite.id = Some(id);
+    refresh_tray_menu(&app);
     Ok(favorite)
 }
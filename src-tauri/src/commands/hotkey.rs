@@ -1,30 +1,38 @@
-use crate::commands::{CommandError, CommandResult};
+use crate::commands::{
+    activate_next_profile, copy_recent_favorite, place_window, AppState, CommandError,
+    CommandResult,
+};
+use crate::models::HotkeyAction;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
 
+/// Outcome of registering one action's shortcut. Returned per binding so the
+/// settings UI can flag exactly which one failed instead of all-or-nothing.
+#[derive(Debug, Serialize)]
+pub struct HotkeyStatus {
+    pub action: HotkeyAction,
+    pub hotkey: String,
+    /// `None` when the shortcut is live; otherwise why it isn't.
+    pub error: Option<String>,
+}
+
 fn parse_shortcut(hotkey: &str) -> CommandResult<Shortcut> {
     hotkey
         .parse::<Shortcut>()
         .map_err(|e| CommandError::Hotkey(format!("invalid hotkey '{}': {}", hotkey, e)))
 }
 
+/// Replace every registered shortcut with `hotkeys`. Each binding is attempted
+/// independently: a bad or conflicting one is reported in its status and the
+/// rest still register.
 #[tauri::command]
-pub async fn register_hotkey(app: AppHandle, hotkey: String) -> CommandResult<()> {
-    // Replace any previously-registered shortcut so duplicate registrations don't error.
-    let _ = unregister_all_hotkeys(app.clone()).await;
-
-    let shortcut = parse_shortcut(&hotkey)?;
-
-    app.global_shortcut()
-        .on_shortcut(shortcut, |app, _shortcut, event| {
-            if event.state != ShortcutState::Pressed {
-                return;
-            }
-            toggle_main_window(app);
-        })
-        .map_err(|e| CommandError::Hotkey(e.to_string()))?;
-
-    Ok(())
+pub async fn register_hotkeys(
+    app: AppHandle,
+    hotkeys: BTreeMap<HotkeyAction, String>,
+) -> CommandResult<Vec<HotkeyStatus>> {
+    Ok(register_all(&app, &hotkeys))
 }
 
 #[tauri::command]
@@ -50,6 +58,93 @@ pub async fn is_hotkey_registered(app: AppHandle, hotkey: String) -> CommandResu
     Ok(app.global_shortcut().is_registered(shortcut))
 }
 
+pub(crate) fn register_all(
+    app: &AppHandle,
+    hotkeys: &BTreeMap<HotkeyAction, String>,
+) -> Vec<HotkeyStatus> {
+    // Start from a clean slate so re-registering the same set doesn't error.
+    let _ = app.global_shortcut().unregister_all();
+
+    // Paused from the tray: leave everything released until resumed.
+    if app
+        .try_state::<AppState>()
+        .is_some_and(|state| state.hotkeys_paused())
+    {
+        return Vec::new();
+    }
+
+    let mut claimed: HashMap<Shortcut, HotkeyAction> = HashMap::new();
+    let mut statuses = Vec::new();
+
+    for (&action, hotkey) in hotkeys {
+        let hotkey = hotkey.trim();
+        if hotkey.is_empty() {
+            continue;
+        }
+
+        let result = parse_shortcut(hotkey).and_then(|shortcut| {
+            if let Some(other) = claimed.get(&shortcut) {
+                return Err(CommandError::Hotkey(format!(
+                    "'{}' is already bound to {}",
+                    hotkey, other
+                )));
+            }
+            bind(app, shortcut, action)?;
+            claimed.insert(shortcut, action);
+            Ok(())
+        });
+
+        statuses.push(HotkeyStatus {
+            action,
+            hotkey: hotkey.to_string(),
+            error: result.err().map(|e| e.to_string()),
+        });
+    }
+
+    statuses
+}
+
+fn bind(app: &AppHandle, shortcut: Shortcut, action: HotkeyAction) -> CommandResult<()> {
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _shortcut, event| {
+            if event.state == ShortcutState::Pressed {
+                run_action(app, action);
+            }
+        })
+        .map_err(|e| CommandError::Hotkey(e.to_string()))
+}
+
+/// Dispatch a hotkey press. Runs on the main thread, so anything touching the
+/// database is spawned off.
+pub(crate) fn run_action(app: &AppHandle, action: HotkeyAction) {
+    match action {
+        HotkeyAction::TogglePicker => toggle_main_window(app),
+        HotkeyAction::OpenFavorites => open_view(app, "favorites"),
+        HotkeyAction::OpenTrending => open_view(app, "trending"),
+        HotkeyAction::PasteRecent => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = copy_recent_favorite(&app).await {
+                    eprintln!("Failed to copy recent favorite: {}", e);
+                }
+            });
+        }
+        HotkeyAction::NextProfile => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move { activate_next_profile(&app).await });
+        }
+    }
+}
+
+/// Show the picker on a specific view. The view is announced *before* the
+/// window shows so the frontend's on-focus reset lands on it instead of home.
+fn open_view(app: &AppHandle, view: &str) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.emit("open-view", view);
+    }
+    show_main_window(app);
+}
+
 /// Toggle the main window: hide-if-visible / show-if-hidden, mirroring the
 /// tray-icon click behavior. Used by the global hotkey handler.
 pub(crate) fn toggle_main_window(app: &AppHandle) {
@@ -61,12 +156,27 @@ pub(crate) fn toggle_main_window(app: &AppHandle) {
         let _ = window.hide();
         deactivate_app();
     } else {
-        let _ = window.show();
-        let _ = window.set_focus();
-        let _ = window.center();
-        let _ = window.emit("clear-search", ());
-        let _ = window.emit("focus-search", ());
+        show_main_window(app);
+    }
+}
+
+/// Place the main window per the user's placement setting, then show and
+/// focus it with a fresh search.
+pub(crate) fn show_main_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+
+    if let Some(state) = app.try_state::<AppState>() {
+        if let Err(e) = place_window(&window, &state.settings().window_placement) {
+            eprintln!("Failed to position window: {}", e);
+        }
     }
+
+    let _ = window.show();
+    let _ = window.set_focus();
+    let _ = window.emit("clear-search", ());
+    let _ = window.emit("focus-search", ());
 }
 
 #[cfg(target_os = "macos")]
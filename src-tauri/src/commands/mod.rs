@@ -1,21 +1,34 @@
+pub mod backup;
 pub mod clipboard;
+pub mod duplicates;
 pub mod error;
 pub mod favorites;
 pub mod files;
 pub mod hotkey;
+pub mod profiles;
 pub mod search;
 pub mod settings;
 pub mod state;
+pub mod sync;
 pub mod system;
+pub mod tags;
+pub mod trash;
+pub mod tray;
 pub mod window;
 
+pub use backup::*;
 pub use clipboard::*;
+pub use duplicates::*;
 pub use error::*;
 pub use favorites::*;
 pub use files::*;
 pub use hotkey::*;
+pub use profiles::*;
 pub use search::*;
 pub use settings::*;
 pub use state::*;
+pub use sync::*;
 pub use system::*;
+pub use tags::*;
+pub use trash::*;
 pub use window::*;
@@ -0,0 +1,176 @@
+use crate::commands::settings::{apply_changes, SETTINGS_LOCK};
+use crate::commands::tray::{refresh_tray_menu, set_tray_status};
+use crate::commands::{AppState, CommandError, CommandResult};
+use crate::db::SearchQuery;
+use crate::models::SettingsProfile;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Profile names show in the tray menu; keep them to a label's length.
+const MAX_NAME_CHARS: usize = 40;
+
+#[tauri::command]
+pub async fn list_profiles(
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<Vec<SettingsProfile>> {
+    Ok(state.db.settings().profiles().await?)
+}
+
+/// Save the current settings as a new profile. The first profile becomes
+/// the active one.
+#[tauri::command]
+pub async fn create_profile(
+    app: AppHandle,
+    name: String,
+    default_collection: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<SettingsProfile> {
+    let name = profile_name(&name)?;
+    let default_collection = collection(default_collection)?;
+
+    let _guard = SETTINGS_LOCK.lock().await;
+    let settings_db = state.db.settings();
+    let settings = settings_db.get().await?;
+    if !settings_db
+        .create_profile(&name, default_collection.as_deref(), &settings)
+        .await?
+    {
+        return Err(CommandError::InvalidArgument(format!(
+            "a profile named '{}' already exists",
+            name
+        )));
+    }
+
+    refresh_tray_menu(&app);
+    find_profile(&state, &name).await
+}
+
+/// Set or clear the search the favorites view opens with under `name`.
+#[tauri::command]
+pub async fn set_profile_collection(
+    app: AppHandle,
+    name: String,
+    default_collection: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<SettingsProfile> {
+    let default_collection = collection(default_collection)?;
+    if !state
+        .db
+        .settings()
+        .set_profile_collection(&name, default_collection.as_deref())
+        .await?
+    {
+        return Err(CommandError::NotFound(format!("profile '{}'", name)));
+    }
+
+    let profile = find_profile(&state, &name).await?;
+    if profile.active {
+        app.emit("profile-changed", &profile)?;
+    }
+    Ok(profile)
+}
+
+/// Delete a profile. The live settings stay as they are.
+#[tauri::command]
+pub async fn delete_profile(
+    app: AppHandle,
+    name: String,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<()> {
+    let _guard = SETTINGS_LOCK.lock().await;
+    let settings_db = state.db.settings();
+    let was_active = settings_db.active_profile().await?.as_deref() == Some(name.as_str());
+    if !settings_db.delete_profile(&name).await? {
+        return Err(CommandError::NotFound(format!("profile '{}'", name)));
+    }
+    if was_active {
+        app.emit("profile-changed", None::<SettingsProfile>)?;
+    }
+    refresh_tray_menu(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn switch_profile(app: AppHandle, name: String) -> CommandResult<SettingsProfile> {
+    activate_profile(&app, &name).await
+}
+
+/// Make `name` the active profile and apply its settings to the running app:
+/// window flags, tray, hotkeys, then `profile-changed` and `settings-changed`.
+pub(crate) async fn activate_profile(
+    app: &AppHandle,
+    name: &str,
+) -> CommandResult<SettingsProfile> {
+    let state = app.state::<AppState>();
+    let _guard = SETTINGS_LOCK.lock().await;
+    if state.db.settings().switch_profile(name).await?.is_none() {
+        return Err(CommandError::NotFound(format!("profile '{}'", name)));
+    }
+
+    let settings = state.config_file.effective_settings(&state.db).await?;
+    let profile = find_profile(&state, name).await?;
+    app.emit("profile-changed", &profile)?;
+    apply_changes(app, &state.settings(), &settings)?;
+    Ok(profile)
+}
+
+/// Switch to the profile after the active one, wrapping around. Does
+/// nothing without profiles. For the hotkey.
+pub(crate) async fn activate_next_profile(app: &AppHandle) {
+    let profiles = match app.state::<AppState>().db.settings().profiles().await {
+        Ok(profiles) if !profiles.is_empty() => profiles,
+        Ok(_) => return,
+        Err(e) => {
+            eprintln!("Failed to list profiles: {}", e);
+            return;
+        }
+    };
+    let next = profiles
+        .iter()
+        .position(|profile| profile.active)
+        .map_or(0, |active| (active + 1) % profiles.len());
+
+    match activate_profile(app, &profiles[next].name).await {
+        Ok(profile) => set_tray_status(app, Some(&format!("Profile: {}", profile.name))),
+        Err(e) => {
+            eprintln!("Failed to switch profile: {}", e);
+            set_tray_status(app, Some("Profile switch failed"));
+        }
+    }
+}
+
+async fn find_profile(state: &AppState, name: &str) -> CommandResult<SettingsProfile> {
+    state
+        .db
+        .settings()
+        .profiles()
+        .await?
+        .into_iter()
+        .find(|profile| profile.name == name)
+        .ok_or_else(|| CommandError::NotFound(format!("profile '{}'", name)))
+}
+
+fn profile_name(name: &str) -> CommandResult<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(CommandError::InvalidArgument(
+            "profile name is empty".to_string(),
+        ));
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(CommandError::InvalidArgument(format!(
+            "profile names are at most {} characters",
+            MAX_NAME_CHARS
+        )));
+    }
+    Ok(name.to_string())
+}
+
+/// A default collection is a local search; blank means none.
+fn collection(query: Option<String>) -> CommandResult<Option<String>> {
+    let query = query.as_deref().map(str::trim).unwrap_or_default();
+    if query.is_empty() {
+        return Ok(None);
+    }
+    query.parse::<SearchQuery>()?;
+    Ok(Some(query.to_string()))
+}
@@ -1,7 +1,16 @@
-use crate::commands::{AppState, CommandResult};
-use crate::models::Favorite;
-use crate::services::klipy::{KlipyAd, KlipyGif, KlipyItem};
+use crate::commands::{AppState, CommandError, CommandResult};
+use crate::db::SearchQuery;
+use crate::models::{Favorite, MediaType, Source};
+use crate::services::klipy::{
+    KlipyAd, KlipyCategoriesResponse, KlipyClip, KlipyContentType, KlipyGif, KlipyItem, KlipyMeme,
+    KlipySearchResponse, KlipySticker,
+};
+use crate::services::{perceptual_hash, similar, ContentContext, ContentDenylist};
 use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+/// How many results `find_similar` returns from each source by default.
+const DEFAULT_SIMILAR_LIMIT: u32 = 30;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SearchResult {
@@ -19,7 +28,8 @@ pub struct KlipySearchResults {
 }
 
 /// Discriminated union over a `kind` tag — `kind` (not `type`) to avoid the
-/// JS reserved-word issue and to match TS convention.
+/// JS reserved-word issue and to match TS convention. Every content type
+/// comes through as `gif`; its `content_type` says which it is.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "kind", rename_all = "lowercase")]
 pub enum KlipyResultItem {
@@ -33,10 +43,17 @@ pub struct KlipyGifResult {
     pub slug: String,
     pub title: String,
     pub url: String,
+    /// The file saved and copied: the GIF, a sticker's transparent WebP or
+    /// PNG, a meme's image, or a clip's silent preview.
     pub gif_url: String,
+    /// For clips, the video with sound.
     pub mp4_url: Option<String>,
     pub width: u32,
     pub height: u32,
+    pub content_type: KlipyContentType,
+    /// What a favorite saved from this result holds.
+    pub media_type: MediaType,
+    pub has_audio: bool,
 }
 
 /// Ad item passed through to the frontend. `content` is a full HTML document
@@ -73,15 +90,86 @@ impl From<KlipyGif> for KlipyGifResult {
             id: gif.id.to_string(),
             slug: gif.slug.clone(),
             title: gif.title,
-            url: format!("https://klipy.com/gifs/{}", gif.slug),
+            url: KlipyContentType::Gif.page_url(&gif.slug),
             gif_url: hd.gif.url.clone(),
             mp4_url: hd.mp4.as_ref().map(|m| m.url.clone()),
             width: md.gif.width,
             height: md.gif.height,
+            content_type: KlipyContentType::Gif,
+            media_type: KlipyContentType::Gif.media_type(),
+            has_audio: false,
+        }
+    }
+}
+
+impl From<KlipySticker> for KlipyGifResult {
+    fn from(sticker: KlipySticker) -> Self {
+        // WebP keeps both the animation and the alpha channel; PNG keeps the
+        // alpha. Any MP4 would flatten it, so none is offered.
+        let hd = &sticker.file.hd;
+        let md = &sticker.file.md;
+        let file = hd.webp.as_ref().or(hd.png.as_ref()).unwrap_or(&hd.gif);
+        KlipyGifResult {
+            id: sticker.id.to_string(),
+            slug: sticker.slug.clone(),
+            title: sticker.title,
+            url: KlipyContentType::Sticker.page_url(&sticker.slug),
+            gif_url: file.url.clone(),
+            mp4_url: None,
+            width: md.gif.width,
+            height: md.gif.height,
+            content_type: KlipyContentType::Sticker,
+            media_type: KlipyContentType::Sticker.media_type(),
+            has_audio: false,
         }
     }
 }
 
+impl From<KlipyClip> for KlipyGifResult {
+    fn from(clip: KlipyClip) -> Self {
+        let files = &clip.file;
+        let preview = files
+            .gif
+            .as_ref()
+            .or(files.webp.as_ref())
+            .or(files.jpg.as_ref())
+            .unwrap_or(&files.mp4);
+        KlipyGifResult {
+            id: clip.id.to_string(),
+            slug: clip.slug.clone(),
+            title: clip.title,
+            url: KlipyContentType::Clip.page_url(&clip.slug),
+            gif_url: preview.url.clone(),
+            mp4_url: Some(files.mp4.url.clone()),
+            width: files.mp4.width,
+            height: files.mp4.height,
+            content_type: KlipyContentType::Clip,
+            media_type: KlipyContentType::Clip.media_type(),
+            has_audio: true,
+        }
+    }
+}
+
+/// `None` for a meme without any image to show.
+fn meme_result(meme: KlipyMeme) -> Option<KlipyGifResult> {
+    let formats = &meme.file;
+    let file = formats.hd.best().or(formats.md.best())?;
+    let shown = formats.md.best().unwrap_or(file);
+    Some(KlipyGifResult {
+        id: meme.id.to_string(),
+        slug: meme.slug.clone(),
+        url: KlipyContentType::Meme.page_url(&meme.slug),
+        gif_url: file.url.clone(),
+        mp4_url: None,
+        width: shown.width,
+        height: shown.height,
+        title: meme.title,
+        content_type: KlipyContentType::Meme,
+        media_type: KlipyContentType::Meme.media_type(),
+        has_audio: false,
+    })
+}
+
 impl From<KlipyAd> for KlipyAdResult {
     fn from(ad: KlipyAd) -> Self {
         KlipyAdResult {
@@ -92,40 +180,89 @@ impl From<KlipyAd> for KlipyAdResult {
     }
 }
 
-fn map_items(items: Vec<KlipyItem>) -> Vec<KlipyResultItem> {
+/// Frontend items, without unknown kinds and items on the content denylist.
+fn map_items(items: Vec<KlipyItem>, denylist: &ContentDenylist) -> Vec<KlipyResultItem> {
     items
         .into_iter()
+        .filter(|item| !denylist.denies_item(item))
         .filter_map(|item| match item {
             KlipyItem::Gif(gif) => Some(KlipyResultItem::Gif(gif.into())),
+            KlipyItem::Sticker(sticker) => Some(KlipyResultItem::Gif(sticker.into())),
+            KlipyItem::Clip(clip) => Some(KlipyResultItem::Gif(clip.into())),
+            KlipyItem::Meme(meme) => meme_result(meme).map(KlipyResultItem::Gif),
             KlipyItem::Ad(ad) => Some(KlipyResultItem::Ad(ad.into())),
             KlipyItem::Unknown => None,
         })
         .collect()
 }
 
+/// Run `fetch` in the chosen locale, and again in English when that comes
+/// back empty.
+async fn with_english_fallback<T, F, Fut>(
+    content: ContentContext,
+    is_empty: impl Fn(&T) -> bool,
+    fetch: F,
+) -> anyhow::Result<T>
+where
+    F: Fn(ContentContext) -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let fallback = content.english_fallback();
+    let result = fetch(content).await?;
+    match fallback {
+        Some(english) if is_empty(&result) => fetch(english).await,
+        _ => Ok(result),
+    }
+}
+
+/// No content at all, as opposed to a page past the last one.
+fn has_no_content(response: &KlipySearchResponse) -> bool {
+    response.data.total.unwrap_or(0) == 0
+        && !response
+            .data
+            .data
+            .iter()
+            .any(|item| item.labels().is_some())
+}
+
+/// Search favorites with the filter syntax described on [`SearchQuery`],
+/// e.g. `tag:cat -tag:nsfw used:>5 "exact phrase"`.
 #[tauri::command]
 pub async fn search_local(
     query: String,
     state: tauri::State<'_, AppState>,
 ) -> CommandResult<Vec<Favorite>> {
-    Ok(state.db.favorites().search(&query).await?)
+    let query: SearchQuery = query.parse()?;
+    Ok(state.db.favorites().query(&query).await?)
 }
 
+/// Search Klipy for `content_type`; GIFs when it's not given.
 #[tauri::command]
 pub async fn search_klipy(
     query: String,
     limit: u32,
     page: u32,
     show_ads: bool,
+    content_type: Option<KlipyContentType>,
     state: tauri::State<'_, AppState>,
 ) -> CommandResult<KlipySearchResults> {
-    let response = state
-        .klipy(show_ads)
-        .search(&query, limit, page, state.ad_context_for(show_ads))
-        .await?;
+    let content_type = content_type.unwrap_or_default();
+    let klipy = state.klipy(show_ads);
+    let ad_context = state.ad_context_for(show_ads);
+    let query = query.as_str();
+    let response = with_english_fallback(
+        state.content_context(),
+        has_no_content,
+        |content| async move {
+            klipy
+                .search(content_type, query, limit, page, &content, ad_context)
+                .await
+        },
+    )
+    .await?;
 
     Ok(KlipySearchResults {
-        items: map_items(response.data.data),
+        items: map_items(response.data.data, &state.content_denylist()),
         total_count: response.data.total.unwrap_or(0),
         page: response.data.current_page.unwrap_or(page),
     })
@@ -137,28 +274,105 @@ pub async fn search_combined(
     klipy_limit: u32,
     klipy_page: u32,
     show_ads: bool,
+    content_type: Option<KlipyContentType>,
     state: tauri::State<'_, AppState>,
 ) -> CommandResult<SearchResult> {
     let local = state.db.favorites().search(&query).await?;
-    let klipy = search_klipy(query, klipy_limit, klipy_page, show_ads, state)
-        .await
-        .ok();
+    let klipy = search_klipy(
+        query,
+        klipy_limit,
+        klipy_page,
+        show_ads,
+        content_type,
+        state,
+    )
+    .await
+    .ok();
+    Ok(SearchResult { local, klipy })
+}
+
+/// "More like this": favorites that look alike or share tags with
+/// `favorite_id`, plus — for Klipy favorites — a provider search seeded from
+/// its title and tags, for `content_type` (GIFs when it's not given). Same
+/// shape as `search_combined` so the grid can show it.
+#[tauri::command]
+pub async fn find_similar(
+    favorite_id: i64,
+    limit: Option<u32>,
+    show_ads: bool,
+    content_type: Option<KlipyContentType>,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<SearchResult> {
+    let limit = limit.unwrap_or(DEFAULT_SIMILAR_LIMIT);
+    let favorites = state.db.favorites();
+    let mut target = favorites
+        .get_by_id(favorite_id)
+        .await?
+        .ok_or_else(|| CommandError::NotFound(format!("favorite {}", favorite_id)))?;
+
+    if target.phash.is_none() {
+        if let Some(path) = target.filepath.as_deref() {
+            if let Some(hash) = perceptual_hash(path.into()).await {
+                favorites.set_phash(favorite_id, &hash.to_string()).await?;
+                target.phash = Some(hash.to_string());
+            }
+        }
+    }
+
+    let local = similar::rank_similar(&target, favorites.get_all().await?, limit as usize);
+
+    let seed = match target.source {
+        Some(Source::Klipy) => similar::seed_query(&target),
+        _ => None,
+    };
+    let klipy = match seed {
+        // Goes through `search_klipy` so the content filter, locale and
+        // denylist apply as they do to any other search.
+        Some(query) => match search_klipy(query, limit, 1, show_ads, content_type, state).await {
+            Ok(mut results) => {
+                // The favorite itself is the best match; don't show it back.
+                results.items.retain(|item| match item {
+                    KlipyResultItem::Gif(gif) => Some(&gif.slug) != target.source_id.as_ref(),
+                    KlipyResultItem::Ad(_) => true,
+                });
+                Some(results)
+            }
+            Err(e) => {
+                // The local half is still worth showing.
+                eprintln!("Failed to search Klipy for similar favorites: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
     Ok(SearchResult { local, klipy })
 }
 
+/// Trending `content_type`; GIFs when it's not given.
 #[tauri::command]
 pub async fn get_klipy_trending(
     limit: u32,
     page: u32,
     show_ads: bool,
+    content_type: Option<KlipyContentType>,
     state: tauri::State<'_, AppState>,
 ) -> CommandResult<KlipySearchResults> {
-    let response = state
-        .klipy(show_ads)
-        .trending(limit, page, state.ad_context_for(show_ads))
-        .await?;
+    let content_type = content_type.unwrap_or_default();
+    let klipy = state.klipy(show_ads);
+    let ad_context = state.ad_context_for(show_ads);
+    let response = with_english_fallback(
+        state.content_context(),
+        has_no_content,
+        |content| async move {
+            klipy
+                .trending(content_type, limit, page, &content, ad_context)
+                .await
+        },
+    )
+    .await?;
     Ok(KlipySearchResults {
-        items: map_items(response.data.data),
+        items: map_items(response.data.data, &state.content_denylist()),
         total_count: response.data.total.unwrap_or(0),
         page: response.data.current_page.unwrap_or(page),
     })
@@ -169,12 +383,20 @@ pub async fn get_klipy_categories(
     show_ads: bool,
     state: tauri::State<'_, AppState>,
 ) -> CommandResult<KlipyCategoriesResult> {
-    let response = state.klipy(show_ads).categories().await?;
+    let klipy = state.klipy(show_ads);
+    let response = with_english_fallback(
+        state.content_context(),
+        |response: &KlipyCategoriesResponse| response.data.categories.is_empty(),
+        |content| async move { klipy.categories(&content).await },
+    )
+    .await?;
 
+    let denylist = state.content_denylist();
     let categories = response
         .data
         .categories
         .into_iter()
+        .filter(|cat| !denylist.is_denied(&cat.category) && !denylist.is_denied(&cat.query))
         .map(|cat| KlipyCategory {
             name: cat.category,
             slug: cat.query,
@@ -200,7 +422,15 @@ pub async fn get_autocomplete(
     if query.trim().is_empty() {
         return Ok(vec![]);
     }
-    Ok(state.klipy(show_ads).autocomplete(&query, limit).await?)
+    let klipy = state.klipy(show_ads);
+    let query = query.as_str();
+    let suggestions = with_english_fallback(
+        state.content_context(),
+        |suggestions: &Vec<String>| suggestions.is_empty(),
+        |content| async move { klipy.autocomplete(query, limit, &content).await },
+    )
+    .await?;
+    Ok(state.content_denylist().filter_suggestions(suggestions))
 }
 
 #[tauri::command]
@@ -213,10 +443,15 @@ pub async fn get_search_suggestions(
     if query.trim().is_empty() {
         return Ok(vec![]);
     }
-    Ok(state
-        .klipy(show_ads)
-        .search_suggestions(&query, limit)
-        .await?)
+    let klipy = state.klipy(show_ads);
+    let query = query.as_str();
+    let suggestions = with_english_fallback(
+        state.content_context(),
+        |suggestions: &Vec<String>| suggestions.is_empty(),
+        |content| async move { klipy.search_suggestions(query, limit, &content).await },
+    )
+    .await?;
+    Ok(state.content_denylist().filter_suggestions(suggestions))
 }
 
 #[tauri::command]
@@ -1,26 +1,263 @@
-use crate::commands::{AppState, CommandResult};
-use crate::models::Settings;
+use crate::commands::hotkey::register_all;
+use crate::commands::tray::refresh_tray_menu;
+use crate::commands::{apply_window_settings, AppState, CommandError, CommandResult};
+use crate::db::Database;
+use crate::models::{ConfigFileStatus, SettingKey, Settings};
+use crate::services::ConfigFile;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_autostart::ManagerExt;
+use tokio::sync::Mutex;
+
+/// How often the config file is checked for edits.
+const CONFIG_FILE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Held while a command reads, writes and applies settings, so a save and
+/// an update can't each apply the other's stale copy.
+pub(crate) static SETTINGS_LOCK: Mutex<()> = Mutex::const_new(());
 
 #[tauri::command]
 pub async fn get_settings(state: tauri::State<'_, AppState>) -> CommandResult<Settings> {
-    Ok(state.db.settings().get().await?)
+    Ok(state.config_file.effective_settings(&state.db).await?)
 }
 
 #[tauri::command]
 pub async fn save_settings(
+    app: AppHandle,
     settings: Settings,
     state: tauri::State<'_, AppState>,
 ) -> CommandResult<()> {
-    state.db.settings().save(&settings).await?;
-    Ok(())
+    store_settings(&state.db, &state.config_file, settings, |checked| {
+        if state.settings().launch_at_startup != checked.launch_at_startup {
+            apply_autostart(&app, checked.launch_at_startup)?;
+        }
+        apply_settings(&app, checked)
+    })
+    .await
+}
+
+/// The body of `save_settings`: validate every value, check the config
+/// file's locks, store the ones that changed, and hand the result to
+/// `apply`, all while holding `SETTINGS_LOCK`.
+pub(crate) async fn store_settings(
+    db: &Database,
+    config_file: &ConfigFile,
+    settings: Settings,
+    apply: impl FnOnce(&Settings) -> CommandResult<()>,
+) -> CommandResult<()> {
+    // Run every value through the registry's validators.
+    let mut checked = Settings::default();
+    for key in SettingKey::ALL {
+        checked
+            .set(key, settings.get(key))
+            .map_err(CommandError::InvalidArgument)?;
+    }
+
+    let _guard = SETTINGS_LOCK.lock().await;
+    let current = config_file.effective_settings(db).await?;
+    for &key in config_file.overlay().locked() {
+        if checked.get(key) != current.get(key) {
+            return Err(locked_error(key));
+        }
+    }
+
+    // Only what changed is stored, so keys the user never set keep
+    // following the config file.
+    let changed: Vec<SettingKey> = SettingKey::ALL
+        .into_iter()
+        .filter(|&key| checked.get(key) != current.get(key))
+        .collect();
+    db.settings().save_keys(&checked, &changed).await?;
+    apply(&checked)
 }
 
+/// Set one setting. `value` is JSON of the key's type, e.g. `"dark"` for
+/// `theme`; unknown keys, keys locked by the config file and values that
+/// don't parse or validate are rejected. A hotkey that fails to bind is
+/// still saved, and reported as an error.
 #[tauri::command]
 pub async fn update_setting(
+    app: AppHandle,
     key: String,
     value: String,
     state: tauri::State<'_, AppState>,
 ) -> CommandResult<()> {
-    state.db.settings().update_key(&key, value).await?;
+    store_setting(&state.db, &state.config_file, &key, &value, |settings| {
+        apply_changes(&app, &state.settings(), settings)
+    })
+    .await
+}
+
+/// The body of `update_setting`, which like `store_settings` hands the
+/// result to `apply` while still holding `SETTINGS_LOCK`.
+pub(crate) async fn store_setting(
+    db: &Database,
+    config_file: &ConfigFile,
+    key: &str,
+    value: &str,
+    apply: impl FnOnce(&Settings) -> CommandResult<()>,
+) -> CommandResult<()> {
+    let key: SettingKey = key.parse().map_err(CommandError::InvalidArgument)?;
+    let value: serde_json::Value = serde_json::from_str(value)
+        .map_err(|e| CommandError::InvalidArgument(format!("{}: invalid JSON: {}", key, e)))?;
+
+    let _guard = SETTINGS_LOCK.lock().await;
+    if config_file.overlay().is_locked(key) {
+        return Err(locked_error(key));
+    }
+    let mut settings = config_file.effective_settings(db).await?;
+    settings
+        .set(key, value)
+        .map_err(CommandError::InvalidArgument)?;
+    db.settings()
+        .update_key(key.as_str(), settings.get(key).to_string())
+        .await?;
+
+    apply(&settings)
+}
+
+/// Where the config file is, which keys it locks and what in it was skipped.
+#[tauri::command]
+pub async fn get_config_file_status(
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<ConfigFileStatus> {
+    Ok(state.config_file.overlay().status())
+}
+
+/// Write the current settings to the config file, creating `config.toml`
+/// if there isn't one.
+#[tauri::command]
+pub async fn export_config_file(
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<ConfigFileStatus> {
+    let _guard = SETTINGS_LOCK.lock().await;
+    let settings = state.config_file.effective_settings(&state.db).await?;
+    state.config_file.export(&settings)?;
+    Ok(state.config_file.overlay().status())
+}
+
+/// Save every value in the config file as this install's own, including
+/// the ones that would otherwise only fill in for unsaved keys.
+#[tauri::command]
+pub async fn import_config_file(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<ConfigFileStatus> {
+    let _guard = SETTINGS_LOCK.lock().await;
+    let overlay = state.config_file.reload();
+    let status = overlay.status();
+    if status.path.is_none() {
+        return Err(CommandError::NotFound("config file".to_string()));
+    }
+    if let Some(error) = &status.error {
+        return Err(CommandError::InvalidArgument(error.clone()));
+    }
+
+    let mut settings = state.config_file.effective_settings(&state.db).await?;
+    overlay.apply_all(&mut settings);
+    state.db.settings().save(&settings).await?;
+
+    app.emit("config-file-changed", &status)?;
+    apply_changes(&app, &state.settings(), &settings)?;
+    Ok(status)
+}
+
+/// Re-read the config file whenever it's created, edited or removed, and
+/// apply the result. Never returns; spawn it.
+pub async fn watch_config_file(app: AppHandle) {
+    let mut interval = tokio::time::interval(CONFIG_FILE_POLL_INTERVAL);
+    // The file as read at startup.
+    let mut last_stamp = app
+        .try_state::<AppState>()
+        .and_then(|state| state.config_file.stamp());
+    loop {
+        interval.tick().await;
+        let Some(state) = app.try_state::<AppState>() else {
+            continue;
+        };
+        let stamp = state.config_file.stamp();
+        if stamp == last_stamp {
+            continue;
+        }
+        last_stamp = stamp;
+        if let Err(e) = reload_config_file(&app, &state).await {
+            eprintln!("Warning: failed to apply config file: {}", e);
+        }
+    }
+}
+
+async fn reload_config_file(app: &AppHandle, state: &AppState) -> CommandResult<()> {
+    let _guard = SETTINGS_LOCK.lock().await;
+    let status = state.config_file.reload().status();
+    log_config_file_problems(&status);
+
+    let settings = state.config_file.effective_settings(&state.db).await?;
+    app.emit("config-file-changed", &status)?;
+    apply_changes(app, &state.settings(), &settings)
+}
+
+pub(crate) fn log_config_file_problems(status: &ConfigFileStatus) {
+    if let Some(error) = &status.error {
+        eprintln!("Warning: config file ignored: {}", error);
+    }
+    for issue in &status.issues {
+        eprintln!("Warning: config file {}: {}", issue.key, issue.message);
+    }
+}
+
+fn locked_error(key: SettingKey) -> CommandError {
+    CommandError::InvalidArgument(format!("{} is locked by the config file", key))
+}
+
+/// `apply_settings`, plus the login item and hotkeys when they differ from
+/// `previous`. Hotkeys that fail to bind come back as an error after
+/// everything else is applied.
+pub(crate) fn apply_changes(
+    app: &AppHandle,
+    previous: &Settings,
+    settings: &Settings,
+) -> CommandResult<()> {
+    if previous.launch_at_startup != settings.launch_at_startup {
+        apply_autostart(app, settings.launch_at_startup)?;
+    }
+    apply_settings(app, settings)?;
+
+    if previous.hotkeys != settings.hotkeys {
+        let failed: Vec<String> = register_all(app, &settings.hotkeys)
+            .into_iter()
+            .filter_map(|status| {
+                let error = status.error?;
+                Some(format!("{} '{}': {}", status.action, status.hotkey, error))
+            })
+            .collect();
+        if !failed.is_empty() {
+            return Err(CommandError::Hotkey(failed.join("; ")));
+        }
+    }
+    Ok(())
+}
+
+/// Push `settings` out to the running app: window flags, size and tray menu
+/// first, then a `settings-changed` event carrying the full `Settings` so every
+/// listener (grid, settings panel, …) picks up the new values without
+/// re-fetching.
+pub(crate) fn apply_settings(app: &AppHandle, settings: &Settings) -> CommandResult<()> {
+    if let Some(state) = app.try_state::<AppState>() {
+        state.set_settings(settings.clone());
+    }
+    apply_window_settings(app, settings)?;
+    refresh_tray_menu(app);
+    app.emit("settings-changed", settings)?;
     Ok(())
 }
+
+/// Register or unregister the app as a login item. Does nothing when the OS
+/// already agrees, so it's safe to call at every launch.
+pub(crate) fn apply_autostart(app: &AppHandle, enabled: bool) -> CommandResult<()> {
+    let autolaunch = app.autolaunch();
+    let result = match autolaunch.is_enabled() {
+        Ok(current) if current == enabled => Ok(()),
+        _ if enabled => autolaunch.enable(),
+        _ => autolaunch.disable(),
+    };
+    result.map_err(|e| CommandError::Autostart(e.to_string()))
+}
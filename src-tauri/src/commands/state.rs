@@ -1,7 +1,11 @@
 use crate::config::{KLIPY_API_KEY_NO_ADS, KLIPY_API_KEY_WITH_ADS};
 use crate::db::Database;
-use crate::services::{AdContext, Downloader, KlipyClient};
-use std::sync::Arc;
+use crate::models::{normalize_locale, Settings};
+use crate::services::{
+    AdContext, BackupStore, ConfigFile, ContentContext, ContentDenylist, Downloader, KlipyClient,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
 
 /// Bounds we tell Klipy our ad slot can fill. The lower bound is the smallest
 /// banner Klipy serves; the upper bound is roughly one masonry column wide
@@ -40,21 +44,40 @@ fn user_agent() -> String {
 /// Held by Tauri's state manager — `tauri::State<'_, AppState>` gives commands
 /// shared access without any locking. All inner services are immutable, async-safe
 /// handles (Arc-wrapped pools/clients), so concurrent commands run in parallel.
+/// The one exception is the settings snapshot, which sits behind a short-lived
+/// `RwLock` so sync callbacks (hotkey, tray) can read it without touching SQLite.
 pub struct AppState {
     pub db: Arc<Database>,
     pub downloader: Arc<Downloader>,
+    pub backups: Arc<BackupStore>,
+    pub config_file: Arc<ConfigFile>,
     pub klipy_with_ads: Arc<KlipyClient>,
     pub klipy_no_ads: Arc<KlipyClient>,
     /// Sent on every ad-eligible Klipy call. Stable per install.
     pub ad_context: AdContext,
+    /// The OS locale at launch; Klipy gets it unless the user picks one.
+    system_locale: Option<String>,
+    /// Last-applied settings. Refreshed by `apply_settings` whenever they change.
+    settings: RwLock<Settings>,
+    /// Set from the tray; while true no global shortcuts are registered.
+    hotkeys_paused: AtomicBool,
 }
 
 impl AppState {
-    pub fn new(db: Arc<Database>, downloader: Arc<Downloader>, customer_id: String) -> Self {
+    pub fn new(
+        db: Arc<Database>,
+        downloader: Arc<Downloader>,
+        backups: Arc<BackupStore>,
+        config_file: Arc<ConfigFile>,
+        customer_id: String,
+        settings: Settings,
+    ) -> Self {
         let ua = user_agent();
         Self {
             db,
             downloader,
+            backups,
+            config_file,
             klipy_with_ads: Arc::new(KlipyClient::new(
                 KLIPY_API_KEY_WITH_ADS.to_string(),
                 &ua,
@@ -70,9 +93,32 @@ impl AppState {
                 make: AD_MAKE,
                 app_version: APP_VERSION,
             },
+            system_locale: sys_locale::get_locale().and_then(|locale| normalize_locale(&locale)),
+            settings: RwLock::new(settings),
+            hotkeys_paused: AtomicBool::new(false),
         }
     }
 
+    /// Snapshot of the current settings.
+    pub fn settings(&self) -> Settings {
+        self.settings
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn set_settings(&self, settings: Settings) {
+        *self.settings.write().unwrap_or_else(|e| e.into_inner()) = settings;
+    }
+
+    pub fn hotkeys_paused(&self) -> bool {
+        self.hotkeys_paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_hotkeys_paused(&self, paused: bool) {
+        self.hotkeys_paused.store(paused, Ordering::Relaxed);
+    }
+
     pub fn klipy(&self, show_ads: bool) -> &Arc<KlipyClient> {
         if show_ads {
             &self.klipy_with_ads
@@ -86,4 +132,21 @@ impl AppState {
     pub fn ad_context_for(&self, show_ads: bool) -> Option<&AdContext> {
         show_ads.then_some(&self.ad_context)
     }
+
+    /// Content rating and locale for Klipy, from the current settings.
+    pub fn content_context(&self) -> ContentContext {
+        let settings = self.settings.read().unwrap_or_else(|e| e.into_inner());
+        ContentContext {
+            filter: settings.content_filter,
+            locale: settings
+                .locale
+                .clone()
+                .or_else(|| self.system_locale.clone()),
+        }
+    }
+
+    pub fn content_denylist(&self) -> ContentDenylist {
+        let settings = self.settings.read().unwrap_or_else(|e| e.into_inner());
+        ContentDenylist::new(&settings.content_denylist)
+    }
 }
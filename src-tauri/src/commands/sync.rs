@@ -0,0 +1,63 @@
+use crate::commands::tray::refresh_tray_menu;
+use crate::commands::{AppState, CommandError, CommandResult};
+use crate::models::SyncReport;
+use crate::services::FolderSync;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+
+/// How often the sync folder is checked while the app runs.
+const SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// One sync at a time; a second one would export the same changes twice.
+static SYNC_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Sync with the folder in settings now.
+#[tauri::command]
+pub async fn sync_now(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<SyncReport> {
+    let folder = state
+        .settings()
+        .sync_folder
+        .ok_or_else(|| CommandError::InvalidArgument("no sync folder is set".to_string()))?;
+    run_sync(&app, &state, PathBuf::from(folder)).await
+}
+
+async fn run_sync(app: &AppHandle, state: &AppState, folder: PathBuf) -> CommandResult<SyncReport> {
+    let _guard = SYNC_LOCK.lock().await;
+    let device_id = state.db.settings().get_or_create_device_id().await?;
+    let report = FolderSync::new(&state.db, &state.downloader, &folder, &device_id)
+        .run()
+        .await?;
+
+    if report.imported > 0 {
+        refresh_tray_menu(app);
+        let _ = app.emit("favorites-changed", ());
+    }
+    Ok(report)
+}
+
+/// Sync at startup and every few minutes after, whenever a folder is set.
+/// Never returns; spawn it.
+pub async fn sync_periodically(app: AppHandle) {
+    let mut interval = tokio::time::interval(SYNC_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some(state) = app.try_state::<AppState>() else {
+            continue;
+        };
+        let Some(folder) = state.settings().sync_folder else {
+            continue;
+        };
+        match run_sync(&app, &state, PathBuf::from(folder)).await {
+            Ok(report) if report.pending > 0 => eprintln!(
+                "Sync: {} change(s) waiting for media to arrive in the folder",
+                report.pending
+            ),
+            Ok(_) => {}
+            Err(e) => eprintln!("Warning: sync failed: {}", e),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::commands::{AppState, CommandError, CommandResult};
+use crate::models::{normalize_tag, TagCount};
+
+fn require_tag(name: &str) -> CommandResult<String> {
+    normalize_tag(name).ok_or_else(|| CommandError::InvalidArgument("tag name is empty".into()))
+}
+
+#[tauri::command]
+pub async fn list_tags(state: tauri::State<'_, AppState>) -> CommandResult<Vec<TagCount>> {
+    Ok(state.db.tags().list().await?)
+}
+
+/// Rename a tag everywhere. Renaming onto an existing tag merges the two.
+/// Returns how many favorites gained the new name.
+#[tauri::command]
+pub async fn rename_tag(
+    from: String,
+    to: String,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<u64> {
+    let from = require_tag(&from)?;
+    let to = require_tag(&to)?;
+
+    let tags = state.db.tags();
+    if !tags.exists(&from).await? {
+        return Err(CommandError::NotFound(format!("tag '{}'", from)));
+    }
+    Ok(tags.rename(&from, &to).await?)
+}
+
+/// Fold several tags into one. Unknown source tags are ignored.
+#[tauri::command]
+pub async fn merge_tags(
+    tags: Vec<String>,
+    into: String,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<u64> {
+    let into = require_tag(&into)?;
+    Ok(state.db.tags().merge(&tags, &into).await?)
+}
+
+/// Remove a tag from every favorite. Returns how many favorites had it.
+#[tauri::command]
+pub async fn delete_tag(name: String, state: tauri::State<'_, AppState>) -> CommandResult<u64> {
+    let name = require_tag(&name)?;
+    Ok(state.db.tags().delete(&name).await?)
+}
+
+/// Add custom tags to a batch of favorites in one go.
+#[tauri::command]
+pub async fn tag_favorites(
+    ids: Vec<i64>,
+    tags: Vec<String>,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<()> {
+    if tags.iter().all(|t| normalize_tag(t).is_none()) {
+        return Err(CommandError::InvalidArgument("no tags given".into()));
+    }
+    state.db.tags().tag_favorites(&ids, &tags).await?;
+    Ok(())
+}
+
+/// Remove tags from a batch of favorites.
+#[tauri::command]
+pub async fn untag_favorites(
+    ids: Vec<i64>,
+    tags: Vec<String>,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<()> {
+    state.db.tags().untag_favorites(&ids, &tags).await?;
+    Ok(())
+}
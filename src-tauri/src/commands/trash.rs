@@ -0,0 +1,128 @@
+use crate::commands::tray::refresh_tray_menu;
+use crate::commands::{AppState, CommandError, CommandResult};
+use crate::models::Favorite;
+use crate::services::Downloader;
+use chrono::{Duration, Utc};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+/// How often the trash is checked for favorites past their retention period
+/// while the app sits in the tray.
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(6 * 60 * 60);
+
+fn files_of(favorite: &Favorite) -> impl Iterator<Item = PathBuf> + '_ {
+    [&favorite.filepath, &favorite.mp4_filepath]
+        .into_iter()
+        .flatten()
+        .map(PathBuf::from)
+}
+
+/// Trashed favorites, most recently deleted first.
+#[tauri::command]
+pub async fn list_trash(state: tauri::State<'_, AppState>) -> CommandResult<Vec<Favorite>> {
+    Ok(state.db.favorites().get_trash().await?)
+}
+
+/// Take a favorite back out of the trash, files included.
+#[tauri::command]
+pub async fn restore_favorite(
+    id: i64,
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<Favorite> {
+    let favorites = state.db.favorites();
+    let favorite = favorites
+        .get_by_id(id)
+        .await?
+        .ok_or_else(|| CommandError::NotFound(format!("favorite {}", id)))?;
+    if favorite.deleted_at.is_none() {
+        return Err(CommandError::InvalidArgument(format!(
+            "favorite {} is not in the trash",
+            id
+        )));
+    }
+
+    for path in files_of(&favorite) {
+        // Files that never made it into the trash are still where they were.
+        if state.downloader.trash_path(id, &path).exists() {
+            let restored = state.downloader.restore_from_trash(id, &path).await?;
+            if restored != path {
+                favorites
+                    .relocate_file(id, &path.to_string_lossy(), &restored.to_string_lossy())
+                    .await?;
+            }
+        }
+    }
+    favorites.restore(id).await?;
+
+    refresh_tray_menu(&app);
+    favorites
+        .get_by_id(id)
+        .await?
+        .ok_or_else(|| CommandError::NotFound(format!("favorite {}", id)))
+}
+
+/// Delete everything in the trash for good. Returns how many favorites went.
+#[tauri::command]
+pub async fn empty_trash(state: tauri::State<'_, AppState>) -> CommandResult<usize> {
+    let trash = state.db.favorites().get_trash().await?;
+    purge(&state, &trash).await
+}
+
+/// Delete trashed favorites and their trashed files. Files are best-effort,
+/// like they were for `delete_favorite` before the trash existed.
+async fn purge(state: &AppState, trash: &[Favorite]) -> CommandResult<usize> {
+    let favorites = state.db.favorites();
+    let mut purged = 0;
+    for favorite in trash {
+        let Some(id) = favorite.id else {
+            continue;
+        };
+        for path in files_of(favorite) {
+            let trashed = state.downloader.trash_path(id, &path);
+            if trashed.exists() {
+                if let Err(e) = Downloader::delete_file(&trashed).await {
+                    eprintln!("Warning: {:#}", e);
+                }
+            }
+        }
+        favorites.delete(id).await?;
+        purged += 1;
+    }
+    Ok(purged)
+}
+
+/// Purge favorites trashed longer than `trash_retention_days` ago.
+pub async fn purge_expired_trash(state: &AppState) {
+    let retention_days = state.settings().trash_retention_days;
+    if retention_days == 0 {
+        return;
+    }
+    let cutoff = Utc::now() - Duration::days(i64::from(retention_days));
+
+    let expired: Vec<Favorite> = match state.db.favorites().get_trash().await {
+        Ok(trash) => trash
+            .into_iter()
+            .filter(|f| f.deleted_at.is_some_and(|at| at < cutoff))
+            .collect(),
+        Err(e) => {
+            eprintln!("Warning: {:#}", e);
+            return;
+        }
+    };
+    if let Err(e) = purge(state, &expired).await {
+        eprintln!("Warning: failed to purge trash: {}", e);
+    }
+}
+
+/// Run `purge_expired_trash` at startup and every few hours after. Never
+/// returns; spawn it.
+pub async fn purge_trash_periodically(app: AppHandle) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Some(state) = app.try_state::<AppState>() {
+            purge_expired_trash(&state).await;
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::commands::{
+    activate_profile, copy_favorite_by_id, register_all, AppState, CommandResult,
+};
+use crate::models::{Favorite, SettingsProfile};
+use tauri::menu::{
+    CheckMenuItemBuilder, Menu, MenuBuilder, MenuEvent, MenuItemBuilder, Submenu, SubmenuBuilder,
+};
+use tauri::{AppHandle, Manager};
+
+pub(crate) const TRAY_ID: &str = "main";
+const TOOLTIP: &str = "GIF Picker";
+
+/// Menu ids for favorite entries are `favorite:<id>` so one handler covers
+/// both submenus.
+const FAVORITE_ID_PREFIX: &str = "favorite:";
+const PAUSE_HOTKEYS_ID: &str = "pause_hotkeys";
+/// Profile entries are `profile:<name>`.
+const PROFILE_ID_PREFIX: &str = "profile:";
+
+/// Tray menu labels get truncated past this; long Klipy titles otherwise
+/// stretch the whole menu.
+const MAX_LABEL_CHARS: usize = 40;
+
+/// Build the full tray menu from the current favorites and settings.
+pub(crate) async fn build_tray_menu(app: &AppHandle) -> CommandResult<Menu<tauri::Wry>> {
+    let state = app.state::<AppState>();
+    let favorites = state.db.favorites();
+    let recent = favorites
+        .get_recent(state.settings().tray_recent_count)
+        .await?;
+    let pinned = favorites.get_pinned().await?;
+    let profiles = state.db.settings().profiles().await?;
+
+    let show_item = MenuItemBuilder::with_id("show", "Show GIF Picker").build(app)?;
+    let recent_menu = favorites_submenu(app, "Recent", &recent)?;
+    let pinned_menu = favorites_submenu(app, "Pinned", &pinned)?;
+    let profile_menu = profiles_submenu(app, &profiles)?;
+    let pause_item = CheckMenuItemBuilder::with_id(PAUSE_HOTKEYS_ID, "Pause Hotkeys")
+        .checked(state.hotkeys_paused())
+        .build(app)?;
+    let settings_item = MenuItemBuilder::with_id("settings", "Settings").build(app)?;
+    let quit_item = MenuItemBuilder::with_id("quit", "Quit").build(app)?;
+
+    let menu = MenuBuilder::new(app)
+        .item(&show_item)
+        .separator()
+        .item(&recent_menu)
+        .item(&pinned_menu)
+        .separator()
+        .item(&profile_menu)
+        .item(&pause_item)
+        .item(&settings_item)
+        .separator()
+        .item(&quit_item)
+        .build()?;
+    Ok(menu)
+}
+
+/// A submenu listing `favorites`, disabled when there's nothing to show.
+fn favorites_submenu(
+    app: &AppHandle,
+    title: &str,
+    favorites: &[Favorite],
+) -> CommandResult<Submenu<tauri::Wry>> {
+    let items = favorites
+        .iter()
+        .filter_map(|favorite| {
+            let id = favorite.id?;
+            Some(
+                MenuItemBuilder::with_id(
+                    format!("{}{}", FAVORITE_ID_PREFIX, id),
+                    menu_label(favorite),
+                )
+                .build(app),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut builder = SubmenuBuilder::new(app, title).enabled(!items.is_empty());
+    for item in &items {
+        builder = builder.item(item);
+    }
+    Ok(builder.build()?)
+}
+
+/// A submenu to switch profiles, with the active one checked. Disabled until
+/// a profile is created in settings.
+fn profiles_submenu(
+    app: &AppHandle,
+    profiles: &[SettingsProfile],
+) -> CommandResult<Submenu<tauri::Wry>> {
+    let items = profiles
+        .iter()
+        .map(|profile| {
+            CheckMenuItemBuilder::with_id(
+                format!("{}{}", PROFILE_ID_PREFIX, profile.name),
+                &profile.name,
+            )
+            .checked(profile.active)
+            .build(app)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut builder = SubmenuBuilder::new(app, "Profile").enabled(!items.is_empty());
+    for item in &items {
+        builder = builder.item(item);
+    }
+    Ok(builder.build()?)
+}
+
+fn menu_label(favorite: &Favorite) -> String {
+    let label = favorite
+        .description
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .unwrap_or(&favorite.filename);
+
+    if label.chars().count() > MAX_LABEL_CHARS {
+        let truncated: String = label.chars().take(MAX_LABEL_CHARS - 1).collect();
+        format!("{}…", truncated.trim_end())
+    } else {
+        label.to_string()
+    }
+}
+
+/// Rebuild the tray menu in the background. Call after anything that changes
+/// which favorites are recent or pinned.
+pub(crate) fn refresh_tray_menu(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let Some(tray) = app.tray_by_id(TRAY_ID) else {
+            return;
+        };
+        match build_tray_menu(&app).await {
+            Ok(menu) => {
+                if let Err(e) = tray.set_menu(Some(menu)) {
+                    eprintln!("Failed to update tray menu: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Failed to build tray menu: {}", e),
+        }
+    });
+}
+
+/// Show `status` (a download in progress, the last error) in the tray
+/// tooltip, or reset it with `None`. Paused hotkeys are always mentioned.
+pub(crate) fn set_tray_status(app: &AppHandle, status: Option<&str>) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    let mut tooltip = TOOLTIP.to_string();
+    if app
+        .try_state::<AppState>()
+        .is_some_and(|state| state.hotkeys_paused())
+    {
+        tooltip.push_str(" (hotkeys paused)");
+    }
+    if let Some(status) = status {
+        tooltip.push_str(" — ");
+        tooltip.push_str(status);
+    }
+    let _ = tray.set_tooltip(Some(tooltip));
+}
+
+/// Route a tray menu click. Returns `false` for ids this module doesn't own so
+/// the caller can handle the static entries.
+pub(crate) fn handle_tray_menu_event(app: &AppHandle, event: &MenuEvent) -> bool {
+    let id = event.id.as_ref();
+
+    if id == PAUSE_HOTKEYS_ID {
+        toggle_hotkeys_paused(app);
+        return true;
+    }
+
+    if let Some(name) = id.strip_prefix(PROFILE_ID_PREFIX) {
+        let app = app.clone();
+        let name = name.to_string();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = activate_profile(&app, &name).await {
+                eprintln!("Failed to switch to profile {}: {}", name, e);
+                set_tray_status(&app, Some("Profile switch failed"));
+                // The clicked entry checked itself; put the marks back.
+                refresh_tray_menu(&app);
+            }
+        });
+        return true;
+    }
+
+    let Some(favorite_id) = id
+        .strip_prefix(FAVORITE_ID_PREFIX)
+        .and_then(|id| id.parse::<i64>().ok())
+    else {
+        return false;
+    };
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match copy_favorite_by_id(&app, favorite_id).await {
+            Ok(()) => set_tray_status(&app, None),
+            Err(e) => {
+                eprintln!("Failed to copy favorite {}: {}", favorite_id, e);
+                set_tray_status(&app, Some("Copy failed"));
+            }
+        }
+    });
+    true
+}
+
+/// Release every global shortcut (so other apps can have them) or bring the
+/// saved bindings back.
+fn toggle_hotkeys_paused(app: &AppHandle) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let paused = !state.hotkeys_paused();
+    state.set_hotkeys_paused(paused);
+
+    let statuses = register_all(app, &state.settings().hotkeys);
+    let failed = statuses.iter().filter(|s| s.error.is_some()).count();
+    if failed > 0 {
+        set_tray_status(
+            app,
+            Some(&format!("{} hotkey(s) failed to register", failed)),
+        );
+    } else {
+        set_tray_status(app, None);
+    }
+    refresh_tray_menu(app);
+}
@@ -1,8 +1,25 @@
-use crate::commands::CommandResult;
-use tauri::{AppHandle, Emitter, Manager};
+use crate::commands::{auto_paste_if_enabled, AppState, CommandResult};
+use crate::models::{Settings, WindowPlacement};
+use tauri::{AppHandle, Emitter, LogicalSize, Manager, Monitor, PhysicalPosition, WebviewWindow};
 
+/// Smallest size we'll shrink the picker to, in logical pixels. Below this the
+/// search bar and a single column of tiles stop fitting.
+const MIN_WINDOW_WIDTH: f64 = 320.0;
+const MIN_WINDOW_HEIGHT: f64 = 240.0;
+
+/// Gap between the cursor and the window's corner in `Cursor` placement, in
+/// logical pixels — keeps the pointer from landing on the first tile.
+const CURSOR_OFFSET: f64 = 12.0;
+
+/// Hide the picker and hand focus back to the previous app. With `paste`, also
+/// auto-pastes into that app when the user has enabled it — callers pass it
+/// after a copy, never on a plain dismiss.
 #[tauri::command]
-pub async fn close_window(app: AppHandle) -> CommandResult<()> {
+pub async fn close_window(
+    app: AppHandle,
+    paste: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> CommandResult<()> {
     let Some(window) = app.get_webview_window("main") else {
         return Ok(());
     };
@@ -30,6 +47,10 @@ pub async fn close_window(app: AppHandle) -> CommandResult<()> {
         let _ = rx.await;
     }
 
+    if paste.unwrap_or(false) {
+        auto_paste_if_enabled(&state.settings()).await;
+    }
+
     Ok(())
 }
 
@@ -68,3 +89,113 @@ pub async fn set_always_on_top(app: AppHandle, value: bool) -> CommandResult<()>
     window.set_always_on_top(value)?;
     Ok(())
 }
+
+/// Apply the window-related parts of `settings` to the main window: the
+/// always-on-top flag, and the stored size clamped to the monitor the window
+/// is currently on. Settings are the source of truth for size — the
+/// window-state plugin only restores position.
+pub(crate) fn apply_window_settings(app: &AppHandle, settings: &Settings) -> CommandResult<()> {
+    let Some(window) = app.get_webview_window("main") else {
+        return Ok(());
+    };
+    window.set_always_on_top(settings.always_on_top)?;
+
+    let (width, height) = clamp_to_monitor(
+        &window,
+        settings.window_width as f64,
+        settings.window_height as f64,
+    )?;
+    window.set_size(LogicalSize::new(width, height))?;
+    Ok(())
+}
+
+/// Clamp a logical size to the work area of the window's monitor (falling back
+/// to the primary monitor when the window hasn't been placed yet).
+fn clamp_to_monitor(window: &WebviewWindow, width: f64, height: f64) -> CommandResult<(f64, f64)> {
+    let monitor = match window.current_monitor()? {
+        Some(monitor) => Some(monitor),
+        None => window.primary_monitor()?,
+    };
+
+    let Some(monitor) = monitor else {
+        return Ok((width.max(MIN_WINDOW_WIDTH), height.max(MIN_WINDOW_HEIGHT)));
+    };
+
+    let area = monitor
+        .work_area()
+        .size
+        .to_logical::<f64>(monitor.scale_factor());
+    Ok((
+        width.clamp(MIN_WINDOW_WIDTH.min(area.width), area.width),
+        height.clamp(MIN_WINDOW_HEIGHT.min(area.height), area.height),
+    ))
+}
+
+/// Move the (hidden) window to where `placement` says it should open. Always
+/// leaves the window fully inside a monitor's work area.
+///
+/// Everything is computed in the *target* monitor's physical pixels: the
+/// window's logical size is re-scaled by that monitor's factor, since the OS
+/// will resize it that way once it lands on a screen with a different DPI.
+pub(crate) fn place_window(window: &WebviewWindow, placement: &WindowPlacement) -> CommandResult<()> {
+    let cursor = window.cursor_position().ok();
+
+    // The "active" monitor is the one the pointer is on; fall back to wherever
+    // the window already is, then to the primary.
+    let cursor_monitor = match cursor {
+        Some(p) => window.monitor_from_point(p.x, p.y)?,
+        None => None,
+    };
+    let window_monitor = window.current_monitor()?;
+
+    let monitor = match placement {
+        WindowPlacement::Remember => window_monitor.or(cursor_monitor),
+        _ => cursor_monitor.or(window_monitor),
+    };
+    let Some(monitor) = monitor.or(window.primary_monitor()?) else {
+        return Ok(());
+    };
+
+    let logical = window.outer_size()?.to_logical::<f64>(window.scale_factor()?);
+    let scale = monitor.scale_factor();
+    let size = (logical.width * scale, logical.height * scale);
+    let area = work_area(&monitor);
+
+    let desired = match (placement, cursor) {
+        (WindowPlacement::Cursor, Some(p)) => {
+            (p.x + CURSOR_OFFSET * scale, p.y + CURSOR_OFFSET * scale)
+        }
+        (WindowPlacement::Remember, _) => {
+            let pos = window.outer_position()?;
+            (pos.x as f64, pos.y as f64)
+        }
+        _ => (
+            area.0 + (area.2 - size.0) / 2.0,
+            area.1 + (area.3 - size.1) / 2.0,
+        ),
+    };
+
+    let (x, y) = keep_on_screen(desired, size, area);
+    window.set_position(PhysicalPosition::new(x.round() as i32, y.round() as i32))?;
+    Ok(())
+}
+
+/// A monitor's work area as `(x, y, width, height)` in physical pixels.
+fn work_area(monitor: &Monitor) -> (f64, f64, f64, f64) {
+    let area = monitor.work_area();
+    (
+        area.position.x as f64,
+        area.position.y as f64,
+        area.size.width as f64,
+        area.size.height as f64,
+    )
+}
+
+/// Shift `pos` so a window of `size` sits entirely inside `area`. If the window
+/// is bigger than the area, pin it to the top-left so the title bar stays reachable.
+fn keep_on_screen(pos: (f64, f64), size: (f64, f64), area: (f64, f64, f64, f64)) -> (f64, f64) {
+    let (ax, ay, aw, ah) = area;
+    let x = pos.0.min(ax + aw - size.0).max(ax);
+    let y = pos.1.min(ay + ah - size.1).max(ay);
+    (x, y)
+}
@@ -0,0 +1,74 @@
+use anyhow::{Context, Result};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+
+use crate::models::{Favorite, PaletteColor};
+
+/// Replace a favorite's palette.
+pub(super) async fn set_favorite_palette(
+    conn: &mut SqliteConnection,
+    favorite_id: i64,
+    palette: &[PaletteColor],
+) -> Result<()> {
+    sqlx::query("DELETE FROM favorite_colors WHERE favorite_id = ?")
+        .bind(favorite_id)
+        .execute(&mut *conn)
+        .await
+        .context("Failed to clear favorite palette")?;
+
+    for (rank, color) in palette.iter().enumerate() {
+        let (hue, saturation, lightness) = color.hsl();
+        sqlx::query(
+            r#"
+            INSERT INTO favorite_colors (favorite_id, rank, r, g, b, hue, saturation, lightness, weight)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(favorite_id)
+        .bind(rank as i64)
+        .bind(color.r)
+        .bind(color.g)
+        .bind(color.b)
+        .bind(hue)
+        .bind(saturation)
+        .bind(lightness)
+        .bind(color.weight)
+        .execute(&mut *conn)
+        .await
+        .context("Failed to store favorite palette")?;
+    }
+    Ok(())
+}
+
+/// Fill in `palette` for already-loaded favorites.
+pub(super) async fn attach_palettes(pool: &SqlitePool, favorites: &mut [Favorite]) -> Result<()> {
+    let mut by_id: HashMap<i64, &mut Favorite> = favorites
+        .iter_mut()
+        .filter_map(|f| f.id.map(|id| (id, f)))
+        .collect();
+    if by_id.is_empty() {
+        return Ok(());
+    }
+
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT favorite_id, r, g, b, weight FROM favorite_colors WHERE favorite_id IN (",
+    );
+    let mut ids = query.separated(", ");
+    for id in by_id.keys() {
+        ids.push_bind(*id);
+    }
+    query.push(") ORDER BY favorite_id, rank");
+
+    let rows: Vec<(i64, u8, u8, u8, f32)> = query
+        .build_query_as()
+        .fetch_all(pool)
+        .await
+        .context("Failed to load favorite palettes")?;
+
+    for (favorite_id, r, g, b, weight) in rows {
+        if let Some(favorite) = by_id.get_mut(&favorite_id) {
+            favorite.palette.push(PaletteColor { r, g, b, weight });
+        }
+    }
+    Ok(())
+}
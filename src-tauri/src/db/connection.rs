@@ -1,12 +1,77 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use super::settings::SettingsDb;
 use super::favorites::FavoritesDb;
+use super::sync::SyncDb;
+use super::tags::TagsDb;
+use super::search_index::index_missing;
+use super::tags::normalize_tag_names;
+
+/// How many `pre-migration-*` snapshots to keep beside the database.
+const PRE_MIGRATION_BACKUPS_KEPT: usize = 3;
+
+/// Every schema migration, in order. Applied ones are checksummed, so once a
+/// file has shipped it must not be edited; add a new migration instead.
+const MIGRATIONS: &[(i64, &str, &str)] = &[
+    (1, "001_initial", include_str!("../../migrations/001_initial.sql")),
+    (2, "002_add_gif_url", include_str!("../../migrations/002_add_gif_url.sql")),
+    (3, "003_add_clipboard_mode", include_str!("../../migrations/003_add_clipboard_mode.sql")),
+    (4, "004_add_pinned", include_str!("../../migrations/004_add_pinned.sql")),
+    (5, "005_add_sort_position", include_str!("../../migrations/005_add_sort_position.sql")),
+    (6, "006_normalize_tags", include_str!("../../migrations/006_normalize_tags.sql")),
+    (7, "007_add_source_query", include_str!("../../migrations/007_add_source_query.sql")),
+    (8, "008_add_search_index", include_str!("../../migrations/008_add_search_index.sql")),
+    (9, "009_add_phash", include_str!("../../migrations/009_add_phash.sql")),
+    (10, "010_add_favorite_colors", include_str!("../../migrations/010_add_favorite_colors.sql")),
+    (11, "011_add_media_info", include_str!("../../migrations/011_add_media_info.sql")),
+    (12, "012_add_trash", include_str!("../../migrations/012_add_trash.sql")),
+    (13, "013_add_sync", include_str!("../../migrations/013_add_sync.sql")),
+    (14, "014_add_settings_profiles", include_str!("../../migrations/014_add_settings_profiles.sql")),
+    (15, "015_add_sync_edits", include_str!("../../migrations/015_add_sync_edits.sql")),
+    (16, "016_add_palette_checked", include_str!("../../migrations/016_add_palette_checked.sql")),
+];
 
 pub struct Database {
     pool: SqlitePool,
+    path: PathBuf,
+}
+
+/// What `Database::inspect_snapshot` found in a snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    pub schema_version: i64,
+    pub favorite_count: i64,
+}
+
+/// Newest schema version this build can migrate to.
+pub fn latest_schema_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |(version, _, _)| *version)
+}
+
+fn checksum_of(sql: &str) -> String {
+    format!("{:x}", Sha256::digest(sql.as_bytes()))
+}
+
+/// Delete all but the newest `keep` pre-migration snapshots in `dir`. Their
+/// names end in the time they were taken, so that's what they sort by.
+fn rotate_pre_migration_backups(dir: &Path, keep: usize) -> Result<()> {
+    let mut backups: Vec<String> = std::fs::read_dir(dir)
+        .context("Failed to read backup directory")?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.starts_with("pre-migration-") && name.ends_with(".db"))
+        .collect();
+    backups.sort_by(|a, b| a.rsplit('-').next().cmp(&b.rsplit('-').next()));
+
+    let excess = backups.len().saturating_sub(keep);
+    for name in &backups[..excess] {
+        std::fs::remove_file(dir.join(name))
+            .with_context(|| format!("Failed to delete old backup {}", name))?;
+    }
+    Ok(())
 }
 
 impl Database {
@@ -27,7 +92,7 @@ impl Database {
             .await
             .context("Failed to connect to database")?;
 
-        Ok(Self { pool })
+        Ok(Self { pool, path: db_path })
     }
 
     pub async fn run_migrations(&self) -> Result<()> {
@@ -36,55 +101,218 @@ impl Database {
             "CREATE TABLE IF NOT EXISTS _migrations (
                 version INTEGER PRIMARY KEY,
                 name TEXT NOT NULL,
-                applied_at TEXT NOT NULL
+                applied_at TEXT NOT NULL,
+                checksum TEXT
             )"
         )
         .execute(&self.pool)
         .await
         .context("Failed to create migrations table")?;
 
-        // Define migrations
-        let migrations = vec![
-            (1, "001_initial", include_str!("../../migrations/001_initial.sql")),
-            (2, "002_add_gif_url", include_str!("../../migrations/002_add_gif_url.sql")),
-            (3, "003_add_clipboard_mode", include_str!("../../migrations/003_add_clipboard_mode.sql")),
-        ];
+        // Tables created before checksums were recorded lack the column.
+        let has_checksum: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM pragma_table_info('_migrations') WHERE name = 'checksum'"
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to inspect migrations table")?;
+        if has_checksum.0 == 0 {
+            sqlx::query("ALTER TABLE _migrations ADD COLUMN checksum TEXT")
+                .execute(&self.pool)
+                .await
+                .context("Failed to add checksum to migrations table")?;
+        }
 
-        // Run each migration if not already applied
-        for (version, name, sql) in migrations {
-            // Check if migration already applied
-            let result: Option<(i64,)> = sqlx::query_as(
-                "SELECT version FROM _migrations WHERE version = ?"
+        let applied: Vec<(i64, String, Option<String>)> = sqlx::query_as(
+            "SELECT version, name, checksum FROM _migrations ORDER BY version"
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to read applied migrations")?;
+
+        // An older build must not write to a schema it doesn't understand.
+        let latest = latest_schema_version();
+        if let Some((version, name, _)) = applied.iter().find(|(v, _, _)| *v > latest) {
+            bail!(
+                "Database schema is newer than this version of the app \
+                 (migration {} {}, this build knows up to {}); update the app to open it",
+                version,
+                name,
+                latest
+            );
+        }
+
+        for (version, name, checksum) in &applied {
+            let Some((_, _, sql)) = MIGRATIONS.iter().find(|(v, _, _)| v == version) else {
+                continue;
+            };
+            let expected = checksum_of(sql);
+            match checksum {
+                Some(checksum) if *checksum != expected => bail!(
+                    "Migration {} was changed after it was applied \
+                     (recorded checksum {}, now {})",
+                    name,
+                    checksum,
+                    expected
+                ),
+                Some(_) => {}
+                // Applied before checksums were recorded; take it as it is.
+                None => {
+                    sqlx::query("UPDATE _migrations SET checksum = ? WHERE version = ?")
+                        .bind(&expected)
+                        .bind(version)
+                        .execute(&self.pool)
+                        .await
+                        .context(format!("Failed to record checksum of migration {}", name))?;
+                }
+            }
+        }
+
+        let pending: Vec<_> = MIGRATIONS
+            .iter()
+            .filter(|(version, _, _)| !applied.iter().any(|(v, _, _)| v == version))
+            .collect();
+
+        // A fresh database has nothing to lose; anything else is copied
+        // before its schema is touched.
+        if !pending.is_empty() && !applied.is_empty() {
+            let from = applied.last().map_or(0, |(version, _, _)| *version);
+            let backup = self.backup_before_migration(from, latest).await?;
+            eprintln!("Backed up database to {}", backup.display());
+        }
+
+        // Each migration commits together with its record, so a crash leaves
+        // the schema before or after it, never halfway through.
+        for (version, name, sql) in pending {
+            let mut tx = self
+                .pool
+                .begin()
+                .await
+                .context(format!("Failed to start migration {}", name))?;
+
+            sqlx::query(sql)
+                .execute(&mut *tx)
+                .await
+                .context(format!("Failed to run migration {}", name))?;
+
+            sqlx::query(
+                "INSERT INTO _migrations (version, name, applied_at, checksum) VALUES (?, ?, datetime('now'), ?)"
             )
             .bind(version)
-            .fetch_optional(&self.pool)
+            .bind(name)
+            .bind(checksum_of(sql))
+            .execute(&mut *tx)
             .await
-            .context(format!("Failed to check migration {}", name))?;
+            .context(format!("Failed to record migration {}", name))?;
 
-            if result.is_none() {
-                // Run migration
-                sqlx::query(sql)
-                    .execute(&self.pool)
-                    .await
-                    .context(format!("Failed to run migration {}", name))?;
-
-                // Record migration as applied
-                sqlx::query(
-                    "INSERT INTO _migrations (version, name, applied_at) VALUES (?, ?, datetime('now'))"
-                )
-                .bind(version)
-                .bind(name)
-                .execute(&self.pool)
+            tx.commit()
                 .await
-                .context(format!("Failed to record migration {}", name))?;
+                .context(format!("Failed to commit migration {}", name))?;
 
-                eprintln!("Applied migration: {}", name);
-            }
+            eprintln!("Applied migration: {}", name);
         }
 
+        // Tags migrated by 006 were normalized in SQL, which can't match
+        // `normalize_tag` exactly; finish the job in Rust.
+        normalize_tag_names(&self.pool).await?;
+
+        // The fuzzy search index is built in Rust rather than SQL, so rows
+        // from before 008 are filled in here.
+        index_missing(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// Copy the database into `backups/` beside it. `VACUUM INTO` gives a
+    /// consistent snapshot even with other connections open.
+    async fn backup_before_migration(&self, from: i64, to: i64) -> Result<PathBuf> {
+        let dir = self
+            .path
+            .parent()
+            .map(|parent| parent.join("backups"))
+            .unwrap_or_else(|| PathBuf::from("backups"));
+        std::fs::create_dir_all(&dir)
+            .context("Failed to create backup directory")?;
+
+        let path = dir.join(format!(
+            "pre-migration-{:03}-to-{:03}-{}.db",
+            from,
+            to,
+            Utc::now().format("%Y%m%dT%H%M%S")
+        ));
+        self.snapshot_to(&path)
+            .await
+            .context("Failed to back up database before migrating")?;
+        if let Err(e) = rotate_pre_migration_backups(&dir, PRE_MIGRATION_BACKUPS_KEPT) {
+            eprintln!("Warning: {:#}", e);
+        }
+        Ok(path)
+    }
+
+    /// Write a consistent copy of the database to `path`, which must not
+    /// exist yet. Safe to call while other connections are writing.
+    pub async fn snapshot_to(&self, path: &Path) -> Result<()> {
+        sqlx::query("VACUUM INTO ?")
+            .bind(path.to_string_lossy().into_owned())
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Failed to write snapshot to {}", path.display()))?;
         Ok(())
     }
 
+    /// Open a snapshot read-only and check that this build could use it:
+    /// intact pages, a schema no newer than ours and a favorites table.
+    pub async fn inspect_snapshot(path: &Path) -> Result<SnapshotInfo> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .read_only(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .with_context(|| format!("Failed to open snapshot {}", path.display()))?;
+
+        let result = async {
+            let (integrity,): (String,) = sqlx::query_as("PRAGMA integrity_check")
+                .fetch_one(&pool)
+                .await
+                .context("Failed to check snapshot integrity")?;
+            ensure!(integrity == "ok", "Snapshot is corrupt: {}", integrity);
+
+            let (schema_version,): (Option<i64>,) =
+                sqlx::query_as("SELECT MAX(version) FROM _migrations")
+                    .fetch_one(&pool)
+                    .await
+                    .context("Snapshot has no migration history")?;
+            let schema_version = schema_version.unwrap_or(0);
+            ensure!(
+                schema_version <= latest_schema_version(),
+                "Snapshot schema {} is newer than this build knows ({})",
+                schema_version,
+                latest_schema_version()
+            );
+
+            let (favorite_count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM favorites")
+                .fetch_one(&pool)
+                .await
+                .context("Snapshot has no favorites table")?;
+
+            Ok(SnapshotInfo {
+                schema_version,
+                favorite_count,
+            })
+        }
+        .await;
+
+        pool.close().await;
+        result
+    }
+
+    /// Where the database file lives.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
     }
@@ -96,11 +324,20 @@ impl Database {
     pub fn favorites(&self) -> FavoritesDb<'_> {
         FavoritesDb::new(&self.pool)
     }
+
+    pub fn tags(&self) -> TagsDb<'_> {
+        TagsDb::new(&self.pool)
+    }
+
+    pub fn sync(&self) -> SyncDb<'_> {
+        SyncDb::new(&self.pool)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::{normalize_tag, normalize_tags};
     use tempfile::TempDir;
 
     async fn create_test_db() -> (Database, TempDir) {
@@ -148,4 +385,206 @@ mod tests {
 
         assert_eq!(result.0, 1);
     }
+
+    /// A database at schema version 5, where tags still lived in JSON
+    /// columns, holding favorites given as (name, tags, custom tags).
+    async fn create_legacy_db(favorites: &[(&str, &str, &str)]) -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+
+        sqlx::query(
+            "CREATE TABLE _migrations (version INTEGER PRIMARY KEY, name TEXT NOT NULL, applied_at TEXT NOT NULL)",
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+        let legacy = [
+            include_str!("../../migrations/001_initial.sql"),
+            include_str!("../../migrations/002_add_gif_url.sql"),
+            include_str!("../../migrations/003_add_clipboard_mode.sql"),
+            include_str!("../../migrations/004_add_pinned.sql"),
+            include_str!("../../migrations/005_add_sort_position.sql"),
+        ];
+        for (i, sql) in legacy.iter().enumerate() {
+            sqlx::query(sql).execute(db.pool()).await.unwrap();
+            sqlx::query("INSERT INTO _migrations VALUES (?, 'legacy', datetime('now'))")
+                .bind(i as i64 + 1)
+                .execute(db.pool())
+                .await
+                .unwrap();
+        }
+
+        for (name, tags, custom_tags) in favorites {
+            sqlx::query(
+                r#"
+                INSERT INTO favorites (filename, filepath, media_type, tags, custom_tags, created_at)
+                VALUES (?, ?, 'gif', ?, ?, datetime('now'))
+                "#,
+            )
+            .bind(name)
+            .bind(format!("/path/to/{}.gif", name))
+            .bind(tags)
+            .bind(custom_tags)
+            .execute(db.pool())
+            .await
+            .unwrap();
+        }
+
+        (db, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_legacy_json_tags_are_migrated() {
+        let (db, _temp) = create_legacy_db(&[
+            ("a", r#"["Cat", "funny   cat"]"#, r#"[" CAT ", "mine"]"#),
+            ("b", r#"["cat", ""]"#, "not json"),
+        ])
+        .await;
+
+        db.run_migrations().await.unwrap();
+
+        let favorites = db.favorites().get_all().await.unwrap();
+        let a = favorites.iter().find(|f| f.filename == "a").unwrap();
+        assert_eq!(a.custom_tags, vec!["cat", "mine"]);
+        assert_eq!(a.tags, vec!["funny cat"]);
+        let b = favorites.iter().find(|f| f.filename == "b").unwrap();
+        assert_eq!(b.tags, vec!["cat"]);
+        assert!(b.custom_tags.is_empty());
+
+        let tags = db.tags().list().await.unwrap();
+        assert_eq!(tags[0].name, "cat");
+        assert_eq!(tags[0].count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_migrated_tags_match_normalize_tag() {
+        // Things the SQL in 006 can't normalize: non-ASCII case, long runs of
+        // spaces, and whitespace other than tabs and newlines.
+        let legacy = [
+            "ÜBER",
+            "über",
+            "a                                b",
+            "no\u{a0}break",
+            "\u{b}",
+            "Ça Va",
+        ];
+        let tags = serde_json::to_string(&legacy[..3]).unwrap();
+        let custom_tags = serde_json::to_string(&legacy[3..]).unwrap();
+        let (db, _temp) = create_legacy_db(&[("a", &tags, &custom_tags)]).await;
+
+        db.run_migrations().await.unwrap();
+
+        let favorite = db.favorites().get_all().await.unwrap().pop().unwrap();
+        assert_eq!(favorite.tags, normalize_tags(&legacy[..3]));
+        assert_eq!(favorite.custom_tags, normalize_tags(&legacy[3..]));
+        for tag in db.tags().list().await.unwrap() {
+            assert_eq!(normalize_tag(&tag.name), Some(tag.name.clone()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_backs_up_and_records_checksums() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+
+        // A tracking table from before checksums, with only 001 applied.
+        sqlx::query(
+            "CREATE TABLE _migrations (version INTEGER PRIMARY KEY, name TEXT NOT NULL, applied_at TEXT NOT NULL)",
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+        sqlx::query(MIGRATIONS[0].2).execute(db.pool()).await.unwrap();
+        sqlx::query("INSERT INTO _migrations VALUES (1, '001_initial', datetime('now'))")
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        db.run_migrations().await.unwrap();
+
+        let backups: Vec<_> = std::fs::read_dir(temp_dir.path().join("backups"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert!(backups[0].starts_with("pre-migration-001-to-"));
+
+        let missing: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM _migrations WHERE checksum IS NULL")
+                .fetch_one(db.pool())
+                .await
+                .unwrap();
+        assert_eq!(missing.0, 0);
+
+        // Nothing pending, so no second backup.
+        db.run_migrations().await.unwrap();
+        let count = std::fs::read_dir(temp_dir.path().join("backups")).unwrap().count();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_rotates_pre_migration_backups() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let names = [
+            "pre-migration-012-to-014-20260301T090000.db",
+            "pre-migration-001-to-012-20250101T090000.db",
+            "pre-migration-014-to-016-20261001T090000.db",
+            "pre-migration-013-to-014-20260201T090000.db",
+        ];
+        for name in names {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        // Not ours to delete.
+        std::fs::create_dir(dir.join("20250101T000000")).unwrap();
+
+        rotate_pre_migration_backups(dir, 2).unwrap();
+
+        let mut left: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            vec![
+                "20250101T000000",
+                "pre-migration-012-to-014-20260301T090000.db",
+                "pre-migration-014-to-016-20261001T090000.db",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fresh_database_is_not_backed_up() {
+        let (_db, temp) = create_test_db().await;
+        assert!(!temp.path().join("backups").exists());
+    }
+
+    #[tokio::test]
+    async fn test_edited_migration_is_refused() {
+        let (db, _temp) = create_test_db().await;
+        sqlx::query("UPDATE _migrations SET checksum = 'abc' WHERE version = 3")
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        let err = db.run_migrations().await.unwrap_err();
+        assert!(err.to_string().contains("003_add_clipboard_mode"));
+    }
+
+    #[tokio::test]
+    async fn test_newer_schema_is_refused() {
+        let (db, temp) = create_test_db().await;
+        sqlx::query(
+            "INSERT INTO _migrations (version, name, applied_at) VALUES (999, '999_future', datetime('now'))",
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        let err = db.run_migrations().await.unwrap_err();
+        assert!(err.to_string().contains("newer"));
+        assert!(!temp.path().join("backups").exists());
+    }
 }
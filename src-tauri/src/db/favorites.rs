@@ -1,8 +1,15 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use sqlx::SqlitePool;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 
-use crate::models::{Favorite, MediaType, Source};
+use super::colors::{attach_palettes, set_favorite_palette};
+use super::search_index::{fuzzy_matches, reindex};
+use super::search_query::SearchQuery;
+use super::sync::{changed_fields, new_sync_id, stamp_edits};
+use super::tags::{attach_tags, prune_orphans, set_favorite_tags};
+use crate::models::{Favorite, MediaInfo, MediaType, PaletteColor, Source, SyncField};
 
 pub struct FavoritesDb<'a> {
     pool: &'a SqlitePool,
@@ -14,20 +21,37 @@ impl<'a> FavoritesDb<'a> {
     }
 
     pub async fn create(&self, favorite: &Favorite) -> Result<i64> {
-        let tags_json = serde_json::to_string(&favorite.tags)?;
-        let custom_tags_json = serde_json::to_string(&favorite.custom_tags)?;
+        self.insert(favorite, &new_sync_id(favorite)).await
+    }
+
+    /// Create a favorite another device synced, keeping the id it was
+    /// shared under.
+    pub async fn create_with_sync_id(&self, favorite: &Favorite, sync_id: &str) -> Result<i64> {
+        self.insert(favorite, sync_id).await
+    }
+
+    async fn insert(&self, favorite: &Favorite, sync_id: &str) -> Result<i64> {
         let source = favorite.source.as_ref().map(|s| s.to_string());
         let created_at = favorite.created_at.to_rfc3339();
         let last_used = favorite.last_used.map(|dt| dt.to_rfc3339());
 
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
         let result = sqlx::query(
             r#"
             INSERT INTO favorites (
-                filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url,
-                tags, custom_tags, description, width, height, file_size,
-                created_at, last_used, use_count
+                filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url, source_query,
+                description, width, height, file_size,
+                created_at, last_used, use_count, pinned, sort_position, phash,
+                frame_count, duration_ms, fps, loop_count, has_alpha, sync_id
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                    (SELECT COALESCE(MIN(sort_position), 0) - 1 FROM favorites), ?,
+                    ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&favorite.filename)
@@ -38,8 +62,7 @@ impl<'a> FavoritesDb<'a> {
         .bind(source)
         .bind(&favorite.source_id)
         .bind(&favorite.source_url)
-        .bind(tags_json)
-        .bind(custom_tags_json)
+        .bind(&favorite.source_query)
         .bind(&favorite.description)
         .bind(favorite.width)
         .bind(favorite.height)
@@ -47,19 +70,34 @@ impl<'a> FavoritesDb<'a> {
         .bind(created_at)
         .bind(last_used)
         .bind(favorite.use_count)
-        .execute(self.pool)
+        .bind(favorite.pinned)
+        .bind(&favorite.phash)
+        .bind(favorite.frame_count)
+        .bind(favorite.duration_ms)
+        .bind(favorite.fps)
+        .bind(favorite.loop_count)
+        .bind(favorite.has_alpha)
+        .bind(sync_id)
+        .execute(&mut *tx)
         .await
         .context("Failed to insert favorite")?;
 
-        Ok(result.last_insert_rowid())
+        let id = result.last_insert_rowid();
+        set_favorite_tags(&mut tx, id, &favorite.tags, &favorite.custom_tags).await?;
+        set_favorite_palette(&mut tx, id, &favorite.palette).await?;
+        reindex(&mut tx, id).await?;
+
+        tx.commit().await.context("Failed to commit favorite")?;
+        Ok(id)
     }
 
     pub async fn get_by_id(&self, id: i64) -> Result<Option<Favorite>> {
         let row = sqlx::query_as::<_, FavoriteRow>(
             r#"
-            SELECT id, filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url,
-                   tags, custom_tags, description, width, height, file_size,
-                   created_at, last_used, use_count
+            SELECT id, filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url, source_query,
+                   description, width, height, file_size,
+                   created_at, last_used, use_count, pinned, sort_position, phash,
+                   frame_count, duration_ms, fps, loop_count, has_alpha, deleted_at
             FROM favorites
             WHERE id = ?
             "#,
@@ -69,40 +107,114 @@ impl<'a> FavoritesDb<'a> {
         .await
         .context("Failed to fetch favorite")?;
 
-        Ok(row.map(|r| r.into()))
+        Ok(self.hydrate(row.into_iter().collect()).await?.pop())
     }
 
     pub async fn get_all(&self) -> Result<Vec<Favorite>> {
         let rows = sqlx::query_as::<_, FavoriteRow>(
             r#"
-            SELECT id, filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url,
-                   tags, custom_tags, description, width, height, file_size,
-                   created_at, last_used, use_count
+            SELECT id, filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url, source_query,
+                   description, width, height, file_size,
+                   created_at, last_used, use_count, pinned, sort_position, phash,
+                   frame_count, duration_ms, fps, loop_count, has_alpha, deleted_at
             FROM favorites
-            ORDER BY created_at DESC
+            WHERE deleted_at IS NULL
+            ORDER BY pinned DESC, sort_position ASC, created_at DESC
             "#,
         )
         .fetch_all(self.pool)
         .await
         .context("Failed to fetch all favorites")?;
 
-        Ok(rows.into_iter().map(|r| r.into()).collect())
+        self.hydrate(rows).await
+    }
+
+    /// The favorite copied most recently, if any has been used at all.
+    pub async fn get_most_recently_used(&self) -> Result<Option<Favorite>> {
+        let row = sqlx::query_as::<_, FavoriteRow>(
+            r#"
+            SELECT id, filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url, source_query,
+                   description, width, height, file_size,
+                   created_at, last_used, use_count, pinned, sort_position, phash,
+                   frame_count, duration_ms, fps, loop_count, has_alpha, deleted_at
+            FROM favorites
+            WHERE last_used IS NOT NULL AND deleted_at IS NULL
+            ORDER BY last_used DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(self.pool)
+        .await
+        .context("Failed to fetch most recently used favorite")?;
+
+        Ok(self.hydrate(row.into_iter().collect()).await?.pop())
+    }
+
+    /// Up to `limit` favorites, most recently used first; never-used ones fall
+    /// back to when they were added.
+    pub async fn get_recent(&self, limit: u32) -> Result<Vec<Favorite>> {
+        let rows = sqlx::query_as::<_, FavoriteRow>(
+            r#"
+            SELECT id, filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url, source_query,
+                   description, width, height, file_size,
+                   created_at, last_used, use_count, pinned, sort_position, phash,
+                   frame_count, duration_ms, fps, loop_count, has_alpha, deleted_at
+            FROM favorites
+            WHERE deleted_at IS NULL
+            ORDER BY COALESCE(last_used, created_at) DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await
+        .context("Failed to fetch recent favorites")?;
+
+        self.hydrate(rows).await
+    }
+
+    pub async fn get_pinned(&self) -> Result<Vec<Favorite>> {
+        let rows = sqlx::query_as::<_, FavoriteRow>(
+            r#"
+            SELECT id, filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url, source_query,
+                   description, width, height, file_size,
+                   created_at, last_used, use_count, pinned, sort_position, phash,
+                   frame_count, duration_ms, fps, loop_count, has_alpha, deleted_at
+            FROM favorites
+            WHERE pinned = 1 AND deleted_at IS NULL
+            ORDER BY sort_position ASC, created_at DESC
+            "#,
+        )
+        .fetch_all(self.pool)
+        .await
+        .context("Failed to fetch pinned favorites")?;
+
+        self.hydrate(rows).await
     }
 
+    /// Substring matches in the usual order, followed by typo-tolerant
+    /// matches from the search index ("hapy cat" → "happy cat"). Any exact hit
+    /// outranks every fuzzy one; fuzzy hits are ranked by closeness.
     pub async fn search(&self, query: &str) -> Result<Vec<Favorite>> {
         let search_term = format!("%{}%", query.to_lowercase());
 
         let rows = sqlx::query_as::<_, FavoriteRow>(
             r#"
-            SELECT id, filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url,
-                   tags, custom_tags, description, width, height, file_size,
-                   created_at, last_used, use_count
+            SELECT id, filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url, source_query,
+                   description, width, height, file_size,
+                   created_at, last_used, use_count, pinned, sort_position, phash,
+                   frame_count, duration_ms, fps, loop_count, has_alpha, deleted_at
             FROM favorites
-            WHERE LOWER(filename) LIKE ?
-               OR LOWER(tags) LIKE ?
-               OR LOWER(custom_tags) LIKE ?
+            WHERE deleted_at IS NULL
+              AND (LOWER(filename) LIKE ?
                OR LOWER(description) LIKE ?
-            ORDER BY use_count DESC, created_at DESC
+               OR LOWER(source_query) LIKE ?
+               OR EXISTS (
+                   SELECT 1 FROM favorite_tags ft
+                   JOIN tags t ON t.id = ft.tag_id
+                   WHERE ft.favorite_id = favorites.id AND t.name LIKE ?
+               ))
+            ORDER BY pinned DESC, use_count DESC, created_at DESC
             "#,
         )
         .bind(&search_term)
@@ -113,22 +225,125 @@ impl<'a> FavoritesDb<'a> {
         .await
         .context("Failed to search favorites")?;
 
-        Ok(rows.into_iter().map(|r| r.into()).collect())
+        let mut results = self.hydrate(rows).await?;
+        let fuzzy = self
+            .fuzzy_search(query, &SearchQuery::default(), &results)
+            .await?;
+        results.extend(fuzzy);
+        Ok(results)
+    }
+
+    /// Typo-tolerant matches for `words` that also pass `filters`, best first,
+    /// leaving out the favorites already in `exact`.
+    async fn fuzzy_search(
+        &self,
+        words: &str,
+        filters: &SearchQuery,
+        exact: &[Favorite],
+    ) -> Result<Vec<Favorite>> {
+        let exact: HashSet<i64> = exact.iter().filter_map(|f| f.id).collect();
+
+        let scores: HashMap<i64, f64> = fuzzy_matches(self.pool, words)
+            .await?
+            .into_iter()
+            .filter(|(id, _)| !exact.contains(id))
+            .collect();
+        if scores.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut fuzzy = self.get_many(scores.keys().copied(), filters).await?;
+        let score = |f: &Favorite| f.id.and_then(|id| scores.get(&id)).copied();
+        fuzzy.sort_by(|a, b| {
+            score(b)
+                .partial_cmp(&score(a))
+                .unwrap_or(Ordering::Equal)
+                .then(b.pinned.cmp(&a.pinned))
+                .then(b.use_count.cmp(&a.use_count))
+        });
+        Ok(fuzzy)
+    }
+
+    /// The favorites among `ids` that aren't in the trash and pass `filters`.
+    async fn get_many(
+        &self,
+        ids: impl IntoIterator<Item = i64>,
+        filters: &SearchQuery,
+    ) -> Result<Vec<Favorite>> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            r#"
+            SELECT id, filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url, source_query,
+                   description, width, height, file_size,
+                   created_at, last_used, use_count, pinned, sort_position, phash,
+                   frame_count, duration_ms, fps, loop_count, has_alpha, deleted_at
+            FROM favorites
+            WHERE deleted_at IS NULL AND id IN ("#,
+        );
+        let mut list = query.separated(", ");
+        for id in ids {
+            list.push_bind(id);
+        }
+        query.push(")");
+        filters.push_conditions(&mut query, Utc::now());
+
+        let rows = query
+            .build_query_as::<FavoriteRow>()
+            .fetch_all(self.pool)
+            .await
+            .context("Failed to load favorites")?;
+
+        self.hydrate(rows).await
+    }
+
+    /// Run a parsed filter query (see [`SearchQuery`]). Ordered like `search`:
+    /// exact hits first, then favorites whose plain words only match with
+    /// typos but that pass every other filter.
+    pub async fn query(&self, query: &SearchQuery) -> Result<Vec<Favorite>> {
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            r#"
+            SELECT id, filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url, source_query,
+                   description, width, height, file_size,
+                   created_at, last_used, use_count, pinned, sort_position, phash,
+                   frame_count, duration_ms, fps, loop_count, has_alpha, deleted_at
+            FROM favorites
+            WHERE deleted_at IS NULL"#,
+        );
+        query.push_conditions(&mut builder, Utc::now());
+        builder.push(" ORDER BY pinned DESC, use_count DESC, created_at DESC");
+
+        let rows = builder
+            .build_query_as::<FavoriteRow>()
+            .fetch_all(self.pool)
+            .await
+            .context("Failed to query favorites")?;
+
+        let mut results = self.hydrate(rows).await?;
+        let (words, filters) = query.split_fuzzy_words();
+        if !words.is_empty() {
+            let fuzzy = self.fuzzy_search(&words, &filters, &results).await?;
+            results.extend(fuzzy);
+        }
+        Ok(results)
     }
 
     pub async fn update(&self, favorite: &Favorite) -> Result<()> {
         let id = favorite.id.context("Favorite must have an ID to update")?;
-        let tags_json = serde_json::to_string(&favorite.tags)?;
-        let custom_tags_json = serde_json::to_string(&favorite.custom_tags)?;
         let source = favorite.source.as_ref().map(|s| s.to_string());
         let last_used = favorite.last_used.map(|dt| dt.to_rfc3339());
+        let before = self.get_by_id(id).await?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
 
         sqlx::query(
             r#"
             UPDATE favorites
             SET filename = ?, filepath = ?, mp4_filepath = ?, gif_url = ?, media_type = ?, source = ?, source_id = ?,
-                source_url = ?, tags = ?, custom_tags = ?, description = ?,
-                width = ?, height = ?, file_size = ?, last_used = ?, use_count = ?
+                source_url = ?, source_query = ?, description = ?,
+                width = ?, height = ?, file_size = ?, last_used = ?, use_count = ?, pinned = ?
             WHERE id = ?
             "#,
         )
@@ -140,32 +355,382 @@ impl<'a> FavoritesDb<'a> {
         .bind(source)
         .bind(&favorite.source_id)
         .bind(&favorite.source_url)
-        .bind(tags_json)
-        .bind(custom_tags_json)
+        .bind(&favorite.source_query)
         .bind(&favorite.description)
         .bind(favorite.width)
         .bind(favorite.height)
         .bind(favorite.file_size)
         .bind(last_used)
         .bind(favorite.use_count)
+        .bind(favorite.pinned)
         .bind(id)
-        .execute(self.pool)
+        .execute(&mut *tx)
         .await
         .context("Failed to update favorite")?;
 
+        set_favorite_tags(&mut tx, id, &favorite.tags, &favorite.custom_tags).await?;
+        reindex(&mut tx, id).await?;
+        if let Some(before) = before {
+            // The trash has its own methods; `deleted_at` isn't written here.
+            let mut edited = changed_fields(&before, favorite);
+            edited.retain(|&field| field != SyncField::Trashed);
+            stamp_edits(&mut tx, id, &edited).await?;
+        }
+
+        tx.commit().await.context("Failed to commit favorite")?;
+        Ok(())
+    }
+
+    pub async fn set_pinned(&self, id: i64, pinned: bool) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        let result = sqlx::query("UPDATE favorites SET pinned = ? WHERE id = ? AND pinned != ?")
+            .bind(pinned)
+            .bind(id)
+            .bind(pinned)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to update pinned flag")?;
+        if result.rows_affected() > 0 {
+            stamp_edits(&mut tx, id, &[SyncField::Pinned]).await?;
+        }
+
+        tx.commit().await.context("Failed to commit pinned flag")?;
+        Ok(())
+    }
+
+    /// Move `id` to sit directly before `before_id` in the `get_all` order, or
+    /// to the end when `before_id` is `None`. Positions are renumbered in one
+    /// transaction so concurrent moves can't interleave.
+    pub async fn move_before(&self, id: i64, before_id: Option<i64>) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+
+        let mut ids: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM favorites
+            WHERE deleted_at IS NULL
+            ORDER BY pinned DESC, sort_position ASC, created_at DESC
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to load favorite order")?;
+
+        let from = ids
+            .iter()
+            .position(|&other| other == id)
+            .with_context(|| format!("Favorite {} not found", id))?;
+        ids.remove(from);
+
+        let to = match before_id {
+            Some(before_id) => ids
+                .iter()
+                .position(|&other| other == before_id)
+                .with_context(|| format!("Favorite {} not found", before_id))?,
+            None => ids.len(),
+        };
+        ids.insert(to, id);
+
+        for (position, id) in ids.iter().enumerate() {
+            sqlx::query("UPDATE favorites SET sort_position = ? WHERE id = ?")
+                .bind(position as i64)
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to update sort position")?;
+        }
+
+        tx.commit().await.context("Failed to commit reorder")?;
+        Ok(())
+    }
+
+    pub async fn set_phash(&self, id: i64, phash: &str) -> Result<()> {
+        sqlx::query("UPDATE favorites SET phash = ? WHERE id = ?")
+            .bind(phash)
+            .bind(id)
+            .execute(self.pool)
+            .await
+            .context("Failed to store perceptual hash")?;
+
+        Ok(())
+    }
+
+    /// Store an extracted palette and mark the favorite as checked, so
+    /// `missing_palettes` skips it even when `palette` is empty.
+    pub async fn set_palette(&self, id: i64, palette: &[PaletteColor]) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        set_favorite_palette(&mut tx, id, palette).await?;
+        sqlx::query("UPDATE favorites SET palette_checked_at = ? WHERE id = ?")
+            .bind(Utc::now().to_rfc3339())
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to mark palette as checked")?;
+
+        tx.commit().await.context("Failed to commit palette")?;
+        Ok(())
+    }
+
+    /// Store a probe of the favorite's file. Dimensions are only filled in
+    /// where missing — for Klipy favorites they come from the API.
+    pub async fn set_media_info(&self, id: i64, info: &MediaInfo) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE favorites
+            SET width = COALESCE(width, ?), height = COALESCE(height, ?),
+                frame_count = ?, duration_ms = ?, fps = ?, loop_count = ?, has_alpha = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(info.width)
+        .bind(info.height)
+        .bind(info.frame_count)
+        .bind(info.duration_ms.map(|ms| ms as i64))
+        .bind(info.fps())
+        .bind(info.loop_count)
+        .bind(info.has_alpha)
+        .bind(id)
+        .execute(self.pool)
+        .await
+        .context("Failed to store media info")?;
+
         Ok(())
     }
 
+    /// Favorites whose file hasn't been probed yet, as `(id, filepath)`.
+    pub async fn missing_media_info(&self) -> Result<Vec<(i64, String)>> {
+        sqlx::query_as(
+            r#"
+            SELECT id, filepath FROM favorites
+            WHERE filepath IS NOT NULL AND frame_count IS NULL
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(self.pool)
+        .await
+        .context("Failed to find favorites without media info")
+    }
+
+    /// Still images and GIFs that have no palette yet, as `(id, filepath)`.
+    pub async fn missing_palettes(&self) -> Result<Vec<(i64, String)>> {
+        sqlx::query_as(
+            r#"
+            SELECT id, filepath FROM favorites
+            WHERE filepath IS NOT NULL
+              AND media_type IN ('gif', 'image')
+              AND palette_checked_at IS NULL
+              AND NOT EXISTS (SELECT 1 FROM favorite_colors WHERE favorite_id = favorites.id)
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(self.pool)
+        .await
+        .context("Failed to find favorites without a palette")
+    }
+
+    /// Fold duplicates into `keep` and delete them. Tags are combined (custom
+    /// wins), use counts summed, the earliest `created_at` and latest
+    /// `last_used` kept, and the result is pinned if any copy was. Text
+    /// fields `keep` lacks are taken from the others. Files are the caller's
+    /// business. Returns the merged favorite.
+    pub async fn merge(&self, keep: i64, others: &[i64]) -> Result<Favorite> {
+        let mut all = self
+            .get_many(
+                std::iter::once(keep).chain(others.iter().copied()),
+                &SearchQuery::default(),
+            )
+            .await?;
+        let position = all
+            .iter()
+            .position(|f| f.id == Some(keep))
+            .with_context(|| format!("Favorite {} not found", keep))?;
+        let mut merged = all.swap_remove(position);
+        let before = merged.clone();
+
+        for other in &all {
+            merged.use_count += other.use_count;
+            merged.created_at = merged.created_at.min(other.created_at);
+            merged.last_used = merged.last_used.max(other.last_used);
+            merged.pinned |= other.pinned;
+            merged.description = merged.description.or_else(|| other.description.clone());
+            merged.source_query = merged.source_query.or_else(|| other.source_query.clone());
+            merged.custom_tags.extend(other.custom_tags.iter().cloned());
+            merged.tags.extend(other.tags.iter().cloned());
+        }
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        sqlx::query(
+            r#"
+            UPDATE favorites
+            SET use_count = ?, created_at = ?, last_used = ?, pinned = ?, description = ?, source_query = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(merged.use_count)
+        .bind(merged.created_at.to_rfc3339())
+        .bind(merged.last_used.map(|dt| dt.to_rfc3339()))
+        .bind(merged.pinned)
+        .bind(&merged.description)
+        .bind(&merged.source_query)
+        .bind(keep)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to update merged favorite")?;
+
+        for other in &all {
+            sqlx::query("DELETE FROM favorites WHERE id = ?")
+                .bind(other.id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to delete merged duplicate")?;
+        }
+
+        set_favorite_tags(&mut tx, keep, &merged.tags, &merged.custom_tags).await?;
+        reindex(&mut tx, keep).await?;
+        stamp_edits(&mut tx, keep, &changed_fields(&before, &merged)).await?;
+
+        tx.commit().await.context("Failed to commit merge")?;
+
+        self.get_by_id(keep)
+            .await?
+            .context("Merged favorite disappeared")
+    }
+
+    /// Move a favorite to the trash. Its files are the caller's business.
+    pub async fn trash(&self, id: i64) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        let result =
+            sqlx::query("UPDATE favorites SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+                .bind(Utc::now().to_rfc3339())
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to move favorite to trash")?;
+
+        anyhow::ensure!(
+            result.rows_affected() == 1,
+            "Favorite {} not found or already in the trash",
+            id
+        );
+        stamp_edits(&mut tx, id, &[SyncField::Trashed]).await?;
+        tx.commit().await.context("Failed to commit trash")?;
+        Ok(())
+    }
+
+    pub async fn restore(&self, id: i64) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        let result = sqlx::query(
+            "UPDATE favorites SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to restore favorite")?;
+
+        anyhow::ensure!(
+            result.rows_affected() == 1,
+            "Favorite {} is not in the trash",
+            id
+        );
+        stamp_edits(&mut tx, id, &[SyncField::Trashed]).await?;
+        tx.commit().await.context("Failed to commit restore")?;
+        Ok(())
+    }
+
+    /// Point whichever of the favorite's file paths is `from` at `to`, for a
+    /// file that had to be restored under a new name.
+    pub async fn relocate_file(&self, id: i64, from: &str, to: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE favorites
+            SET filepath = CASE WHEN filepath = ?1 THEN ?2 ELSE filepath END,
+                mp4_filepath = CASE WHEN mp4_filepath = ?1 THEN ?2 ELSE mp4_filepath END
+            WHERE id = ?3
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .bind(id)
+        .execute(self.pool)
+        .await
+        .context("Failed to update file path")?;
+
+        Ok(())
+    }
+
+    /// Trashed favorites, most recently deleted first.
+    pub async fn get_trash(&self) -> Result<Vec<Favorite>> {
+        let rows = sqlx::query_as::<_, FavoriteRow>(
+            r#"
+            SELECT id, filename, filepath, mp4_filepath, gif_url, media_type, source, source_id, source_url, source_query,
+                   description, width, height, file_size,
+                   created_at, last_used, use_count, pinned, sort_position, phash,
+                   frame_count, duration_ms, fps, loop_count, has_alpha, deleted_at
+            FROM favorites
+            WHERE deleted_at IS NOT NULL
+            ORDER BY deleted_at DESC
+            "#,
+        )
+        .fetch_all(self.pool)
+        .await
+        .context("Failed to fetch trash")?;
+
+        self.hydrate(rows).await
+    }
+
+    /// Delete a favorite for good, trashed or not.
     pub async fn delete(&self, id: i64) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
         sqlx::query("DELETE FROM favorites WHERE id = ?")
             .bind(id)
-            .execute(self.pool)
+            .execute(&mut *tx)
             .await
             .context("Failed to delete favorite")?;
 
+        // favorite_tags and search_grams rows go with it via ON DELETE CASCADE.
+        prune_orphans(&mut tx).await?;
+
+        tx.commit().await.context("Failed to commit delete")?;
         Ok(())
     }
 
+    /// Turn rows into favorites with their tags and palettes.
+    async fn hydrate(&self, rows: Vec<FavoriteRow>) -> Result<Vec<Favorite>> {
+        let mut favorites: Vec<Favorite> = rows.into_iter().map(Favorite::from).collect();
+        attach_tags(self.pool, &mut favorites).await?;
+        attach_palettes(self.pool, &mut favorites).await?;
+        Ok(favorites)
+    }
+
     pub async fn increment_use_count(&self, id: i64) -> Result<()> {
         let now = Utc::now().to_rfc3339();
 
@@ -197,8 +762,7 @@ struct FavoriteRow {
     source: Option<String>,
     source_id: Option<String>,
     source_url: Option<String>,
-    tags: String,
-    custom_tags: String,
+    source_query: Option<String>,
     description: Option<String>,
     width: Option<i32>,
     height: Option<i32>,
@@ -206,12 +770,19 @@ struct FavoriteRow {
     created_at: String,
     last_used: Option<String>,
     use_count: i32,
+    pinned: bool,
+    sort_position: i64,
+    phash: Option<String>,
+    frame_count: Option<i64>,
+    duration_ms: Option<i64>,
+    fps: Option<f64>,
+    loop_count: Option<i64>,
+    has_alpha: Option<bool>,
+    deleted_at: Option<String>,
 }
 
 impl From<FavoriteRow> for Favorite {
     fn from(row: FavoriteRow) -> Self {
-        let tags: Vec<String> = serde_json::from_str(&row.tags).unwrap_or_default();
-        let custom_tags: Vec<String> = serde_json::from_str(&row.custom_tags).unwrap_or_default();
         let media_type: MediaType = row.media_type.parse().unwrap_or(MediaType::Gif);
         let source: Option<Source> = row.source.and_then(|s| s.parse().ok());
         let created_at = DateTime::parse_from_rfc3339(&row.created_at)
@@ -222,6 +793,11 @@ impl From<FavoriteRow> for Favorite {
                 .map(|dt| dt.with_timezone(&Utc))
                 .ok()
         });
+        let deleted_at = row.deleted_at.and_then(|s| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|dt| dt.with_timezone(&Utc))
+                .ok()
+        });
 
         Favorite {
             id: Some(row.id),
@@ -233,8 +809,10 @@ impl From<FavoriteRow> for Favorite {
             source,
             source_id: row.source_id,
             source_url: row.source_url,
-            tags,
-            custom_tags,
+            source_query: row.source_query,
+            // Filled in from favorite_tags by `attach_tags`.
+            tags: Vec::new(),
+            custom_tags: Vec::new(),
             description: row.description,
             width: row.width,
             height: row.height,
@@ -242,6 +820,17 @@ impl From<FavoriteRow> for Favorite {
             created_at,
             last_used,
             use_count: row.use_count,
+            pinned: row.pinned,
+            sort_position: row.sort_position,
+            phash: row.phash,
+            // Filled in from favorite_colors by `attach_palettes`.
+            palette: Vec::new(),
+            frame_count: row.frame_count,
+            duration_ms: row.duration_ms,
+            fps: row.fps,
+            loop_count: row.loop_count,
+            has_alpha: row.has_alpha,
+            deleted_at,
         }
     }
 }
@@ -350,6 +939,94 @@ mod tests {
         assert!(retrieved.is_none());
     }
 
+    #[tokio::test]
+    async fn test_trash_hides_and_restores() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let cat = Favorite::new("cat.gif".to_string(), None, MediaType::Gif)
+            .with_tags(vec!["cat".to_string()]);
+        let cat_id = favorites_db.create(&cat).await.unwrap();
+        let dog = Favorite::new("dog.gif".to_string(), None, MediaType::Gif);
+        let dog_id = favorites_db.create(&dog).await.unwrap();
+        favorites_db.set_pinned(cat_id, true).await.unwrap();
+        favorites_db.increment_use_count(cat_id).await.unwrap();
+
+        favorites_db.trash(cat_id).await.unwrap();
+        assert!(favorites_db.trash(cat_id).await.is_err());
+
+        let ids = |favorites: Vec<Favorite>| -> Vec<i64> {
+            favorites.into_iter().filter_map(|f| f.id).collect()
+        };
+        assert_eq!(ids(favorites_db.get_all().await.unwrap()), vec![dog_id]);
+        assert!(favorites_db.get_pinned().await.unwrap().is_empty());
+        assert_eq!(
+            ids(favorites_db.get_recent(10).await.unwrap()),
+            vec![dog_id]
+        );
+        assert!(favorites_db
+            .get_most_recently_used()
+            .await
+            .unwrap()
+            .is_none());
+        assert!(favorites_db.search("cat").await.unwrap().is_empty());
+        let query: SearchQuery = "tag:cat".parse().unwrap();
+        assert!(favorites_db.query(&query).await.unwrap().is_empty());
+        assert!(db.tags().list().await.unwrap().is_empty());
+
+        let trash = favorites_db.get_trash().await.unwrap();
+        assert_eq!(ids(trash.clone()), vec![cat_id]);
+        assert!(trash[0].deleted_at.is_some());
+        // Still reachable by id, so it can be restored.
+        assert!(favorites_db.get_by_id(cat_id).await.unwrap().is_some());
+
+        favorites_db.restore(cat_id).await.unwrap();
+        assert!(favorites_db.restore(cat_id).await.is_err());
+        assert!(favorites_db.get_trash().await.unwrap().is_empty());
+        let restored = favorites_db.get_by_id(cat_id).await.unwrap().unwrap();
+        assert!(restored.deleted_at.is_none());
+        assert!(restored.pinned);
+        assert_eq!(restored.tags, vec!["cat".to_string()]);
+        assert_eq!(favorites_db.search("cat").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_create_with_sync_id() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let cat = Favorite::new("cat.gif".to_string(), None, MediaType::Gif);
+        let id = favorites_db
+            .create_with_sync_id(&cat, "from-laptop")
+            .await
+            .unwrap();
+
+        assert_eq!(db.sync().find("from-laptop").await.unwrap(), Some(id));
+    }
+
+    #[tokio::test]
+    async fn test_relocate_file() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let mut cat = Favorite::new(
+            "cat.gif".to_string(),
+            Some("/media/cat.gif".to_string()),
+            MediaType::Gif,
+        );
+        cat.mp4_filepath = Some("/media/cat.mp4".to_string());
+        let id = favorites_db.create(&cat).await.unwrap();
+
+        favorites_db
+            .relocate_file(id, "/media/cat.mp4", "/media/cat-1.mp4")
+            .await
+            .unwrap();
+
+        let cat = favorites_db.get_by_id(id).await.unwrap().unwrap();
+        assert_eq!(cat.filepath.as_deref(), Some("/media/cat.gif"));
+        assert_eq!(cat.mp4_filepath.as_deref(), Some("/media/cat-1.mp4"));
+    }
+
     #[tokio::test]
     async fn test_increment_use_count() {
         let (db, _temp) = create_test_db().await;
@@ -370,4 +1047,444 @@ mod tests {
         assert_eq!(updated.use_count, 2);
         assert!(updated.last_used.is_some());
     }
+
+    #[tokio::test]
+    async fn test_get_most_recently_used() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        assert!(favorites_db.get_most_recently_used().await.unwrap().is_none());
+
+        let mut ids = Vec::new();
+        for i in 0..2 {
+            let favorite = Favorite::new(
+                format!("test{}.gif", i),
+                Some(format!("/path/to/test{}.gif", i)),
+                MediaType::Gif,
+            );
+            ids.push(favorites_db.create(&favorite).await.unwrap());
+        }
+
+        favorites_db.increment_use_count(ids[1]).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        favorites_db.increment_use_count(ids[0]).await.unwrap();
+
+        let recent = favorites_db.get_most_recently_used().await.unwrap().unwrap();
+        assert_eq!(recent.id, Some(ids[0]));
+    }
+
+    #[tokio::test]
+    async fn test_get_recent_prefers_last_used() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let mut favorite = Favorite::new(
+                format!("test{}.gif", i),
+                Some(format!("/path/to/test{}.gif", i)),
+                MediaType::Gif,
+            );
+            favorite.created_at = Utc::now() - chrono::Duration::minutes(10 - i);
+            ids.push(favorites_db.create(&favorite).await.unwrap());
+        }
+
+        // The oldest one was just used, so it jumps to the front.
+        favorites_db.increment_use_count(ids[0]).await.unwrap();
+
+        let recent = favorites_db.get_recent(2).await.unwrap();
+        let recent_ids: Vec<_> = recent.iter().map(|f| f.id.unwrap()).collect();
+        assert_eq!(recent_ids, vec![ids[0], ids[2]]);
+    }
+
+    #[tokio::test]
+    async fn test_get_pinned() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let mut pinned = Favorite::new(
+            "pinned.gif".to_string(),
+            Some("/path/to/pinned.gif".to_string()),
+            MediaType::Gif,
+        );
+        pinned.pinned = true;
+        favorites_db.create(&pinned).await.unwrap();
+        favorites_db
+            .create(&Favorite::new(
+                "other.gif".to_string(),
+                Some("/path/to/other.gif".to_string()),
+                MediaType::Gif,
+            ))
+            .await
+            .unwrap();
+
+        let results = favorites_db.get_pinned().await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].filename, "pinned.gif");
+        assert!(results[0].pinned);
+    }
+
+    #[tokio::test]
+    async fn test_pinned_and_manual_order() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let mut ids = Vec::new();
+        for i in 0..4 {
+            let favorite = Favorite::new(
+                format!("test{}.gif", i),
+                Some(format!("/path/to/test{}.gif", i)),
+                MediaType::Gif,
+            );
+            ids.push(favorites_db.create(&favorite).await.unwrap());
+        }
+
+        let order = |favorites: Vec<Favorite>| -> Vec<i64> {
+            favorites.into_iter().map(|f| f.id.unwrap()).collect()
+        };
+
+        // New favorites go on top.
+        let all = favorites_db.get_all().await.unwrap();
+        assert_eq!(order(all), vec![ids[3], ids[2], ids[1], ids[0]]);
+
+        favorites_db.move_before(ids[0], Some(ids[2])).await.unwrap();
+        favorites_db.move_before(ids[3], None).await.unwrap();
+        let all = favorites_db.get_all().await.unwrap();
+        assert_eq!(order(all), vec![ids[0], ids[2], ids[1], ids[3]]);
+
+        // Pinned items lead every listing, whatever their position.
+        favorites_db.set_pinned(ids[1], true).await.unwrap();
+        let all = favorites_db.get_all().await.unwrap();
+        assert_eq!(order(all), vec![ids[1], ids[0], ids[2], ids[3]]);
+
+        let results = favorites_db.search("test").await.unwrap();
+        assert_eq!(results[0].id, Some(ids[1]));
+        assert!(results[0].pinned);
+
+        assert!(favorites_db.move_before(ids[0], Some(9999)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_matches_source_query() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let mut favorite = Favorite::new(
+            "abc123.gif".to_string(),
+            Some("/path/to/abc123.gif".to_string()),
+            MediaType::Gif,
+        );
+        favorite.source_query = Some("Deal With It".to_string());
+        let id = favorites_db.create(&favorite).await.unwrap();
+
+        let results = favorites_db.search("deal with").await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, Some(id));
+        assert_eq!(results[0].source_query.as_deref(), Some("Deal With It"));
+    }
+
+    #[tokio::test]
+    async fn test_query_filters() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let mut cat = Favorite::new("cat.gif".to_string(), None, MediaType::Gif)
+            .with_tags(vec!["cat".to_string(), "funny".to_string()]);
+        cat.source = Some(Source::Klipy);
+        cat.width = Some(480);
+        cat.use_count = 7;
+        let cat_id = favorites_db.create(&cat).await.unwrap();
+
+        let mut dog = Favorite::new("dog.mp4".to_string(), None, MediaType::Video)
+            .with_tags(vec!["dog".to_string(), "nsfw".to_string()]);
+        dog.description = Some("a very good boy".to_string());
+        dog.width = Some(320);
+        let dog_id = favorites_db.create(&dog).await.unwrap();
+
+        let mut old = Favorite::new("old cat.gif".to_string(), None, MediaType::Gif)
+            .with_tags(vec!["cat".to_string()]);
+        old.created_at = Utc::now() - chrono::Duration::days(90);
+        let old_id = favorites_db.create(&old).await.unwrap();
+        favorites_db.set_pinned(old_id, true).await.unwrap();
+
+        let ids = |query: &str| {
+            let query: SearchQuery = query.parse().unwrap();
+            let favorites_db = FavoritesDb::new(db.pool());
+            async move {
+                let mut ids: Vec<i64> = favorites_db
+                    .query(&query)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .filter_map(|f| f.id)
+                    .collect();
+                ids.sort();
+                ids
+            }
+        };
+
+        assert_eq!(ids("").await, vec![cat_id, dog_id, old_id]);
+        assert_eq!(ids("tag:cat").await, vec![cat_id, old_id]);
+        assert_eq!(ids("tag:cat added:<30d").await, vec![cat_id]);
+        assert_eq!(ids("added:>30d").await, vec![old_id]);
+        assert_eq!(ids("-tag:nsfw").await, vec![cat_id, old_id]);
+        assert_eq!(ids("type:video").await, vec![dog_id]);
+        assert_eq!(ids("source:klipy used:>5 width:>400").await, vec![cat_id]);
+        assert_eq!(ids("is:pinned").await, vec![old_id]);
+        assert_eq!(ids(r#""good boy""#).await, vec![dog_id]);
+        assert_eq!(ids(r#""boy good""#).await, Vec::<i64>::new());
+        assert_eq!(ids("100%").await, Vec::<i64>::new());
+    }
+
+    #[tokio::test]
+    async fn test_search_tolerates_typos() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let happy = Favorite::new("happy-cat.gif".to_string(), None, MediaType::Gif);
+        let happy_id = favorites_db.create(&happy).await.unwrap();
+        let tagged = Favorite::new("x1.gif".to_string(), None, MediaType::Gif)
+            .with_tags(vec!["hapy".to_string(), "cat".to_string()]);
+        let tagged_id = favorites_db.create(&tagged).await.unwrap();
+        let dog = Favorite::new("happy-dog.gif".to_string(), None, MediaType::Gif);
+        favorites_db.create(&dog).await.unwrap();
+
+        // Neither is a substring hit; the tagged one matches word for word,
+        // the other only with a typo, and the dog not at all.
+        let results = favorites_db.search("hapy cat").await.unwrap();
+        let ids: Vec<_> = results.iter().filter_map(|f| f.id).collect();
+        assert_eq!(ids, vec![tagged_id, happy_id]);
+
+        // Substring hits come before typo matches.
+        let results = favorites_db.search("happy").await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[2].id, Some(tagged_id));
+
+        // Tag edits keep the index current.
+        db.tags().rename("hapy", "grumpy").await.unwrap();
+        let results = favorites_db.search("grumpi").await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, Some(tagged_id));
+
+        favorites_db.delete(tagged_id).await.unwrap();
+        assert!(favorites_db.search("grumpi").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_query_tolerates_typos() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let happy = Favorite::new("happy-cat.gif".to_string(), None, MediaType::Gif);
+        let happy_id = favorites_db.create(&happy).await.unwrap();
+        let clip = Favorite::new("happy-cat.mp4".to_string(), None, MediaType::Video);
+        favorites_db.create(&clip).await.unwrap();
+        let grumpy = Favorite::new("grumpy-cat.gif".to_string(), None, MediaType::Gif)
+            .with_tags(vec!["hapy".to_string()]);
+        let grumpy_id = favorites_db.create(&grumpy).await.unwrap();
+
+        // What `search_local` does with the user's input.
+        let search = |input: &str| {
+            let query: SearchQuery = input.parse().unwrap();
+            let favorites_db = FavoritesDb::new(db.pool());
+            async move {
+                favorites_db
+                    .query(&query)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .filter_map(|f| f.id)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        // The tag is an exact hit and comes first; other filters still apply
+        // to the typo matches behind it.
+        assert_eq!(search("hapy type:gif").await, vec![grumpy_id, happy_id]);
+        assert_eq!(search("cat grumy").await, vec![grumpy_id]);
+        // Negated words and phrases stay exact.
+        assert_eq!(search("type:gif -hapy").await, vec![happy_id]);
+        assert!(search(r#""hapy cat""#).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_merge_combines_duplicates() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let mut keep = Favorite::new("big.gif".to_string(), None, MediaType::Gif)
+            .with_tags(vec!["cat".to_string()]);
+        keep.use_count = 2;
+        let keep_id = favorites_db.create(&keep).await.unwrap();
+
+        let mut dupe = Favorite::new("small.gif".to_string(), None, MediaType::Gif)
+            .with_tags(vec!["cat".to_string(), "smug".to_string()]);
+        dupe.custom_tags = vec!["mine".to_string()];
+        dupe.use_count = 3;
+        dupe.description = Some("smug cat".to_string());
+        dupe.created_at = Utc::now() - chrono::Duration::days(10);
+        let dupe_id = favorites_db.create(&dupe).await.unwrap();
+        favorites_db.set_pinned(dupe_id, true).await.unwrap();
+
+        let merged = favorites_db.merge(keep_id, &[dupe_id]).await.unwrap();
+        assert_eq!(merged.filename, "big.gif");
+        assert_eq!(merged.use_count, 5);
+        assert!(merged.pinned);
+        assert_eq!(merged.description.as_deref(), Some("smug cat"));
+        assert_eq!(merged.created_at.timestamp(), dupe.created_at.timestamp());
+        assert_eq!(merged.custom_tags, vec!["mine"]);
+        assert_eq!(merged.tags, vec!["cat", "smug"]);
+
+        assert!(favorites_db.get_by_id(dupe_id).await.unwrap().is_none());
+        assert_eq!(favorites_db.search("smug").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_color_filter() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let color = |r, g, b, weight| PaletteColor { r, g, b, weight };
+
+        let mut fire = Favorite::new("fire.gif".to_string(), None, MediaType::Gif);
+        fire.palette = vec![color(200, 30, 20, 0.6), color(10, 10, 10, 0.3)];
+        let fire_id = favorites_db.create(&fire).await.unwrap();
+
+        let mut sea = Favorite::new("sea.gif".to_string(), None, MediaType::Gif);
+        sea.palette = vec![color(20, 40, 240, 0.9), color(220, 20, 20, 0.05)];
+        let sea_id = favorites_db.create(&sea).await.unwrap();
+
+        let plain = Favorite::new(
+            "plain.gif".to_string(),
+            Some("/gifs/plain.gif".to_string()),
+            MediaType::Gif,
+        );
+        let plain_id = favorites_db.create(&plain).await.unwrap();
+
+        let ids = |query: &str| {
+            let query: SearchQuery = query.parse().unwrap();
+            let favorites_db = FavoritesDb::new(db.pool());
+            async move {
+                let mut ids: Vec<i64> = favorites_db
+                    .query(&query)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .filter_map(|f| f.id)
+                    .collect();
+                ids.sort();
+                ids
+            }
+        };
+
+        // The speck of red in the sea is too small to count.
+        assert_eq!(ids("color:red").await, vec![fire_id]);
+        assert_eq!(ids("color:black").await, vec![fire_id]);
+        assert_eq!(ids("color:blue").await, vec![sea_id]);
+        assert_eq!(ids("color:#0000ff~60").await, vec![sea_id]);
+        assert_eq!(ids("color:#0000ff~10").await, Vec::<i64>::new());
+        assert_eq!(ids("-color:red").await, vec![sea_id, plain_id]);
+
+        let loaded = favorites_db.get_by_id(fire_id).await.unwrap().unwrap();
+        assert_eq!(loaded.palette, fire.palette);
+
+        assert_eq!(
+            favorites_db.missing_palettes().await.unwrap(),
+            vec![(plain_id, "/gifs/plain.gif".to_string())]
+        );
+        favorites_db
+            .set_palette(plain_id, &[color(250, 250, 250, 1.0)])
+            .await
+            .unwrap();
+        assert_eq!(ids("colour:white").await, vec![plain_id]);
+        assert!(favorites_db.missing_palettes().await.unwrap().is_empty());
+
+        // A file no palette came out of is only tried once.
+        let blank = Favorite::new(
+            "blank.gif".to_string(),
+            Some("/gifs/blank.gif".to_string()),
+            MediaType::Gif,
+        );
+        let blank_id = favorites_db.create(&blank).await.unwrap();
+        assert_eq!(favorites_db.missing_palettes().await.unwrap().len(), 1);
+        favorites_db.set_palette(blank_id, &[]).await.unwrap();
+        assert!(favorites_db.missing_palettes().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_media_filters() {
+        let (db, _temp) = create_test_db().await;
+        let favorites_db = FavoritesDb::new(db.pool());
+
+        let probed = |frames, duration_ms, loop_count| MediaInfo {
+            width: Some(100),
+            height: Some(80),
+            frame_count: Some(frames),
+            duration_ms,
+            loop_count,
+            has_alpha: Some(false),
+        };
+
+        let short = Favorite::new("short.gif".to_string(), None, MediaType::Gif)
+            .with_media_info(&probed(12, Some(1200), Some(0)));
+        let short_id = favorites_db.create(&short).await.unwrap();
+
+        let long = Favorite::new("long.gif".to_string(), None, MediaType::Gif)
+            .with_media_info(&probed(90, Some(6000), Some(0)));
+        let long_id = favorites_db.create(&long).await.unwrap();
+
+        let once = Favorite::new("once.gif".to_string(), None, MediaType::Gif)
+            .with_media_info(&probed(10, Some(1000), Some(1)));
+        let once_id = favorites_db.create(&once).await.unwrap();
+
+        let still = Favorite::new(
+            "still.png".to_string(),
+            Some("/gifs/still.png".to_string()),
+            MediaType::Image,
+        );
+        let still_id = favorites_db.create(&still).await.unwrap();
+
+        let ids = |query: &str| {
+            let query: SearchQuery = query.parse().unwrap();
+            let favorites_db = FavoritesDb::new(db.pool());
+            async move {
+                let mut ids: Vec<i64> = favorites_db
+                    .query(&query)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .filter_map(|f| f.id)
+                    .collect();
+                ids.sort();
+                ids
+            }
+        };
+
+        assert_eq!(ids("is:looping duration:<2s").await, vec![short_id]);
+        assert_eq!(ids("is:animated").await, vec![short_id, long_id, once_id]);
+        assert_eq!(ids("-duration:<2s").await, vec![long_id]);
+        assert_eq!(ids("fps:>12").await, vec![long_id]);
+        assert_eq!(ids("is:transparent").await, Vec::<i64>::new());
+
+        assert_eq!(
+            favorites_db.missing_media_info().await.unwrap(),
+            vec![(still_id, "/gifs/still.png".to_string())]
+        );
+        let info = MediaInfo {
+            width: Some(64),
+            height: Some(64),
+            frame_count: Some(1),
+            has_alpha: Some(true),
+            ..Default::default()
+        };
+        favorites_db.set_media_info(still_id, &info).await.unwrap();
+        assert_eq!(ids("is:transparent width:64").await, vec![still_id]);
+        assert!(favorites_db.missing_media_info().await.unwrap().is_empty());
+
+        let loaded = favorites_db.get_by_id(short_id).await.unwrap().unwrap();
+        assert_eq!(loaded.duration_ms, Some(1200));
+        assert_eq!(loaded.fps, Some(10.0));
+        assert_eq!(loaded.loop_count, Some(0));
+        assert_eq!((loaded.width, loaded.height), (Some(100), Some(80)));
+    }
 }
@@ -1,7 +1,15 @@
+mod colors;
 pub mod connection;
 pub mod favorites;
+mod search_index;
+pub mod search_query;
 pub mod settings;
+pub mod sync;
+pub mod tags;
 
 pub use connection::*;
 pub use favorites::*;
+pub use search_query::*;
 pub use settings::*;
+pub use sync::*;
+pub use tags::*;
@@ -0,0 +1,250 @@
+//! Typo-tolerant search over favorites.
+//!
+//! Every word of a favorite's filename, description, originating search and
+//! tags is split into padded trigrams (`cat` → `$ca`, `cat`, `at$`) and stored
+//! in `search_grams`. A query word pulls candidate words that share enough
+//! trigrams with it, and those are confirmed with an edit distance.
+
+use anyhow::{Context, Result};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use std::collections::{BTreeSet, HashMap};
+
+/// Words shorter than this aren't indexed or fuzzily matched — one typo in a
+/// two-letter word could be anything.
+const MIN_WORD_CHARS: usize = 2;
+
+/// Similarity given to a word that merely starts with the query word, so
+/// `danc` finds `dancing` without beating a real match.
+const PREFIX_SIMILARITY: f64 = 0.9;
+
+/// Lowercased alphanumeric words in `text`, deduplicated.
+pub fn words(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_WORD_CHARS)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Padded trigrams of a single word.
+pub fn trigrams(word: &str) -> BTreeSet<String> {
+    let padded: Vec<char> = format!("${}$", word).chars().collect();
+    padded
+        .windows(3)
+        .map(|window| window.iter().collect())
+        .collect()
+}
+
+/// How many edits a query word of this length may be off by.
+pub fn max_typos(word: &str) -> usize {
+    match word.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+/// Optimal string alignment distance: insertions, deletions, substitutions
+/// and swaps of adjacent characters each cost one.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let width = b.len() + 1;
+    let mut d = vec![0usize; (a.len() + 1) * width];
+
+    for i in 0..=a.len() {
+        d[i * width] = i;
+    }
+    for (j, cell) in d.iter_mut().take(width).enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (d[(i - 1) * width + j] + 1)
+                .min(d[i * width + j - 1] + 1)
+                .min(d[(i - 1) * width + j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(d[(i - 2) * width + j - 2] + 1);
+            }
+            d[i * width + j] = best;
+        }
+    }
+    d[a.len() * width + b.len()]
+}
+
+/// How well an indexed word matches a query word, from 0 to 1, or `None` if
+/// it's too far off to count.
+pub fn word_similarity(query: &str, word: &str) -> Option<f64> {
+    if query == word {
+        return Some(1.0);
+    }
+    if word.starts_with(query) {
+        return Some(PREFIX_SIMILARITY);
+    }
+    let distance = edit_distance(query, word);
+    if distance > max_typos(query) {
+        return None;
+    }
+    let longest = query.chars().count().max(word.chars().count());
+    Some(1.0 - distance as f64 / longest as f64)
+}
+
+/// Rewrite the index rows for one favorite from its current text and tags.
+pub(super) async fn reindex(conn: &mut SqliteConnection, favorite_id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM search_grams WHERE favorite_id = ?")
+        .bind(favorite_id)
+        .execute(&mut *conn)
+        .await
+        .context("Failed to clear search index")?;
+
+    let texts: Vec<(Option<String>,)> = sqlx::query_as(
+        r#"
+        SELECT filename FROM favorites WHERE id = ?1
+        UNION ALL SELECT description FROM favorites WHERE id = ?1
+        UNION ALL SELECT source_query FROM favorites WHERE id = ?1
+        UNION ALL SELECT t.name FROM favorite_tags ft JOIN tags t ON t.id = ft.tag_id
+                  WHERE ft.favorite_id = ?1
+        "#,
+    )
+    .bind(favorite_id)
+    .fetch_all(&mut *conn)
+    .await
+    .context("Failed to load favorite for indexing")?;
+
+    let words: BTreeSet<String> = texts
+        .iter()
+        .filter_map(|(text,)| text.as_deref())
+        .flat_map(words)
+        .collect();
+    let rows: Vec<(String, &String)> = words
+        .iter()
+        .flat_map(|word| trigrams(word).into_iter().map(move |gram| (gram, word)))
+        .collect();
+
+    // Stay well under SQLite's bound-parameter limit.
+    for chunk in rows.chunks(300) {
+        let mut insert = QueryBuilder::<Sqlite>::new(
+            "INSERT OR IGNORE INTO search_grams (gram, word, favorite_id) ",
+        );
+        insert.push_values(chunk, |mut row, (gram, word)| {
+            row.push_bind(gram).push_bind(*word).push_bind(favorite_id);
+        });
+        insert
+            .build()
+            .execute(&mut *conn)
+            .await
+            .context("Failed to update search index")?;
+    }
+    Ok(())
+}
+
+/// Index favorites that have no rows yet — everything saved before the index
+/// existed.
+pub(super) async fn index_missing(pool: &SqlitePool) -> Result<()> {
+    let ids: Vec<i64> = sqlx::query_scalar(
+        r#"
+        SELECT id FROM favorites
+        WHERE NOT EXISTS (SELECT 1 FROM search_grams WHERE favorite_id = favorites.id)
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to find unindexed favorites")?;
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await.context("Failed to start transaction")?;
+    for id in ids {
+        reindex(&mut tx, id).await?;
+    }
+    tx.commit().await.context("Failed to commit search index")?;
+    Ok(())
+}
+
+/// Favorites matching every word of `query` within a few typos, with a score
+/// in (0, 1] — the mean of each query word's best match. Unsorted.
+pub(super) async fn fuzzy_matches(pool: &SqlitePool, query: &str) -> Result<HashMap<i64, f64>> {
+    let query_words = words(query);
+    let mut scores: Option<HashMap<i64, f64>> = None;
+
+    for query_word in &query_words {
+        let grams = trigrams(query_word);
+        // Each edit destroys at most four of the query's trigrams (a swap
+        // touches two characters), so fewer shared than this can't match.
+        let min_shared = grams.len().saturating_sub(4 * max_typos(query_word)).max(1);
+
+        let mut candidates = QueryBuilder::<Sqlite>::new(
+            "SELECT word, favorite_id FROM search_grams WHERE gram IN (",
+        );
+        let mut list = candidates.separated(", ");
+        for gram in &grams {
+            list.push_bind(gram);
+        }
+        candidates.push(") GROUP BY word, favorite_id HAVING COUNT(*) >= ");
+        candidates.push_bind(min_shared as i64);
+
+        let rows: Vec<(String, i64)> = candidates
+            .build_query_as()
+            .fetch_all(pool)
+            .await
+            .context("Failed to search index")?;
+
+        let mut best: HashMap<i64, f64> = HashMap::new();
+        for (word, favorite_id) in rows {
+            if let Some(similarity) = word_similarity(query_word, &word) {
+                let entry = best.entry(favorite_id).or_default();
+                *entry = entry.max(similarity);
+            }
+        }
+
+        scores = Some(match scores {
+            None => best,
+            Some(so_far) => so_far
+                .into_iter()
+                .filter_map(|(id, total)| best.get(&id).map(|s| (id, total + s)))
+                .collect(),
+        });
+    }
+
+    let count = query_words.len() as f64;
+    Ok(scores
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(id, total)| (id, total / count))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words_and_trigrams() {
+        assert_eq!(
+            words("Happy-Cat_dance.GIF a"),
+            ["cat", "dance", "gif", "happy"].map(String::from).into()
+        );
+        assert_eq!(
+            trigrams("cat"),
+            ["$ca", "cat", "at$"].map(String::from).into()
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("hapy", "happy"), 1);
+        assert_eq!(edit_distance("teh", "the"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_word_similarity() {
+        assert_eq!(word_similarity("cat", "cat"), Some(1.0));
+        assert_eq!(word_similarity("danc", "dancing"), Some(PREFIX_SIMILARITY));
+        assert_eq!(word_similarity("hapy", "happy"), Some(0.8));
+        assert_eq!(word_similarity("cat", "dog"), None);
+        assert_eq!(word_similarity("ct", "at"), None);
+    }
+}
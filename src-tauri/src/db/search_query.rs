@@ -0,0 +1,747 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use sqlx::{QueryBuilder, Sqlite};
+use std::fmt;
+use std::str::FromStr;
+
+use super::search_index::words;
+use crate::models::{normalize_tag, parse_hex_color, MediaType, NamedColor, Source};
+
+/// A parsed local-search query: free text plus filters, all ANDed together.
+///
+/// ```text
+/// tag:cat -tag:nsfw type:video source:klipy is:pinned used:>5 added:<30d
+/// width:>400 color:red color:#ff8800~40 "exact phrase"
+/// is:looping duration:<2s frames:>10 fps:>=24 is:transparent
+/// ```
+///
+/// Any term can be negated with a leading `-`, and filter values can be
+/// quoted (`tag:"funny cat"`).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SearchQuery {
+    pub terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub negated: bool,
+    pub filter: Filter,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// Substring of the filename, description, originating query or a tag.
+    Text(String),
+    Tag(String),
+    Type(MediaType),
+    Source(Source),
+    Is(Flag),
+    UseCount(Comparison<i64>),
+    Width(Comparison<i64>),
+    Height(Comparison<i64>),
+    Added(AddedFilter),
+    Color(ColorFilter),
+    /// Length of one play-through, in milliseconds.
+    Duration(Comparison<i64>),
+    Frames(Comparison<i64>),
+    Fps(Comparison<i64>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flag {
+    Pinned,
+    Used,
+    Tagged,
+    Animated,
+    /// Loops forever, the way reaction GIFs are meant to.
+    Looping,
+    Transparent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+impl Op {
+    fn sql(self) -> &'static str {
+        match self {
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Eq => "=",
+            Op::Ge => ">=",
+            Op::Gt => ">",
+        }
+    }
+
+    /// The same comparison with its sides swapped: `x < y` ⇔ `y > x`.
+    fn flipped(self) -> Op {
+        match self {
+            Op::Lt => Op::Gt,
+            Op::Le => Op::Ge,
+            Op::Eq => Op::Eq,
+            Op::Ge => Op::Le,
+            Op::Gt => Op::Lt,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison<T> {
+    pub op: Op,
+    pub value: T,
+}
+
+/// `added:<30d` is an age ("less than 30 days old"); `added:>2024-01-01` is a
+/// date ("after New Year"). The two read in opposite directions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddedFilter {
+    Age(Comparison<Duration>),
+    Date(Comparison<NaiveDate>),
+}
+
+/// A palette color to look for: by name (`color:red`) or near a hex value
+/// (`color:#ff8800`, optionally `~tolerance` as a distance in RGB space).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorFilter {
+    Named(NamedColor),
+    Near { rgb: (u8, u8, u8), tolerance: u32 },
+}
+
+/// How close a hex color has to be when no `~tolerance` is given.
+const DEFAULT_COLOR_TOLERANCE: u32 = 60;
+
+/// Palette entries covering less of the image than this don't count — every
+/// GIF has a few stray red pixels.
+const MIN_COLOR_WEIGHT: f32 = 0.1;
+
+/// Why a query didn't parse. `position` is the char offset of the offending
+/// term so the UI can point at it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at position {})", self.message, self.position)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl FromStr for SearchQuery {
+    type Err = QueryError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let terms = tokenize(input)?
+            .into_iter()
+            .map(parse_term)
+            .collect::<Result<_, _>>()?;
+        Ok(SearchQuery { terms })
+    }
+}
+
+impl SearchQuery {
+    /// Append this query's conditions, each as ` AND (…)`, to a statement
+    /// that already has a `WHERE` clause. Every value is bound, never spliced
+    /// into the SQL.
+    pub fn push_conditions(&self, builder: &mut QueryBuilder<'_, Sqlite>, now: DateTime<Utc>) {
+        for term in &self.terms {
+            builder.push(" AND ");
+            if term.negated {
+                builder.push("NOT ");
+            }
+            builder.push("(");
+            push_filter(builder, &term.filter, now);
+            builder.push(")");
+        }
+    }
+
+    /// Split off the plain words — unnegated text terms of a single word —
+    /// which may also match with typos. Returns them space-separated, plus a
+    /// query of everything else. Phrases and text with punctuation in it
+    /// (`"good boy"`, `100%`) only ever match exactly.
+    pub fn split_fuzzy_words(&self) -> (String, SearchQuery) {
+        let mut fuzzy = Vec::new();
+        let mut rest = Vec::new();
+        for term in &self.terms {
+            match &term.filter {
+                Filter::Text(text) if !term.negated && is_single_word(text) => {
+                    fuzzy.push(text.to_lowercase())
+                }
+                _ => rest.push(term.clone()),
+            }
+        }
+        (fuzzy.join(" "), SearchQuery { terms: rest })
+    }
+}
+
+fn is_single_word(text: &str) -> bool {
+    let lowered = text.to_lowercase();
+    let words = words(&lowered);
+    words.len() == 1 && words.contains(&lowered)
+}
+
+fn push_filter(builder: &mut QueryBuilder<'_, Sqlite>, filter: &Filter, now: DateTime<Utc>) {
+    match filter {
+        Filter::Text(text) => {
+            let pattern = format!("%{}%", escape_like(&text.to_lowercase()));
+            builder.push("LOWER(filename) LIKE ");
+            builder.push_bind(pattern.clone());
+            builder.push(" ESCAPE '\\' OR LOWER(COALESCE(description, '')) LIKE ");
+            builder.push_bind(pattern.clone());
+            builder.push(" ESCAPE '\\' OR LOWER(COALESCE(source_query, '')) LIKE ");
+            builder.push_bind(pattern.clone());
+            builder.push(
+                " ESCAPE '\\' OR EXISTS (SELECT 1 FROM favorite_tags ft \
+                 JOIN tags t ON t.id = ft.tag_id \
+                 WHERE ft.favorite_id = favorites.id AND t.name LIKE ",
+            );
+            builder.push_bind(pattern);
+            builder.push(" ESCAPE '\\')");
+        }
+        Filter::Tag(tag) => {
+            builder.push(
+                "EXISTS (SELECT 1 FROM favorite_tags ft \
+                 JOIN tags t ON t.id = ft.tag_id \
+                 WHERE ft.favorite_id = favorites.id AND t.name = ",
+            );
+            builder.push_bind(tag.clone());
+            builder.push(")");
+        }
+        Filter::Type(media_type) => {
+            builder.push("media_type = ");
+            builder.push_bind(media_type.to_string());
+        }
+        Filter::Source(source) => {
+            builder.push("source = ");
+            builder.push_bind(source.to_string());
+        }
+        Filter::Is(Flag::Pinned) => {
+            builder.push("pinned = 1");
+        }
+        Filter::Is(Flag::Used) => {
+            builder.push("use_count > 0");
+        }
+        Filter::Is(Flag::Tagged) => {
+            builder.push(
+                "EXISTS (SELECT 1 FROM favorite_tags ft WHERE ft.favorite_id = favorites.id)",
+            );
+        }
+        Filter::Is(Flag::Animated) => {
+            builder.push("COALESCE(frame_count, 0) > 1");
+        }
+        Filter::Is(Flag::Looping) => {
+            builder.push("COALESCE(loop_count = 0, 0)");
+        }
+        Filter::Is(Flag::Transparent) => {
+            builder.push("COALESCE(has_alpha, 0) = 1");
+        }
+        Filter::UseCount(cmp) => push_comparison(builder, "use_count", cmp),
+        Filter::Width(cmp) => push_comparison(builder, "COALESCE(width, 0)", cmp),
+        Filter::Height(cmp) => push_comparison(builder, "COALESCE(height, 0)", cmp),
+        // Left NULL for stills and unprobed files, which then match neither
+        // the filter nor its negation.
+        Filter::Duration(cmp) => push_comparison(builder, "duration_ms", cmp),
+        Filter::Frames(cmp) => push_comparison(builder, "frame_count", cmp),
+        Filter::Fps(cmp) => push_comparison(builder, "fps", cmp),
+        Filter::Added(AddedFilter::Age(cmp)) => {
+            // Younger than N days means created *after* now - N days.
+            let threshold = (now - cmp.value).to_rfc3339();
+            builder.push("created_at ");
+            builder.push(cmp.op.flipped().sql());
+            builder.push(" ");
+            builder.push_bind(threshold);
+        }
+        Filter::Added(AddedFilter::Date(cmp)) => {
+            // created_at is RFC 3339; compare on its date part.
+            builder.push("substr(created_at, 1, 10) ");
+            builder.push(cmp.op.sql());
+            builder.push(" ");
+            builder.push_bind(cmp.value.format("%Y-%m-%d").to_string());
+        }
+        Filter::Color(color) => {
+            builder.push(
+                "EXISTS (SELECT 1 FROM favorite_colors c \
+                 WHERE c.favorite_id = favorites.id AND c.weight >= ",
+            );
+            builder.push_bind(MIN_COLOR_WEIGHT);
+            push_color(builder, color);
+            builder.push(")");
+        }
+    }
+}
+
+fn push_color(builder: &mut QueryBuilder<'_, Sqlite>, color: &ColorFilter) {
+    match *color {
+        ColorFilter::Named(name) => {
+            let range = name.range();
+            // Ranges with start > end wrap through 0° (red).
+            if let Some((start, end)) = range.hue {
+                builder.push(" AND (c.hue >= ");
+                builder.push_bind(start);
+                builder.push(if start > end { " OR " } else { " AND " });
+                builder.push("c.hue < ");
+                builder.push_bind(end);
+                builder.push(")");
+            }
+            builder.push(" AND c.saturation BETWEEN ");
+            builder.push_bind(range.saturation.0);
+            builder.push(" AND ");
+            builder.push_bind(range.saturation.1);
+            builder.push(" AND c.lightness BETWEEN ");
+            builder.push_bind(range.lightness.0);
+            builder.push(" AND ");
+            builder.push_bind(range.lightness.1);
+        }
+        ColorFilter::Near {
+            rgb: (r, g, b),
+            tolerance,
+        } => {
+            builder.push(" AND ");
+            for (i, (column, value)) in [("c.r", r), ("c.g", g), ("c.b", b)].into_iter().enumerate()
+            {
+                if i > 0 {
+                    builder.push(" + ");
+                }
+                builder.push(format!("({} - ", column));
+                builder.push_bind(i64::from(value));
+                builder.push(format!(") * ({} - ", column));
+                builder.push_bind(i64::from(value));
+                builder.push(")");
+            }
+            builder.push(" <= ");
+            builder.push_bind(i64::from(tolerance) * i64::from(tolerance));
+        }
+    }
+}
+
+fn push_comparison(builder: &mut QueryBuilder<'_, Sqlite>, column: &str, cmp: &Comparison<i64>) {
+    builder.push(column);
+    builder.push(" ");
+    builder.push(cmp.op.sql());
+    builder.push(" ");
+    builder.push_bind(cmp.value);
+}
+
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// One whitespace-separated chunk of the input, with quotes already removed.
+#[derive(Debug)]
+struct Token {
+    position: usize,
+    negated: bool,
+    /// `Some("tag")` for `tag:value`.
+    key: Option<String>,
+    value: String,
+    /// Whether any part was quoted — quoted text is never read as a filter.
+    quoted: bool,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let position = i;
+        let negated = chars[i] == '-' && chars.get(i + 1).is_some_and(|c| !c.is_whitespace());
+        if negated {
+            i += 1;
+        }
+
+        let mut key = None;
+        let mut value = String::new();
+        let mut quoted = false;
+
+        while i < chars.len() && !chars[i].is_whitespace() {
+            match chars[i] {
+                '"' => {
+                    let start = i;
+                    i += 1;
+                    while i < chars.len() && chars[i] != '"' {
+                        value.push(chars[i]);
+                        i += 1;
+                    }
+                    if i == chars.len() {
+                        return Err(QueryError {
+                            message: "unterminated quote".into(),
+                            position: start,
+                        });
+                    }
+                    quoted = true;
+                    i += 1;
+                }
+                ':' if key.is_none() && !quoted && !value.is_empty() => {
+                    key = Some(std::mem::take(&mut value).to_lowercase());
+                    i += 1;
+                }
+                c => {
+                    value.push(c);
+                    i += 1;
+                }
+            }
+        }
+
+        tokens.push(Token {
+            position,
+            negated,
+            key,
+            value,
+            quoted,
+        });
+    }
+
+    Ok(tokens)
+}
+
+fn parse_term(token: Token) -> Result<Term, QueryError> {
+    let error = |message: String| QueryError {
+        message,
+        position: token.position,
+    };
+
+    let Some(key) = token.key.as_deref() else {
+        if token.value.trim().is_empty() {
+            return Err(error("empty search term".into()));
+        }
+        return Ok(Term {
+            negated: token.negated,
+            filter: Filter::Text(token.value),
+        });
+    };
+
+    let value = token.value.trim();
+    if value.is_empty() {
+        return Err(error(format!("'{}:' needs a value", key)));
+    }
+    let lowered = value.to_lowercase();
+
+    let filter = match key {
+        "tag" => Filter::Tag(normalize_tag(value).ok_or_else(|| error("empty tag".into()))?),
+        "type" => Filter::Type(match lowered.as_str() {
+            "gif" => MediaType::Gif,
+            "image" | "img" => MediaType::Image,
+            "video" | "mp4" => MediaType::Video,
+            _ => {
+                return Err(error(format!(
+                    "unknown type '{}' (expected gif, image or video)",
+                    value
+                )))
+            }
+        }),
+        "source" => Filter::Source(lowered.parse().map_err(|_| {
+            error(format!(
+                "unknown source '{}' (expected klipy, local or upload)",
+                value
+            ))
+        })?),
+        "is" => Filter::Is(match lowered.as_str() {
+            "pinned" => Flag::Pinned,
+            "used" => Flag::Used,
+            "tagged" => Flag::Tagged,
+            "animated" => Flag::Animated,
+            "looping" | "loop" => Flag::Looping,
+            "transparent" => Flag::Transparent,
+            _ => {
+                return Err(error(format!(
+                    "unknown flag '{}' (expected pinned, used, tagged, animated, looping or transparent)",
+                    value
+                )))
+            }
+        }),
+        "used" => Filter::UseCount(parse_number(value).map_err(error)?),
+        "width" => Filter::Width(parse_number(value).map_err(error)?),
+        "height" => Filter::Height(parse_number(value).map_err(error)?),
+        "added" => Filter::Added(parse_added(value).map_err(error)?),
+        "color" | "colour" => Filter::Color(parse_color(value).map_err(error)?),
+        "duration" | "length" => Filter::Duration(parse_duration(value).map_err(error)?),
+        "frames" => Filter::Frames(parse_number(value).map_err(error)?),
+        "fps" => Filter::Fps(parse_number(value).map_err(error)?),
+        _ if token.quoted => {
+            // `"re:zero"` style quoting already kept the colon out of the key,
+            // but `re:"zero"` lands here — read it back as plain text.
+            Filter::Text(format!("{}:{}", key, token.value))
+        }
+        _ => {
+            return Err(error(format!(
+                "unknown filter '{}:' (try quoting it to search for the text)",
+                key
+            )))
+        }
+    };
+
+    Ok(Term {
+        negated: token.negated,
+        filter,
+    })
+}
+
+fn split_op(value: &str) -> (Op, &str) {
+    for (prefix, op) in [
+        (">=", Op::Ge),
+        ("<=", Op::Le),
+        (">", Op::Gt),
+        ("<", Op::Lt),
+        ("=", Op::Eq),
+    ] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (op, rest);
+        }
+    }
+    (Op::Eq, value)
+}
+
+fn parse_number(value: &str) -> Result<Comparison<i64>, String> {
+    let (op, number) = split_op(value);
+    let value = number
+        .parse::<i64>()
+        .map_err(|_| format!("'{}' is not a number", number))?;
+    Ok(Comparison { op, value })
+}
+
+/// `2s`, `1.5s`, `500ms`; a bare number is seconds. In milliseconds.
+fn parse_duration(value: &str) -> Result<Comparison<i64>, String> {
+    let (op, rest) = split_op(value);
+    let (amount, scale) = if let Some(ms) = rest.strip_suffix("ms") {
+        (ms, 1.0)
+    } else {
+        (rest.strip_suffix('s').unwrap_or(rest), 1000.0)
+    };
+    let amount: f64 = amount
+        .parse()
+        .ok()
+        .filter(|n: &f64| n.is_finite() && *n >= 0.0)
+        .ok_or_else(|| format!("'{}' is not a duration like 2s or 500ms", rest))?;
+    Ok(Comparison {
+        op,
+        value: (amount * scale).round() as i64,
+    })
+}
+
+fn parse_color(value: &str) -> Result<ColorFilter, String> {
+    if let Ok(name) = value.parse::<NamedColor>() {
+        return Ok(ColorFilter::Named(name));
+    }
+
+    let (hex, tolerance) = match value.split_once('~') {
+        Some((hex, tolerance)) => {
+            let tolerance = tolerance
+                .parse()
+                .map_err(|_| format!("'{}' is not a color tolerance", tolerance))?;
+            (hex, tolerance)
+        }
+        None => (value, DEFAULT_COLOR_TOLERANCE),
+    };
+    let rgb = parse_hex_color(hex).ok_or_else(|| {
+        format!(
+            "unknown color '{}' (use a name like red or a hex value like #ff8800)",
+            hex
+        )
+    })?;
+    Ok(ColorFilter::Near { rgb, tolerance })
+}
+
+fn parse_added(value: &str) -> Result<AddedFilter, String> {
+    let (op, rest) = split_op(value);
+
+    if let Ok(date) = NaiveDate::parse_from_str(rest, "%Y-%m-%d") {
+        return Ok(AddedFilter::Date(Comparison { op, value: date }));
+    }
+
+    let unit_at = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("'{}' needs a unit, e.g. 30d", rest))?;
+    let (amount, unit) = rest.split_at(unit_at);
+    let amount: i64 = amount.parse().map_err(|_| {
+        format!(
+            "'{}' is not a duration like 30d or a date like 2024-01-31",
+            rest
+        )
+    })?;
+    let duration = match unit {
+        "h" => Duration::hours(amount),
+        "d" => Duration::days(amount),
+        "w" => Duration::weeks(amount),
+        "m" => Duration::days(amount * 30),
+        "y" => Duration::days(amount * 365),
+        _ => {
+            return Err(format!(
+                "unknown unit '{}' (expected h, d, w, m or y)",
+                unit
+            ))
+        }
+    };
+    Ok(AddedFilter::Age(Comparison {
+        op,
+        value: duration,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Vec<Term> {
+        input.parse::<SearchQuery>().unwrap().terms
+    }
+
+    fn term(negated: bool, filter: Filter) -> Term {
+        Term { negated, filter }
+    }
+
+    #[test]
+    fn test_parses_filters_and_text() {
+        let terms = parse(
+            r#"tag:Cat -tag:"NSFW stuff" type:video source:klipy is:pinned used:>5 width:>=400 "exact phrase" hello"#,
+        );
+        assert_eq!(
+            terms,
+            vec![
+                term(false, Filter::Tag("cat".into())),
+                term(true, Filter::Tag("nsfw stuff".into())),
+                term(false, Filter::Type(MediaType::Video)),
+                term(false, Filter::Source(Source::Klipy)),
+                term(false, Filter::Is(Flag::Pinned)),
+                term(
+                    false,
+                    Filter::UseCount(Comparison {
+                        op: Op::Gt,
+                        value: 5
+                    })
+                ),
+                term(
+                    false,
+                    Filter::Width(Comparison {
+                        op: Op::Ge,
+                        value: 400
+                    })
+                ),
+                term(false, Filter::Text("exact phrase".into())),
+                term(false, Filter::Text("hello".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parses_added_ages_and_dates() {
+        assert_eq!(
+            parse("added:<30d added:>2024-01-31"),
+            vec![
+                term(
+                    false,
+                    Filter::Added(AddedFilter::Age(Comparison {
+                        op: Op::Lt,
+                        value: Duration::days(30)
+                    }))
+                ),
+                term(
+                    false,
+                    Filter::Added(AddedFilter::Date(Comparison {
+                        op: Op::Gt,
+                        value: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap()
+                    }))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parses_colors() {
+        assert_eq!(
+            parse("color:Red colour:#f80 color:00ff00~25"),
+            vec![
+                term(false, Filter::Color(ColorFilter::Named(NamedColor::Red))),
+                term(
+                    false,
+                    Filter::Color(ColorFilter::Near {
+                        rgb: (255, 136, 0),
+                        tolerance: DEFAULT_COLOR_TOLERANCE
+                    })
+                ),
+                term(
+                    false,
+                    Filter::Color(ColorFilter::Near {
+                        rgb: (0, 255, 0),
+                        tolerance: 25
+                    })
+                ),
+            ]
+        );
+        assert!("color:reddish".parse::<SearchQuery>().is_err());
+        assert!("color:#ff0000~far".parse::<SearchQuery>().is_err());
+    }
+
+    #[test]
+    fn test_parses_media_filters() {
+        let cmp = |op, value| Comparison { op, value };
+        assert_eq!(
+            parse("is:loop duration:<2s length:>=1.5 duration:500ms frames:>10 fps:24"),
+            vec![
+                term(false, Filter::Is(Flag::Looping)),
+                term(false, Filter::Duration(cmp(Op::Lt, 2000))),
+                term(false, Filter::Duration(cmp(Op::Ge, 1500))),
+                term(false, Filter::Duration(cmp(Op::Eq, 500))),
+                term(false, Filter::Frames(cmp(Op::Gt, 10))),
+                term(false, Filter::Fps(cmp(Op::Eq, 24))),
+            ]
+        );
+        assert!("duration:short".parse::<SearchQuery>().is_err());
+        assert!("duration:-2s".parse::<SearchQuery>().is_err());
+    }
+
+    #[test]
+    fn test_quoted_colon_is_text() {
+        assert_eq!(
+            parse(r#""re:zero""#),
+            vec![term(false, Filter::Text("re:zero".into()))]
+        );
+        assert_eq!(
+            parse("- -x"),
+            vec![
+                term(false, Filter::Text("-".into())),
+                term(true, Filter::Text("x".into()))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_point_at_the_bad_term() {
+        let err = "cat tpye:gif".parse::<SearchQuery>().unwrap_err();
+        assert_eq!(err.position, 4);
+        assert!(err.message.contains("tpye"));
+
+        let err = r#"cat "open"#.parse::<SearchQuery>().unwrap_err();
+        assert_eq!(err.message, "unterminated quote");
+        assert_eq!(err.position, 4);
+
+        assert!("used:lots".parse::<SearchQuery>().is_err());
+        assert!("added:<30x".parse::<SearchQuery>().is_err());
+        assert!("type:sticker".parse::<SearchQuery>().is_err());
+        assert!("tag:".parse::<SearchQuery>().is_err());
+    }
+
+    #[test]
+    fn test_values_are_bound_not_spliced() {
+        let query: SearchQuery = r#"tag:x "'; DROP TABLE favorites; --""#.parse().unwrap();
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT id FROM favorites WHERE 1");
+        query.push_conditions(&mut builder, Utc::now());
+        let sql = builder.sql();
+        assert!(!sql.contains("DROP"));
+        assert!(sql.contains("?"));
+    }
+}
@@ -1,13 +1,25 @@
 use anyhow::{Context, Result};
-use sqlx::SqlitePool;
+use chrono::Utc;
+use sqlx::{Sqlite, SqliteConnection, SqlitePool};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
-use crate::models::Settings;
+use crate::models::{HotkeyAction, SettingKey, Settings, SettingsProfile};
 
 /// Settings-table key for the per-install Klipy `customer_id`. Not part of
 /// `Settings` because it's never user-editable — generated once, kept forever.
 const CUSTOMER_ID_KEY: &str = "klipy_customer_id";
 
+/// Settings-table key for this device's id in the sync folder. Generated
+/// once, like the customer id.
+const DEVICE_ID_KEY: &str = "sync_device_id";
+
+/// Pre-multi-hotkey installs stored a single toggle shortcut under this key.
+const LEGACY_HOTKEY_KEY: &str = "hotkey";
+
+/// Settings-table key naming the active profile, if any.
+const ACTIVE_PROFILE_KEY: &str = "active_profile";
+
 pub struct SettingsDb<'a> {
     pool: &'a SqlitePool,
 }
@@ -20,12 +32,21 @@ impl<'a> SettingsDb<'a> {
     /// Read the persisted Klipy customer_id, generating one on first call.
     /// Stable across launches; reset only by clearing the settings table.
     pub async fn get_or_create_customer_id(&self) -> Result<String> {
+        self.get_or_create_id(CUSTOMER_ID_KEY).await
+    }
+
+    /// This device's id in the sync folder, generated on first call.
+    pub async fn get_or_create_device_id(&self) -> Result<String> {
+        self.get_or_create_id(DEVICE_ID_KEY).await
+    }
+
+    async fn get_or_create_id(&self, key: &str) -> Result<String> {
         let existing: Option<(String,)> =
             sqlx::query_as("SELECT value FROM settings WHERE key = ?")
-                .bind(CUSTOMER_ID_KEY)
+                .bind(key)
                 .fetch_optional(self.pool)
                 .await
-                .context("Failed to read customer_id")?;
+                .with_context(|| format!("Failed to read {}", key))?;
 
         if let Some((value,)) = existing {
             // Stored as a JSON string (matches the rest of the table's format).
@@ -35,12 +56,17 @@ impl<'a> SettingsDb<'a> {
         }
 
         let id = Uuid::new_v4().to_string();
-        self.update_key(CUSTOMER_ID_KEY, serde_json::to_string(&id)?)
+        self.update_key(key, serde_json::to_string(&id)?)
             .await?;
         Ok(id)
     }
 
     pub async fn get(&self) -> Result<Settings> {
+        self.get_with_defaults(Settings::default()).await
+    }
+
+    /// Like `get`, taking keys that were never saved from `defaults`.
+    pub async fn get_with_defaults(&self, defaults: Settings) -> Result<Settings> {
         let rows = sqlx::query_as::<_, (String, String)>("SELECT key, value FROM settings")
             .fetch_all(self.pool)
             .await
@@ -48,144 +74,304 @@ impl<'a> SettingsDb<'a> {
 
         if rows.is_empty() {
             // Return default settings if none exist
-            return Ok(Settings::default());
+            return Ok(defaults);
         }
 
         // Deserialize from key-value pairs
-        let mut settings = Settings::default();
+        let mut settings = defaults;
+        let mut legacy_hotkey: Option<String> = None;
+        let mut has_hotkeys = false;
         for (key, value) in rows {
-            match key.as_str() {
-                "hotkey" => {
-                    settings.hotkey = serde_json::from_str(&value).unwrap_or(settings.hotkey)
-                }
-                "window_width" => {
-                    settings.window_width =
-                        serde_json::from_str(&value).unwrap_or(settings.window_width)
-                }
-                "window_height" => {
-                    settings.window_height =
-                        serde_json::from_str(&value).unwrap_or(settings.window_height)
-                }
-                "max_item_width" => {
-                    settings.max_item_width =
-                        serde_json::from_str(&value).unwrap_or(settings.max_item_width)
-                }
-                "close_after_selection" => {
-                    settings.close_after_selection =
-                        serde_json::from_str(&value).unwrap_or(settings.close_after_selection)
-                }
-                "launch_at_startup" => {
-                    settings.launch_at_startup =
-                        serde_json::from_str(&value).unwrap_or(settings.launch_at_startup)
-                }
-                "theme" => settings.theme = serde_json::from_str(&value).unwrap_or(settings.theme),
-                "clipboard_mode" => {
-                    settings.clipboard_mode =
-                        serde_json::from_str(&value).unwrap_or(settings.clipboard_mode)
-                }
-                "clipboard_format" => {
-                    settings.clipboard_format =
-                        serde_json::from_str(&value).unwrap_or(settings.clipboard_format)
-                }
-                "show_ads" => {
-                    settings.show_ads = serde_json::from_str(&value).unwrap_or(settings.show_ads)
-                }
-                "tile_size" => {
-                    settings.tile_size =
-                        serde_json::from_str(&value).unwrap_or(settings.tile_size)
-                }
-                "always_on_top" => {
-                    settings.always_on_top =
-                        serde_json::from_str(&value).unwrap_or(settings.always_on_top)
-                }
-                _ => {}
+            if key == LEGACY_HOTKEY_KEY {
+                legacy_hotkey = serde_json::from_str(&value).ok();
+                continue;
+            }
+            // Reserved keys (customer id, device id) aren't settings.
+            let Ok(setting) = key.parse::<SettingKey>() else {
+                continue;
+            };
+            let parsed = serde_json::from_str(&value)
+                .map_err(|e| format!("{}: {}", setting, e))
+                .and_then(|value| settings.set(setting, value));
+            match parsed {
+                Ok(()) => has_hotkeys |= setting == SettingKey::Hotkeys,
+                Err(e) => eprintln!(
+                    "Warning: ignoring stored setting, using the default instead: {}",
+                    e
+                ),
+            }
+        }
+
+        if !has_hotkeys {
+            if let Some(hotkey) = legacy_hotkey {
+                settings.hotkeys.insert(HotkeyAction::TogglePicker, hotkey);
             }
         }
 
         Ok(settings)
     }
 
+    /// Write every setting in one transaction: either all of them change or
+    /// none do. Rows that aren't settings (the customer and device ids, the
+    /// active profile) are left alone.
     pub async fn save(&self, settings: &Settings) -> Result<()> {
-        // Delete all existing settings
-        sqlx::query("DELETE FROM settings")
-            .execute(self.pool)
-            .await
-            .context("Failed to clear settings")?;
-
-        // Insert all settings
-        let pairs = vec![
-            ("hotkey", serde_json::to_string(&settings.hotkey)?),
-            (
-                "window_width",
-                serde_json::to_string(&settings.window_width)?,
-            ),
-            (
-                "window_height",
-                serde_json::to_string(&settings.window_height)?,
-            ),
-            (
-                "max_item_width",
-                serde_json::to_string(&settings.max_item_width)?,
-            ),
-            (
-                "close_after_selection",
-                serde_json::to_string(&settings.close_after_selection)?,
-            ),
-            (
-                "launch_at_startup",
-                serde_json::to_string(&settings.launch_at_startup)?,
-            ),
-            ("theme", serde_json::to_string(&settings.theme)?),
-            (
-                "clipboard_mode",
-                serde_json::to_string(&settings.clipboard_mode)?,
-            ),
-            (
-                "clipboard_format",
-                serde_json::to_string(&settings.clipboard_format)?,
-            ),
-            ("show_ads", serde_json::to_string(&settings.show_ads)?),
-            ("tile_size", serde_json::to_string(&settings.tile_size)?),
-            (
-                "always_on_top",
-                serde_json::to_string(&settings.always_on_top)?,
-            ),
-        ];
-
-        for (key, value) in pairs {
-            sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?)")
-                .bind(key)
-                .bind(value)
-                .execute(self.pool)
-                .await
-                .context("Failed to insert setting")?;
-        }
+        self.save_keys(settings, &SettingKey::ALL).await
+    }
 
+    /// Like `save`, but only `keys`; the rest keep their stored value or stay
+    /// unsaved.
+    pub async fn save_keys(&self, settings: &Settings, keys: &[SettingKey]) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+        write_settings(&mut tx, settings, keys).await?;
+        tx.commit().await.context("Failed to commit settings")?;
         Ok(())
     }
 
     pub async fn update_key(&self, key: &str, value: String) -> Result<()> {
-        sqlx::query(
+        upsert(self.pool, key, value)
+            .await
+            .context("Failed to update setting")
+    }
+
+    /// Every profile, by name.
+    pub async fn profiles(&self) -> Result<Vec<SettingsProfile>> {
+        let active = self.active_profile().await?;
+        let rows: Vec<(String, Option<String>)> = sqlx::query_as(
+            "SELECT name, default_collection FROM settings_profiles ORDER BY name COLLATE NOCASE",
+        )
+        .fetch_all(self.pool)
+        .await
+        .context("Failed to fetch profiles")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(name, default_collection)| SettingsProfile {
+                active: active.as_deref() == Some(name.as_str()),
+                name,
+                default_collection,
+            })
+            .collect())
+    }
+
+    pub async fn active_profile(&self) -> Result<Option<String>> {
+        let value: Option<String> =
+            sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+                .bind(ACTIVE_PROFILE_KEY)
+                .fetch_optional(self.pool)
+                .await
+                .context("Failed to read active profile")?;
+        Ok(value.and_then(|value| serde_json::from_str(&value).ok()))
+    }
+
+    /// Add a profile holding the per-profile keys of `settings`. With no
+    /// profile active yet, it becomes the active one, since the live
+    /// settings are already its settings. Returns `false` if the name is
+    /// taken.
+    pub async fn create_profile(
+        &self,
+        name: &str,
+        default_collection: Option<&str>,
+        settings: &Settings,
+    ) -> Result<bool> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        let result = sqlx::query(
             r#"
-            INSERT INTO settings (key, value) VALUES (?, ?)
-            ON CONFLICT(key) DO UPDATE SET value = excluded.value
+            INSERT INTO settings_profiles (name, settings, default_collection, created_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(name) DO NOTHING
             "#,
         )
-        .bind(key)
-        .bind(value)
-        .execute(self.pool)
+        .bind(name)
+        .bind(profile_values(settings))
+        .bind(default_collection)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut *tx)
         .await
-        .context("Failed to update setting")?;
+        .context("Failed to create profile")?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
 
-        Ok(())
+        sqlx::query("INSERT OR IGNORE INTO settings (key, value) VALUES (?, ?)")
+            .bind(ACTIVE_PROFILE_KEY)
+            .bind(serde_json::to_string(name)?)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to set active profile")?;
+
+        tx.commit().await.context("Failed to commit profile")?;
+        Ok(true)
+    }
+
+    /// Returns `false` if there's no such profile.
+    pub async fn set_profile_collection(
+        &self,
+        name: &str,
+        default_collection: Option<&str>,
+    ) -> Result<bool> {
+        let result =
+            sqlx::query("UPDATE settings_profiles SET default_collection = ? WHERE name = ?")
+                .bind(default_collection)
+                .bind(name)
+                .execute(self.pool)
+                .await
+                .context("Failed to update profile")?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete a profile. Deleting the active one leaves the live settings
+    /// as they are, with no profile active. Returns `false` if there's no
+    /// such profile.
+    pub async fn delete_profile(&self, name: &str) -> Result<bool> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        let result = sqlx::query("DELETE FROM settings_profiles WHERE name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to delete profile")?;
+        sqlx::query("DELETE FROM settings WHERE key = ? AND value = ?")
+            .bind(ACTIVE_PROFILE_KEY)
+            .bind(serde_json::to_string(name)?)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to clear active profile")?;
+
+        tx.commit().await.context("Failed to commit profile")?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Make `name` the active profile, in one transaction: the live settings
+    /// are first written back to the outgoing profile so nothing changed
+    /// while it was active is lost, then the incoming profile's keys replace
+    /// them. Returns the new live settings, or `None` if there's no such
+    /// profile.
+    pub async fn switch_profile(&self, name: &str) -> Result<Option<Settings>> {
+        let mut settings = self.get().await?;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        let values: Option<String> =
+            sqlx::query_scalar("SELECT settings FROM settings_profiles WHERE name = ?")
+                .bind(name)
+                .fetch_optional(&mut *tx)
+                .await
+                .context("Failed to read profile")?;
+        let Some(values) = values else {
+            return Ok(None);
+        };
+
+        let outgoing: Option<String> =
+            sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+                .bind(ACTIVE_PROFILE_KEY)
+                .fetch_optional(&mut *tx)
+                .await
+                .context("Failed to read active profile")?;
+        if let Some(outgoing) = outgoing.and_then(|value| serde_json::from_str::<String>(&value).ok())
+        {
+            sqlx::query("UPDATE settings_profiles SET settings = ? WHERE name = ?")
+                .bind(profile_values(&settings))
+                .bind(outgoing)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to save outgoing profile")?;
+        }
+
+        let values: BTreeMap<String, serde_json::Value> = serde_json::from_str(&values)
+            .with_context(|| format!("Corrupt settings in profile {}", name))?;
+        for (key, value) in values {
+            let Ok(key) = key.parse::<SettingKey>() else {
+                continue;
+            };
+            if !key.is_per_profile() {
+                continue;
+            }
+            if let Err(e) = settings.set(key, value) {
+                eprintln!("Warning: profile {}: ignoring {}", name, e);
+            }
+        }
+
+        write_settings(&mut tx, &settings, &SettingKey::ALL).await?;
+        upsert(&mut *tx, ACTIVE_PROFILE_KEY, serde_json::to_string(name)?)
+            .await
+            .context("Failed to set active profile")?;
+
+        tx.commit().await.context("Failed to commit profile switch")?;
+        Ok(Some(settings))
     }
 }
 
+/// A profile's stored settings: its per-profile keys as a JSON object.
+fn profile_values(settings: &Settings) -> String {
+    let values: BTreeMap<&str, serde_json::Value> = SettingKey::ALL
+        .into_iter()
+        .filter(|key| key.is_per_profile())
+        .map(|key| (key.as_str(), settings.get(key)))
+        .collect();
+    serde_json::Value::from_iter(values).to_string()
+}
+
+async fn upsert<'e, E>(executor: E, key: &str, value: String) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO settings (key, value) VALUES (?, ?)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value
+        "#,
+    )
+    .bind(key)
+    .bind(value)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+async fn write_settings(
+    conn: &mut SqliteConnection,
+    settings: &Settings,
+    keys: &[SettingKey],
+) -> Result<()> {
+    for &key in keys {
+        upsert(&mut *conn, key.as_str(), settings.get(key).to_string())
+            .await
+            .with_context(|| format!("Failed to save {}", key))?;
+    }
+    if !keys.contains(&SettingKey::Hotkeys) {
+        return Ok(());
+    }
+
+    // `hotkeys` supersedes it.
+    sqlx::query("DELETE FROM settings WHERE key = ?")
+        .bind(LEGACY_HOTKEY_KEY)
+        .execute(&mut *conn)
+        .await
+        .context("Failed to remove legacy hotkey")?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::db::Database;
-    use crate::models::Theme;
+    use crate::models::{ClipboardMode, Theme, WindowPlacement};
     use tempfile::TempDir;
 
     async fn create_test_db() -> (Database, TempDir) {
@@ -214,12 +400,14 @@ mod tests {
         let mut settings = Settings::default();
         settings.window_width = 1024;
         settings.theme = Theme::Dark;
+        settings.window_placement = WindowPlacement::Cursor;
 
         settings_db.save(&settings).await.unwrap();
 
         let retrieved = settings_db.get().await.unwrap();
         assert_eq!(retrieved.window_width, 1024);
         assert_eq!(retrieved.theme, Theme::Dark);
+        assert_eq!(retrieved.window_placement, WindowPlacement::Cursor);
     }
 
     #[tokio::test]
@@ -233,6 +421,224 @@ mod tests {
             .unwrap();
 
         let settings = settings_db.get().await.unwrap();
-        assert_eq!(settings.hotkey, "Ctrl+Shift+G");
+        assert_eq!(
+            settings.hotkeys.get(&HotkeyAction::TogglePicker).map(String::as_str),
+            Some("Ctrl+Shift+G")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hotkeys_round_trip() {
+        let (db, _temp) = create_test_db().await;
+        let settings_db = SettingsDb::new(db.pool());
+
+        let mut settings = Settings::default();
+        settings
+            .hotkeys
+            .insert(HotkeyAction::OpenTrending, "Ctrl+Alt+T".to_string());
+        settings_db.save(&settings).await.unwrap();
+
+        // A stale legacy row must not clobber the saved map.
+        settings_db
+            .update_key("hotkey", serde_json::to_string("Ctrl+Alt+X").unwrap())
+            .await
+            .unwrap();
+
+        let retrieved = settings_db.get().await.unwrap();
+        assert_eq!(retrieved.hotkeys, settings.hotkeys);
+    }
+
+    #[tokio::test]
+    async fn test_bad_stored_value_falls_back_to_default() {
+        let (db, _temp) = create_test_db().await;
+        let settings_db = SettingsDb::new(db.pool());
+
+        settings_db
+            .update_key("window_width", "\"wide\"".to_string())
+            .await
+            .unwrap();
+        settings_db
+            .update_key("theme", serde_json::to_string("dark").unwrap())
+            .await
+            .unwrap();
+
+        let settings = settings_db.get().await.unwrap();
+        assert_eq!(settings.window_width, Settings::default().window_width);
+        assert_eq!(settings.theme, Theme::Dark);
+    }
+
+    #[tokio::test]
+    async fn test_save_keeps_reserved_keys() {
+        let (db, _temp) = create_test_db().await;
+        let settings_db = SettingsDb::new(db.pool());
+
+        let customer_id = settings_db.get_or_create_customer_id().await.unwrap();
+        let device_id = settings_db.get_or_create_device_id().await.unwrap();
+        settings_db.save(&Settings::default()).await.unwrap();
+
+        assert_eq!(
+            settings_db.get_or_create_customer_id().await.unwrap(),
+            customer_id
+        );
+        assert_eq!(
+            settings_db.get_or_create_device_id().await.unwrap(),
+            device_id
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_saves_and_updates() {
+        use crate::commands::settings::{store_setting, store_settings, SETTINGS_LOCK};
+        use crate::services::ConfigFile;
+        use std::sync::Arc;
+
+        let (db, temp) = create_test_db().await;
+        let db = Arc::new(db);
+        let config_file = Arc::new(ConfigFile::new(temp.path().join("config")));
+        let customer_id = db.settings().get_or_create_customer_id().await.unwrap();
+        let base = Settings {
+            theme: Theme::Dark,
+            show_ads: false,
+            ..Settings::default()
+        };
+
+        // Queue saves and updates on the lock in a known order: the lock is
+        // fair, so each must see the last one's write and keep what it didn't
+        // touch.
+        let guard = SETTINGS_LOCK.lock().await;
+        let mut tasks = Vec::new();
+        for i in 0..10 {
+            let (saver_db, saver_file) = (db.clone(), config_file.clone());
+            let settings = Settings {
+                window_width: 1000 + i,
+                ..base.clone()
+            };
+            tasks.push(tokio::spawn(async move {
+                store_settings(&saver_db, &saver_file, settings, |_| Ok(())).await
+            }));
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+
+            let (updater_db, updater_file) = (db.clone(), config_file.clone());
+            tasks.push(tokio::spawn(async move {
+                let value = i.to_string();
+                store_setting(
+                    &updater_db,
+                    &updater_file,
+                    "tray_recent_count",
+                    &value,
+                    |_| Ok(()),
+                )
+                .await
+            }));
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        }
+        drop(guard);
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        // Only keys that were ever changed are stored.
+        let mut rows: Vec<String> = sqlx::query_scalar("SELECT key FROM settings")
+            .fetch_all(db.pool())
+            .await
+            .unwrap();
+        rows.sort();
+        assert_eq!(
+            rows,
+            [CUSTOMER_ID_KEY, "show_ads", "theme", "tray_recent_count", "window_width"]
+        );
+
+        // The last save's values, with the update queued after it on top.
+        let expected = Settings {
+            window_width: 1009,
+            tray_recent_count: 9,
+            ..base
+        };
+        let settings = db.settings().get().await.unwrap();
+        assert_eq!(
+            serde_json::to_value(&settings).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+        assert_eq!(
+            db.settings().get_or_create_customer_id().await.unwrap(),
+            customer_id
+        );
+    }
+
+    #[tokio::test]
+    async fn test_switching_profiles_keeps_each_ones_changes() {
+        let (db, _temp) = create_test_db().await;
+        let settings_db = SettingsDb::new(db.pool());
+
+        let personal = Settings::default();
+        settings_db.save(&personal).await.unwrap();
+        assert!(settings_db
+            .create_profile("personal", None, &personal)
+            .await
+            .unwrap());
+        let work = Settings {
+            show_ads: false,
+            clipboard_mode: ClipboardMode::Url,
+            ..Settings::default()
+        };
+        assert!(settings_db
+            .create_profile("work", Some("tag:work"), &work)
+            .await
+            .unwrap());
+        assert!(!settings_db
+            .create_profile("work", None, &work)
+            .await
+            .unwrap());
+        assert_eq!(
+            settings_db.active_profile().await.unwrap().as_deref(),
+            Some("personal")
+        );
+
+        // Edited while personal is active: one per-profile key, one not.
+        let edited = Settings {
+            theme: Theme::Dark,
+            backup_keep_count: 3,
+            ..personal
+        };
+        settings_db.save(&edited).await.unwrap();
+
+        let switched = settings_db.switch_profile("work").await.unwrap().unwrap();
+        assert!(!switched.show_ads);
+        assert_eq!(switched.clipboard_mode, ClipboardMode::Url);
+        assert_eq!(switched.theme, Theme::System);
+        assert_eq!(switched.backup_keep_count, 3);
+        assert_eq!(settings_db.get().await.unwrap().clipboard_mode, ClipboardMode::Url);
+
+        let back = settings_db
+            .switch_profile("personal")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(back.show_ads);
+        assert_eq!(back.theme, Theme::Dark);
+
+        let profiles = settings_db.profiles().await.unwrap();
+        let active: Vec<(&str, bool)> = profiles
+            .iter()
+            .map(|profile| (profile.name.as_str(), profile.active))
+            .collect();
+        assert_eq!(active, [("personal", true), ("work", false)]);
+        assert_eq!(profiles[1].default_collection.as_deref(), Some("tag:work"));
+        assert!(settings_db.switch_profile("gaming").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_deleting_the_active_profile_clears_it() {
+        let (db, _temp) = create_test_db().await;
+        let settings_db = SettingsDb::new(db.pool());
+
+        settings_db
+            .create_profile("work", None, &Settings::default())
+            .await
+            .unwrap();
+        assert!(settings_db.delete_profile("work").await.unwrap());
+        assert!(!settings_db.delete_profile("work").await.unwrap());
+        assert_eq!(settings_db.active_profile().await.unwrap(), None);
+        assert!(settings_db.profiles().await.unwrap().is_empty());
     }
 }
@@ -0,0 +1,240 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::{BTreeMap, HashMap};
+
+use uuid::Uuid;
+
+use crate::models::{Favorite, FieldState, SyncField};
+
+/// The id a new favorite is known by on every device. Klipy favorites use
+/// the Klipy id, so the same GIF saved on two devices is one favorite (this
+/// matches migration 013); anything else gets a random one.
+pub(crate) fn new_sync_id(favorite: &Favorite) -> String {
+    match (&favorite.source, &favorite.source_id) {
+        (Some(source), Some(source_id)) => format!("{}:{}", source, source_id),
+        _ => Uuid::new_v4().simple().to_string(),
+    }
+}
+
+/// Record that `fields` of a favorite were just edited on this device. Every
+/// write that can change a synced field calls this, the way they reindex.
+pub(super) async fn stamp_edits(
+    conn: &mut SqliteConnection,
+    favorite_id: i64,
+    fields: &[SyncField],
+) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    for field in fields {
+        sqlx::query(
+            r#"
+            INSERT INTO sync_edits (favorite_id, field, edited_at) VALUES (?, ?, ?)
+            ON CONFLICT(favorite_id, field) DO UPDATE SET edited_at = excluded.edited_at
+            "#,
+        )
+        .bind(favorite_id)
+        .bind(field.as_str())
+        .bind(&now)
+        .execute(&mut *conn)
+        .await
+        .context("Failed to record edit")?;
+    }
+    Ok(())
+}
+
+/// The synced fields that differ between two versions of a favorite.
+pub(super) fn changed_fields(before: &Favorite, after: &Favorite) -> Vec<SyncField> {
+    SyncField::ALL
+        .into_iter()
+        .filter(|field| field.value_of(before) != field.value_of(after))
+        .collect()
+}
+
+/// A favorite as of the last sync. See the `sync_shadow` table.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncShadow {
+    pub fields: BTreeMap<SyncField, FieldState>,
+    pub use_count: i64,
+}
+
+/// Bookkeeping for folder sync: favorites' shared ids, what was last
+/// synced, how far other devices' logs have been read, and deletions.
+pub struct SyncDb<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> SyncDb<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Local id to sync id, for every favorite including trashed ones.
+    pub async fn sync_ids(&self) -> Result<HashMap<i64, String>> {
+        let rows: Vec<(i64, String)> =
+            sqlx::query_as("SELECT id, sync_id FROM favorites WHERE sync_id IS NOT NULL")
+                .fetch_all(self.pool)
+                .await
+                .context("Failed to fetch sync ids")?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// When each edited field was last written on this device, keyed by
+    /// local id and field.
+    pub async fn edit_times(&self) -> Result<HashMap<(i64, SyncField), DateTime<Utc>>> {
+        let rows: Vec<(i64, String, String)> =
+            sqlx::query_as("SELECT favorite_id, field, edited_at FROM sync_edits")
+                .fetch_all(self.pool)
+                .await
+                .context("Failed to fetch edit times")?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, field, edited_at)| {
+                let field = SyncField::ALL.into_iter().find(|f| f.as_str() == field)?;
+                let at = DateTime::parse_from_rfc3339(&edited_at).ok()?;
+                Some(((id, field), at.with_timezone(&Utc)))
+            })
+            .collect())
+    }
+
+    pub async fn find(&self, sync_id: &str) -> Result<Option<i64>> {
+        sqlx::query_scalar("SELECT id FROM favorites WHERE sync_id = ?")
+            .bind(sync_id)
+            .fetch_optional(self.pool)
+            .await
+            .context("Failed to look up favorite by sync id")
+    }
+
+    /// Add uses recorded on another device, keeping the later `last_used`.
+    pub async fn add_uses(
+        &self,
+        id: i64,
+        count: i64,
+        last_used: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE favorites
+            SET use_count = use_count + ?,
+                last_used = NULLIF(MAX(COALESCE(last_used, ''), COALESCE(?, '')), '')
+            WHERE id = ?
+            "#,
+        )
+        .bind(count)
+        .bind(last_used.map(|dt| dt.to_rfc3339()))
+        .bind(id)
+        .execute(self.pool)
+        .await
+        .context("Failed to add uses")?;
+        Ok(())
+    }
+
+    pub async fn shadows(&self) -> Result<HashMap<String, SyncShadow>> {
+        let rows: Vec<(String, String, i64)> =
+            sqlx::query_as("SELECT sync_id, fields, use_count FROM sync_shadow")
+                .fetch_all(self.pool)
+                .await
+                .context("Failed to fetch sync shadow")?;
+
+        rows.into_iter()
+            .map(|(sync_id, fields, use_count)| {
+                let fields = serde_json::from_str(&fields)
+                    .with_context(|| format!("Corrupt sync shadow for {}", sync_id))?;
+                Ok((sync_id, SyncShadow { fields, use_count }))
+            })
+            .collect()
+    }
+
+    pub async fn shadow(&self, sync_id: &str) -> Result<Option<SyncShadow>> {
+        let row: Option<(String, i64)> =
+            sqlx::query_as("SELECT fields, use_count FROM sync_shadow WHERE sync_id = ?")
+                .bind(sync_id)
+                .fetch_optional(self.pool)
+                .await
+                .context("Failed to fetch sync shadow")?;
+
+        row.map(|(fields, use_count)| {
+            let fields = serde_json::from_str(&fields)
+                .with_context(|| format!("Corrupt sync shadow for {}", sync_id))?;
+            Ok(SyncShadow { fields, use_count })
+        })
+        .transpose()
+    }
+
+    pub async fn save_shadow(&self, sync_id: &str, shadow: &SyncShadow) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO sync_shadow (sync_id, fields, use_count) VALUES (?, ?, ?)
+            ON CONFLICT(sync_id) DO UPDATE SET fields = excluded.fields, use_count = excluded.use_count
+            "#,
+        )
+        .bind(sync_id)
+        .bind(serde_json::to_string(&shadow.fields)?)
+        .bind(shadow.use_count)
+        .execute(self.pool)
+        .await
+        .context("Failed to save sync shadow")?;
+        Ok(())
+    }
+
+    /// Forget a favorite deleted for good, and remember not to recreate it.
+    pub async fn bury(&self, sync_id: &str) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        sqlx::query("DELETE FROM sync_shadow WHERE sync_id = ?")
+            .bind(sync_id)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to remove sync shadow")?;
+        sqlx::query("INSERT OR IGNORE INTO sync_tombstones (sync_id, deleted_at) VALUES (?, ?)")
+            .bind(sync_id)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&mut *tx)
+            .await
+            .context("Failed to record deletion")?;
+
+        tx.commit().await.context("Failed to commit deletion")?;
+        Ok(())
+    }
+
+    pub async fn is_buried(&self, sync_id: &str) -> Result<bool> {
+        let row: Option<(String,)> =
+            sqlx::query_as("SELECT sync_id FROM sync_tombstones WHERE sync_id = ?")
+                .bind(sync_id)
+                .fetch_optional(self.pool)
+                .await
+                .context("Failed to check deletions")?;
+        Ok(row.is_some())
+    }
+
+    /// Last record read from `device_id`'s log, or for this device the last
+    /// one written. 0 before the first.
+    pub async fn cursor(&self, device_id: &str) -> Result<u64> {
+        let seq: Option<i64> =
+            sqlx::query_scalar("SELECT seq FROM sync_cursors WHERE device_id = ?")
+                .bind(device_id)
+                .fetch_optional(self.pool)
+                .await
+                .context("Failed to read sync cursor")?;
+        Ok(seq.unwrap_or(0) as u64)
+    }
+
+    pub async fn set_cursor(&self, device_id: &str, seq: u64) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO sync_cursors (device_id, seq) VALUES (?, ?)
+            ON CONFLICT(device_id) DO UPDATE SET seq = excluded.seq
+            "#,
+        )
+        .bind(device_id)
+        .bind(seq as i64)
+        .execute(self.pool)
+        .await
+        .context("Failed to save sync cursor")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,516 @@
+use anyhow::{Context, Result};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+
+use super::search_index::reindex;
+use super::sync::stamp_edits;
+use crate::models::{normalize_tag, normalize_tags, Favorite, SyncField, TagCount};
+
+pub struct TagsDb<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> TagsDb<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Every tag in use outside the trash, most used first.
+    pub async fn list(&self) -> Result<Vec<TagCount>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            r#"
+            SELECT t.name, COUNT(ft.favorite_id) AS count
+            FROM tags t
+            JOIN favorite_tags ft ON ft.tag_id = t.id
+            JOIN favorites f ON f.id = ft.favorite_id AND f.deleted_at IS NULL
+            GROUP BY t.id
+            ORDER BY count DESC, t.name ASC
+            "#,
+        )
+        .fetch_all(self.pool)
+        .await
+        .context("Failed to list tags")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(name, count)| TagCount { name, count })
+            .collect())
+    }
+
+    pub async fn exists(&self, name: &str) -> Result<bool> {
+        let Some(name) = normalize_tag(name) else {
+            return Ok(false);
+        };
+        let row: Option<(i64,)> = sqlx::query_as("SELECT id FROM tags WHERE name = ?")
+            .bind(name)
+            .fetch_optional(self.pool)
+            .await
+            .context("Failed to look up tag")?;
+        Ok(row.is_some())
+    }
+
+    /// Rename `from` to `to`. If `to` already exists the two are merged.
+    pub async fn rename(&self, from: &str, to: &str) -> Result<u64> {
+        self.merge(&[from], to).await
+    }
+
+    /// Fold every tag in `sources` into `into`, creating it if needed. A
+    /// favorite that had a source as a custom tag keeps `into` as custom.
+    /// Returns how many favorites now carry `into` because of the merge.
+    pub async fn merge<S: AsRef<str>>(&self, sources: &[S], into: &str) -> Result<u64> {
+        let into = normalize_tag(into).context("Tag name is empty")?;
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        let into_id = upsert_tag(&mut tx, &into).await?;
+        let mut merged = 0;
+        let mut touched = Vec::new();
+
+        for source in normalize_tags(sources) {
+            if source == into {
+                continue;
+            }
+            let source_id: Option<i64> = sqlx::query_scalar("SELECT id FROM tags WHERE name = ?")
+                .bind(&source)
+                .fetch_optional(&mut *tx)
+                .await
+                .context("Failed to look up tag")?;
+            let Some(source_id) = source_id else {
+                continue;
+            };
+            touched.extend(favorites_with_tag(&mut tx, source_id).await?);
+            merged += fold_tag(&mut tx, source_id, into_id).await?;
+        }
+
+        prune_orphans(&mut tx).await?;
+        retagged(&mut tx, &touched).await?;
+        tx.commit().await.context("Failed to commit tag merge")?;
+        Ok(merged)
+    }
+
+    /// Remove a tag from every favorite. Returns how many favorites had it.
+    pub async fn delete(&self, name: &str) -> Result<u64> {
+        let Some(name) = normalize_tag(name) else {
+            return Ok(0);
+        };
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        let tag_id: Option<i64> = sqlx::query_scalar("SELECT id FROM tags WHERE name = ?")
+            .bind(&name)
+            .fetch_optional(&mut *tx)
+            .await
+            .context("Failed to look up tag")?;
+        let removed = match tag_id {
+            Some(tag_id) => {
+                let touched = favorites_with_tag(&mut tx, tag_id).await?;
+                let removed = delete_tag_by_id(&mut tx, tag_id).await?;
+                retagged(&mut tx, &touched).await?;
+                removed
+            }
+            None => 0,
+        };
+
+        tx.commit().await.context("Failed to commit tag delete")?;
+        Ok(removed)
+    }
+
+    /// Add `tags` as custom tags to every favorite in `favorite_ids`, marking
+    /// them custom where the favorite already had them. All or nothing: an
+    /// unknown id rolls the whole batch back.
+    pub async fn tag_favorites<S: AsRef<str>>(
+        &self,
+        favorite_ids: &[i64],
+        tags: &[S],
+    ) -> Result<()> {
+        let tags = normalize_tags(tags);
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        for tag in &tags {
+            let tag_id = upsert_tag(&mut tx, tag).await?;
+            for &favorite_id in favorite_ids {
+                sqlx::query(
+                    r#"
+                    INSERT INTO favorite_tags (favorite_id, tag_id, is_custom) VALUES (?, ?, 1)
+                    ON CONFLICT (favorite_id, tag_id) DO UPDATE SET is_custom = 1
+                    "#,
+                )
+                .bind(favorite_id)
+                .bind(tag_id)
+                .execute(&mut *tx)
+                .await
+                .with_context(|| format!("Failed to tag favorite {}", favorite_id))?;
+            }
+        }
+
+        retagged(&mut tx, favorite_ids).await?;
+        tx.commit().await.context("Failed to commit bulk tag")?;
+        Ok(())
+    }
+
+    /// Remove `tags` from every favorite in `favorite_ids`.
+    pub async fn untag_favorites<S: AsRef<str>>(
+        &self,
+        favorite_ids: &[i64],
+        tags: &[S],
+    ) -> Result<()> {
+        let tags = normalize_tags(tags);
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        for tag in &tags {
+            for &favorite_id in favorite_ids {
+                sqlx::query(
+                    r#"
+                    DELETE FROM favorite_tags
+                    WHERE favorite_id = ? AND tag_id = (SELECT id FROM tags WHERE name = ?)
+                    "#,
+                )
+                .bind(favorite_id)
+                .bind(tag)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to untag favorite")?;
+            }
+        }
+
+        prune_orphans(&mut tx).await?;
+        retagged(&mut tx, favorite_ids).await?;
+        tx.commit().await.context("Failed to commit bulk untag")?;
+        Ok(())
+    }
+}
+
+/// Replace a favorite's tags. Custom tags are written first so a tag in both
+/// lists counts as custom.
+pub(super) async fn set_favorite_tags(
+    conn: &mut SqliteConnection,
+    favorite_id: i64,
+    tags: &[String],
+    custom_tags: &[String],
+) -> Result<()> {
+    sqlx::query("DELETE FROM favorite_tags WHERE favorite_id = ?")
+        .bind(favorite_id)
+        .execute(&mut *conn)
+        .await
+        .context("Failed to clear favorite tags")?;
+
+    let custom = normalize_tags(custom_tags).into_iter().map(|t| (t, true));
+    let regular = normalize_tags(tags).into_iter().map(|t| (t, false));
+
+    for (tag, is_custom) in custom.chain(regular) {
+        let tag_id = upsert_tag(conn, &tag).await?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO favorite_tags (favorite_id, tag_id, is_custom) VALUES (?, ?, ?)",
+        )
+        .bind(favorite_id)
+        .bind(tag_id)
+        .bind(is_custom)
+        .execute(&mut *conn)
+        .await
+        .context("Failed to tag favorite")?;
+    }
+
+    prune_orphans(conn).await
+}
+
+/// Fill in `tags`/`custom_tags` for already-loaded favorites, in the order the
+/// tags were added.
+pub(super) async fn attach_tags(pool: &SqlitePool, favorites: &mut [Favorite]) -> Result<()> {
+    let mut by_id: HashMap<i64, &mut Favorite> = favorites
+        .iter_mut()
+        .filter_map(|f| f.id.map(|id| (id, f)))
+        .collect();
+    if by_id.is_empty() {
+        return Ok(());
+    }
+
+    let mut query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT ft.favorite_id, t.name, ft.is_custom
+        FROM favorite_tags ft
+        JOIN tags t ON t.id = ft.tag_id
+        WHERE ft.favorite_id IN (
+        "#,
+    );
+    let mut ids = query.separated(", ");
+    for id in by_id.keys() {
+        ids.push_bind(*id);
+    }
+    query.push(") ORDER BY ft.rowid");
+
+    let rows: Vec<(i64, String, bool)> = query
+        .build_query_as()
+        .fetch_all(pool)
+        .await
+        .context("Failed to load favorite tags")?;
+
+    for (favorite_id, name, is_custom) in rows {
+        if let Some(favorite) = by_id.get_mut(&favorite_id) {
+            if is_custom {
+                favorite.custom_tags.push(name);
+            } else {
+                favorite.tags.push(name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rewrite tag names that `normalize_tag` wouldn't have produced, folding
+/// them into the normalized tag. Migration 006 normalized the old JSON tags
+/// in SQL, whose `LOWER` only folds ASCII and whose `REPLACE` passes only
+/// catch tabs and newlines and so many spaces in a row.
+pub(super) async fn normalize_tag_names(pool: &SqlitePool) -> Result<()> {
+    let tags: Vec<(i64, String)> = sqlx::query_as("SELECT id, name FROM tags")
+        .fetch_all(pool)
+        .await
+        .context("Failed to fetch tags")?;
+    let stray: Vec<(i64, Option<String>)> = tags
+        .into_iter()
+        .filter_map(|(id, name)| {
+            let normalized = normalize_tag(&name);
+            (normalized.as_deref() != Some(name.as_str())).then_some((id, normalized))
+        })
+        .collect();
+    if stray.is_empty() {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await.context("Failed to start transaction")?;
+    let mut touched = Vec::new();
+    for (tag_id, normalized) in stray {
+        touched.extend(favorites_with_tag(&mut tx, tag_id).await?);
+        match normalized {
+            Some(name) => {
+                let into_id = upsert_tag(&mut tx, &name).await?;
+                fold_tag(&mut tx, tag_id, into_id).await?;
+            }
+            None => {
+                delete_tag_by_id(&mut tx, tag_id).await?;
+            }
+        }
+    }
+    touched.sort_unstable();
+    touched.dedup();
+    retagged(&mut tx, &touched).await?;
+    tx.commit()
+        .await
+        .context("Failed to commit tag normalization")?;
+    Ok(())
+}
+
+/// Drop tags no favorite uses any more so `list` never shows zero counts.
+pub(super) async fn prune_orphans(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query("DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM favorite_tags)")
+        .execute(conn)
+        .await
+        .context("Failed to prune unused tags")?;
+    Ok(())
+}
+
+async fn upsert_tag(conn: &mut SqliteConnection, name: &str) -> Result<i64> {
+    sqlx::query("INSERT OR IGNORE INTO tags (name) VALUES (?)")
+        .bind(name)
+        .execute(&mut *conn)
+        .await
+        .context("Failed to create tag")?;
+
+    sqlx::query_scalar("SELECT id FROM tags WHERE name = ?")
+        .bind(name)
+        .fetch_one(conn)
+        .await
+        .context("Failed to look up tag")
+}
+
+async fn favorites_with_tag(conn: &mut SqliteConnection, tag_id: i64) -> Result<Vec<i64>> {
+    sqlx::query_scalar("SELECT favorite_id FROM favorite_tags WHERE tag_id = ?")
+        .bind(tag_id)
+        .fetch_all(conn)
+        .await
+        .context("Failed to look up tagged favorites")
+}
+
+/// Bring the search index and sync edit times up to date for favorites
+/// whose tags just changed.
+async fn retagged(conn: &mut SqliteConnection, favorite_ids: &[i64]) -> Result<()> {
+    for &favorite_id in favorite_ids {
+        reindex(conn, favorite_id).await?;
+        stamp_edits(conn, favorite_id, &[SyncField::Tags, SyncField::CustomTags]).await?;
+    }
+    Ok(())
+}
+
+/// Move every use of `source_id` over to `into_id`, keeping custom-ness, and
+/// delete `source_id`. Returns how many favorites gained `into_id`.
+async fn fold_tag(conn: &mut SqliteConnection, source_id: i64, into_id: i64) -> Result<u64> {
+    sqlx::query(
+        r#"
+        UPDATE favorite_tags SET is_custom = 1
+        WHERE tag_id = ?
+          AND favorite_id IN (
+              SELECT favorite_id FROM favorite_tags WHERE tag_id = ? AND is_custom = 1
+          )
+        "#,
+    )
+    .bind(into_id)
+    .bind(source_id)
+    .execute(&mut *conn)
+    .await
+    .context("Failed to merge tag")?;
+
+    let merged = sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO favorite_tags (favorite_id, tag_id, is_custom)
+        SELECT favorite_id, ?, is_custom FROM favorite_tags WHERE tag_id = ?
+        "#,
+    )
+    .bind(into_id)
+    .bind(source_id)
+    .execute(&mut *conn)
+    .await
+    .context("Failed to merge tag")?
+    .rows_affected();
+
+    delete_tag_by_id(conn, source_id).await?;
+    Ok(merged)
+}
+
+async fn delete_tag_by_id(conn: &mut SqliteConnection, tag_id: i64) -> Result<u64> {
+    let removed = sqlx::query("DELETE FROM favorite_tags WHERE tag_id = ?")
+        .bind(tag_id)
+        .execute(&mut *conn)
+        .await
+        .context("Failed to remove tag from favorites")?
+        .rows_affected();
+
+    sqlx::query("DELETE FROM tags WHERE id = ?")
+        .bind(tag_id)
+        .execute(conn)
+        .await
+        .context("Failed to delete tag")?;
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::models::MediaType;
+    use tempfile::TempDir;
+
+    async fn create_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = Database::new(db_path).await.unwrap();
+        db.run_migrations().await.unwrap();
+        (db, temp_dir)
+    }
+
+    async fn create_favorite(db: &Database, name: &str, tags: &[&str]) -> i64 {
+        let favorite = Favorite::new(
+            format!("{}.gif", name),
+            Some(format!("/path/to/{}.gif", name)),
+            MediaType::Gif,
+        )
+        .with_tags(tags.iter().map(|t| t.to_string()).collect());
+        db.favorites().create(&favorite).await.unwrap()
+    }
+
+    fn counts(tags: Vec<TagCount>) -> Vec<(String, i64)> {
+        tags.into_iter().map(|t| (t.name, t.count)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_tags_are_normalized_and_counted() {
+        let (db, _temp) = create_test_db().await;
+        create_favorite(&db, "a", &["Funny  Cat", "cat"]).await;
+        create_favorite(&db, "b", &[" CAT ", ""]).await;
+
+        let tags = db.tags().list().await.unwrap();
+        assert_eq!(
+            counts(tags),
+            vec![("cat".to_string(), 2), ("funny cat".to_string(), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_merges_into_existing_tag() {
+        let (db, _temp) = create_test_db().await;
+        let a = create_favorite(&db, "a", &["kitty"]).await;
+        create_favorite(&db, "b", &["cat"]).await;
+        let c = create_favorite(&db, "c", &["kitty", "cat"]).await;
+
+        db.tags().tag_favorites(&[a], &["kitty"]).await.unwrap();
+        let merged = db.tags().rename("Kitty", "CAT").await.unwrap();
+        assert_eq!(merged, 1);
+
+        assert_eq!(
+            counts(db.tags().list().await.unwrap()),
+            vec![("cat".to_string(), 3)]
+        );
+        assert!(!db.tags().exists("kitty").await.unwrap());
+
+        let a = db.favorites().get_by_id(a).await.unwrap().unwrap();
+        assert_eq!(a.custom_tags, vec!["cat"]);
+        let c = db.favorites().get_by_id(c).await.unwrap().unwrap();
+        assert_eq!(c.tags, vec!["cat"]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_bulk_tag() {
+        let (db, _temp) = create_test_db().await;
+        let a = create_favorite(&db, "a", &["cat"]).await;
+        let b = create_favorite(&db, "b", &[]).await;
+
+        db.tags()
+            .tag_favorites(&[a, b], &["Reaction"])
+            .await
+            .unwrap();
+        assert!(db
+            .tags()
+            .tag_favorites(&[a, 9999], &["oops"])
+            .await
+            .is_err());
+        assert!(!db.tags().exists("oops").await.unwrap());
+
+        let b_tags = db
+            .favorites()
+            .get_by_id(b)
+            .await
+            .unwrap()
+            .unwrap()
+            .custom_tags;
+        assert_eq!(b_tags, vec!["reaction"]);
+
+        assert_eq!(db.tags().delete("reaction").await.unwrap(), 2);
+        db.tags().untag_favorites(&[a], &["cat"]).await.unwrap();
+        assert!(db.tags().list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_deleting_favorite_prunes_its_tags() {
+        let (db, _temp) = create_test_db().await;
+        let a = create_favorite(&db, "a", &["cat", "only-here"]).await;
+        create_favorite(&db, "b", &["cat"]).await;
+
+        db.favorites().delete(a).await.unwrap();
+        assert_eq!(
+            counts(db.tags().list().await.unwrap()),
+            vec![("cat".to_string(), 1)]
+        );
+    }
+}
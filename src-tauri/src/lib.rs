@@ -6,21 +6,30 @@ pub mod services;
 
 use commands::AppState;
 use db::Database;
-use services::Downloader;
+use services::{BackupStore, ConfigFile, Downloader};
 use std::sync::Arc;
-use tauri::menu::{MenuBuilder, MenuItemBuilder};
 use tauri::tray::{TrayIconBuilder, TrayIconEvent};
 use tauri::{Emitter, Manager};
-use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+use tauri_plugin_window_state::StateFlags;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
-        .plugin(tauri_plugin_window_state::Builder::new().build())
+        .plugin(
+            // Size comes from `Settings` (applied in setup below); the plugin
+            // only remembers where the window was.
+            tauri_plugin_window_state::Builder::new()
+                .with_state_flags(StateFlags::all() & !StateFlags::SIZE)
+                .build(),
+        )
         .plugin(tauri_plugin_drag::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_autostart::init(
+            tauri_plugin_autostart::MacosLauncher::LaunchAgent,
+            None,
+        ))
         .setup(|app| {
             // macOS: keep the app out of the dock — it's a menu-bar utility.
             #[cfg(target_os = "macos")]
@@ -34,6 +43,13 @@ pub fn run() {
                 .expect("Failed to get app data directory");
 
             let db_path = app_dir.join("data").join("gifpicker.db");
+            // A restore from backup is staged by `restore_backup` and swapped
+            // in here, before anything has the database open.
+            match services::apply_pending_restore(&db_path) {
+                Ok(true) => eprintln!("Restored database from backup"),
+                Ok(false) => {}
+                Err(e) => eprintln!("Failed to restore database from backup: {:#}", e),
+            }
             let db = tauri::async_runtime::block_on(async {
                 let database = Database::new(db_path)
                     .await
@@ -47,35 +63,43 @@ pub fn run() {
 
             let media_dir = app_dir.join("media");
             let downloader = Downloader::new(media_dir).expect("Failed to initialize downloader");
+            let backups = BackupStore::new(app_dir.join("backups"));
+            // Optional settings file for provisioning; see `ConfigOverlay`.
+            let config_file = ConfigFile::new(
+                app.path()
+                    .app_config_dir()
+                    .expect("Failed to get app config directory"),
+            );
+            commands::log_config_file_problems(&config_file.overlay().status());
 
             // Stable per-install UUID sent to Klipy for ad attribution. Generated
             // on first launch and persisted in the settings table.
-            let customer_id = tauri::async_runtime::block_on(async {
-                db.settings()
+            let (customer_id, settings) = tauri::async_runtime::block_on(async {
+                let customer_id = db
+                    .settings()
                     .get_or_create_customer_id()
                     .await
-                    .expect("Failed to load or generate customer_id")
+                    .expect("Failed to load or generate customer_id");
+                let settings = config_file
+                    .effective_settings(&db)
+                    .await
+                    .unwrap_or_default();
+                (customer_id, settings)
             });
 
             app.manage(AppState::new(
                 Arc::new(db),
                 Arc::new(downloader),
+                Arc::new(backups),
+                Arc::new(config_file),
                 customer_id,
+                settings,
             ));
 
-            // Tray menu
-            let show_item = MenuItemBuilder::with_id("show", "Show GIF Picker").build(app)?;
-            let settings_item = MenuItemBuilder::with_id("settings", "Settings").build(app)?;
-            let quit_item = MenuItemBuilder::with_id("quit", "Quit").build(app)?;
-
-            let menu = MenuBuilder::new(app)
-                .item(&show_item)
-                .item(&settings_item)
-                .separator()
-                .item(&quit_item)
-                .build()?;
+            // Tray menu. Rebuilt by `refresh_tray_menu` whenever favorites change.
+            let menu = tauri::async_runtime::block_on(commands::tray::build_tray_menu(app.handle()))?;
 
-            let tray_builder = TrayIconBuilder::new()
+            let tray_builder = TrayIconBuilder::with_id(commands::tray::TRAY_ID)
                 .icon(app.default_window_icon().unwrap().clone())
                 .tooltip("GIF Picker")
                 .menu(&menu)
@@ -86,24 +110,22 @@ pub fn run() {
             let tray_builder = tray_builder.icon_as_template(true);
 
             let _tray = tray_builder
-                .on_menu_event(|app, event| match event.id.as_ref() {
-                    "show" => {
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                            let _ = window.emit("clear-search", ());
-                            let _ = window.emit("focus-search", ());
-                        }
+                .on_menu_event(|app, event| {
+                    if commands::tray::handle_tray_menu_event(app, &event) {
+                        return;
                     }
-                    "settings" => {
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                            let _ = window.emit("open-settings", ());
+                    match event.id.as_ref() {
+                        "show" => commands::hotkey::show_main_window(app),
+                        "settings" => {
+                            if let Some(window) = app.get_webview_window("main") {
+                                let _ = window.show();
+                                let _ = window.set_focus();
+                                let _ = window.emit("open-settings", ());
+                            }
                         }
+                        "quit" => std::process::exit(0),
+                        _ => {}
                     }
-                    "quit" => std::process::exit(0),
-                    _ => {}
                 })
                 .on_tray_icon_event(|tray, event| {
                     // Only respond to left-click release (Up) to avoid toggling on mouse-down.
@@ -119,30 +141,65 @@ pub fn run() {
                 })
                 .build(app)?;
 
-            // Register the user's saved hotkey on startup, and apply the
-            // persisted always-on-top preference to the window.
+            // Register the user's saved hotkeys on startup, and apply the
+            // persisted window settings (size, always-on-top) and login item.
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 if let Some(state) = app_handle.try_state::<AppState>() {
-                    if let Ok(settings) = state.db.settings().get().await {
-                        if let Ok(shortcut) = settings.hotkey.parse::<Shortcut>() {
-                            let _ = app_handle.global_shortcut().on_shortcut(
-                                shortcut,
-                                |app, _shortcut, event| {
-                                    if event.state == ShortcutState::Pressed {
-                                        commands::hotkey::toggle_main_window(app);
-                                    }
-                                },
+                    if let Ok(settings) = state.config_file.effective_settings(&state.db).await {
+                        let statuses =
+                            commands::hotkey::register_all(&app_handle, &settings.hotkeys);
+                        let mut failed = 0;
+                        for status in statuses {
+                            if let Some(error) = status.error {
+                                eprintln!(
+                                    "Failed to register {} hotkey '{}': {}",
+                                    status.action, status.hotkey, error
+                                );
+                                failed += 1;
+                            }
+                        }
+                        if failed > 0 {
+                            commands::tray::set_tray_status(
+                                &app_handle,
+                                Some(&format!("{} hotkey(s) failed to register", failed)),
                             );
                         }
 
-                        if let Some(window) = app_handle.get_webview_window("main") {
-                            let _ = window.set_always_on_top(settings.always_on_top);
+                        if let Err(e) = commands::apply_settings(&app_handle, &settings) {
+                            eprintln!("Failed to apply window settings: {}", e);
+                        }
+                        if let Err(e) =
+                            commands::apply_autostart(&app_handle, settings.launch_at_startup)
+                        {
+                            eprintln!("Failed to apply launch at startup: {}", e);
                         }
                     }
                 }
             });
 
+            // Fill in palettes and media info for favorites saved before
+            // they were recorded.
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Some(state) = app_handle.try_state::<AppState>() {
+                    commands::backfill_palettes(&state).await;
+                    commands::backfill_media_info(&state).await;
+                }
+            });
+
+            // Purge favorites left in the trash past the retention period.
+            tauri::async_runtime::spawn(commands::purge_trash_periodically(app.handle().clone()));
+
+            // Back up the database on the configured schedule.
+            tauri::async_runtime::spawn(commands::back_up_periodically(app.handle().clone()));
+
+            // Sync favorites through the shared folder, if one is set.
+            tauri::async_runtime::spawn(commands::sync_periodically(app.handle().clone()));
+
+            // Pick up edits to the config file while running.
+            tauri::async_runtime::spawn(commands::watch_config_file(app.handle().clone()));
+
             // Closing the window hides it instead of quitting — the app lives in the tray.
             if let Some(window) = app.get_webview_window("main") {
                 let window_clone = window.clone();
@@ -178,11 +235,34 @@ pub fn run() {
             commands::update_favorite,
             commands::delete_favorite,
             commands::increment_use_count,
+            commands::set_favorite_pinned,
+            commands::move_favorite,
             commands::import_local_file,
+            // Tags
+            commands::list_tags,
+            commands::rename_tag,
+            commands::merge_tags,
+            commands::delete_tag,
+            commands::tag_favorites,
+            commands::untag_favorites,
+            // Duplicates
+            commands::find_duplicates,
+            commands::merge_duplicates,
+            // Trash
+            commands::list_trash,
+            commands::restore_favorite,
+            commands::empty_trash,
+            // Backups
+            commands::list_backups,
+            commands::create_backup,
+            commands::restore_backup,
+            // Sync
+            commands::sync_now,
             // Search
             commands::search_local,
             commands::search_klipy,
             commands::search_combined,
+            commands::find_similar,
             commands::get_klipy_trending,
             commands::get_klipy_categories,
             commands::get_autocomplete,
@@ -192,6 +272,15 @@ pub fn run() {
             commands::get_settings,
             commands::save_settings,
             commands::update_setting,
+            commands::get_config_file_status,
+            commands::export_config_file,
+            commands::import_config_file,
+            // Profiles
+            commands::list_profiles,
+            commands::create_profile,
+            commands::set_profile_collection,
+            commands::delete_profile,
+            commands::switch_profile,
             // Clipboard
             commands::copy_image_to_clipboard,
             commands::copy_text_to_clipboard,
@@ -207,7 +296,7 @@ pub fn run() {
             commands::toggle_window,
             commands::set_always_on_top,
             // Hotkey
-            commands::register_hotkey,
+            commands::register_hotkeys,
             commands::unregister_hotkey,
             commands::unregister_all_hotkeys,
             commands::is_hotkey_registered,
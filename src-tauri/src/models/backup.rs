@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Written next to each database snapshot. The media itself isn't copied;
+/// the manifest records what was on disk so a restore can tell what's
+/// missing.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupManifest {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub schema_version: i64,
+    pub favorite_count: i64,
+    pub db_size: u64,
+    pub media: Vec<MediaEntry>,
+}
+
+/// A file under the media directory, relative to it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MediaEntry {
+    pub path: String,
+    pub size: u64,
+}
+
+/// A backup as listed to the frontend, without the full media list.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupInfo {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub schema_version: i64,
+    pub favorite_count: i64,
+    pub db_size: u64,
+    pub media_count: usize,
+    pub media_size: u64,
+}
+
+impl From<&BackupManifest> for BackupInfo {
+    fn from(manifest: &BackupManifest) -> Self {
+        Self {
+            id: manifest.id.clone(),
+            created_at: manifest.created_at,
+            schema_version: manifest.schema_version,
+            favorite_count: manifest.favorite_count,
+            db_size: manifest.db_size,
+            media_count: manifest.media.len(),
+            media_size: manifest.media.iter().map(|entry| entry.size).sum(),
+        }
+    }
+}
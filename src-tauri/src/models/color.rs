@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+
+/// One entry of a favorite's dominant-color palette.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PaletteColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// Share of the sampled pixels this color stands for, 0–1.
+    pub weight: f32,
+}
+
+impl PaletteColor {
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+
+    /// Hue in degrees (0–360), saturation and lightness (0–1).
+    pub fn hsl(&self) -> (f32, f32, f32) {
+        rgb_to_hsl(self.r, self.g, self.b)
+    }
+}
+
+pub fn rgb_to_hsl(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (hue, saturation.min(1.0), lightness)
+}
+
+/// `#rgb`, `#rrggbb`, with or without the `#`.
+pub fn parse_hex_color(s: &str) -> Option<(u8, u8, u8)> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize, len: usize| u8::from_str_radix(&hex[i * len..(i + 1) * len], 16).ok();
+    match hex.len() {
+        3 => Some((
+            channel(0, 1)? * 17,
+            channel(1, 1)? * 17,
+            channel(2, 1)? * 17,
+        )),
+        6 => Some((channel(0, 2)?, channel(1, 2)?, channel(2, 2)?)),
+        _ => None,
+    }
+}
+
+/// A color by name, as people remember it ("the red one"). Matched on hue,
+/// saturation and lightness rather than distance to one RGB value, so dark
+/// and light reds are both red.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Cyan,
+    Blue,
+    Purple,
+    Pink,
+    Brown,
+    Black,
+    White,
+    Gray,
+}
+
+/// The HSL box a named color covers. A hue range with `start > end` wraps
+/// through 0°.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HslRange {
+    pub hue: Option<(f32, f32)>,
+    pub saturation: (f32, f32),
+    pub lightness: (f32, f32),
+}
+
+impl NamedColor {
+    pub fn range(self) -> HslRange {
+        let chromatic = |start, end, lightness| HslRange {
+            hue: Some((start, end)),
+            saturation: (0.3, 1.0),
+            lightness,
+        };
+        match self {
+            NamedColor::Red => chromatic(345.0, 15.0, (0.2, 0.8)),
+            NamedColor::Orange => chromatic(15.0, 40.0, (0.4, 0.85)),
+            NamedColor::Yellow => chromatic(40.0, 70.0, (0.3, 0.9)),
+            NamedColor::Green => chromatic(70.0, 165.0, (0.15, 0.85)),
+            NamedColor::Cyan => chromatic(165.0, 200.0, (0.2, 0.85)),
+            NamedColor::Blue => chromatic(200.0, 255.0, (0.15, 0.85)),
+            NamedColor::Purple => chromatic(255.0, 290.0, (0.15, 0.85)),
+            NamedColor::Pink => chromatic(290.0, 345.0, (0.3, 0.9)),
+            NamedColor::Brown => HslRange {
+                hue: Some((10.0, 45.0)),
+                saturation: (0.2, 1.0),
+                lightness: (0.1, 0.4),
+            },
+            NamedColor::Black => HslRange {
+                hue: None,
+                saturation: (0.0, 1.0),
+                lightness: (0.0, 0.12),
+            },
+            NamedColor::White => HslRange {
+                hue: None,
+                saturation: (0.0, 1.0),
+                lightness: (0.9, 1.0),
+            },
+            NamedColor::Gray => HslRange {
+                hue: None,
+                saturation: (0.0, 0.12),
+                lightness: (0.12, 0.9),
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for NamedColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "red" => Ok(NamedColor::Red),
+            "orange" => Ok(NamedColor::Orange),
+            "yellow" => Ok(NamedColor::Yellow),
+            "green" => Ok(NamedColor::Green),
+            "cyan" | "teal" => Ok(NamedColor::Cyan),
+            "blue" => Ok(NamedColor::Blue),
+            "purple" | "violet" => Ok(NamedColor::Purple),
+            "pink" | "magenta" => Ok(NamedColor::Pink),
+            "brown" => Ok(NamedColor::Brown),
+            "black" => Ok(NamedColor::Black),
+            "white" => Ok(NamedColor::White),
+            "gray" | "grey" => Ok(NamedColor::Gray),
+            _ => Err(format!("Unknown color: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(color: NamedColor, rgb: (u8, u8, u8)) -> bool {
+        let (h, s, l) = rgb_to_hsl(rgb.0, rgb.1, rgb.2);
+        let range = color.range();
+        let hue_ok = match range.hue {
+            None => true,
+            Some((start, end)) if start > end => h >= start || h < end,
+            Some((start, end)) => h >= start && h < end,
+        };
+        hue_ok
+            && (range.saturation.0..=range.saturation.1).contains(&s)
+            && (range.lightness.0..=range.lightness.1).contains(&l)
+    }
+
+    #[test]
+    fn test_rgb_to_hsl() {
+        assert_eq!(rgb_to_hsl(255, 0, 0), (0.0, 1.0, 0.5));
+        assert_eq!(rgb_to_hsl(0, 0, 255), (240.0, 1.0, 0.5));
+        let (_, s, l) = rgb_to_hsl(128, 128, 128);
+        assert_eq!(s, 0.0);
+        assert!((l - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_named_colors_cover_what_people_mean() {
+        assert!(matches(NamedColor::Red, (255, 0, 0)));
+        assert!(matches(NamedColor::Red, (140, 10, 20)));
+        assert!(matches(NamedColor::Red, (230, 40, 0)));
+        assert!(!matches(NamedColor::Red, (255, 140, 0)));
+        assert!(matches(NamedColor::Orange, (255, 140, 0)));
+        assert!(matches(NamedColor::Brown, (110, 60, 20)));
+        assert!(matches(NamedColor::Blue, (30, 90, 200)));
+        assert!(matches(NamedColor::Black, (10, 10, 12)));
+        assert!(matches(NamedColor::White, (250, 250, 250)));
+        assert!(matches(NamedColor::Gray, (120, 120, 125)));
+        assert!(!matches(NamedColor::Gray, (255, 0, 0)));
+    }
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("#ff8800"), Some((255, 136, 0)));
+        assert_eq!(parse_hex_color("F80"), Some((255, 136, 0)));
+        assert_eq!(parse_hex_color("#ff88"), None);
+        assert_eq!(parse_hex_color("red"), None);
+        assert_eq!(
+            PaletteColor {
+                r: 255,
+                g: 136,
+                b: 0,
+                weight: 1.0
+            }
+            .hex(),
+            "#ff8800"
+        );
+    }
+}
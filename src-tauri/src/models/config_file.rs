@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+use super::SettingKey;
+
+/// An entry of the config file that was skipped. The rest of the file still
+/// applies.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConfigFileIssue {
+    /// The entry as written, e.g. `settings.theme` or `locked`.
+    pub key: String,
+    pub message: String,
+}
+
+/// What the settings panel shows about the config file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConfigFileStatus {
+    /// The file in use; `None` when there isn't one.
+    pub path: Option<String>,
+    /// Keys pinned to the file's values, which the panel can't change.
+    pub locked: Vec<SettingKey>,
+    pub issues: Vec<ConfigFileIssue>,
+    /// Why the file couldn't be used at all (unreadable, not valid TOML or
+    /// JSON). Nothing in it applies until it's fixed.
+    pub error: Option<String>,
+}
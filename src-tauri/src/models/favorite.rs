@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::{MediaInfo, PaletteColor};
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum MediaType {
@@ -77,6 +79,9 @@ pub struct Favorite {
     pub source: Option<Source>,
     pub source_id: Option<String>,
     pub source_url: Option<String>,
+    /// The search that turned this up, for Klipy favorites saved from results.
+    #[serde(default)]
+    pub source_query: Option<String>,
     pub tags: Vec<String>,
     pub custom_tags: Vec<String>,
     pub description: Option<String>,
@@ -86,6 +91,34 @@ pub struct Favorite {
     pub created_at: DateTime<Utc>,
     pub last_used: Option<DateTime<Utc>>,
     pub use_count: i32,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Manual order within the pinned/unpinned groups; lower lists first.
+    /// Assigned on insert and changed only through `move_favorite`.
+    #[serde(default)]
+    pub sort_position: i64,
+    /// Perceptual hash of a few representative frames, for spotting the same
+    /// reaction saved twice. Derived from the file; `update` leaves it alone.
+    #[serde(default)]
+    pub phash: Option<String>,
+    /// Dominant colors, heaviest first. Derived from the file like `phash`.
+    #[serde(default)]
+    pub palette: Vec<PaletteColor>,
+    /// Probed from the file, like `phash`; see `MediaInfo`.
+    #[serde(default)]
+    pub frame_count: Option<i64>,
+    #[serde(default)]
+    pub duration_ms: Option<i64>,
+    #[serde(default)]
+    pub fps: Option<f64>,
+    /// Times the animation plays, 0 meaning forever.
+    #[serde(default)]
+    pub loop_count: Option<i64>,
+    #[serde(default)]
+    pub has_alpha: Option<bool>,
+    /// When the favorite was moved to the trash; `None` for live favorites.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Favorite {
@@ -100,6 +133,7 @@ impl Favorite {
             source: None,
             source_id: None,
             source_url: None,
+            source_query: None,
             tags: Vec::new(),
             custom_tags: Vec::new(),
             description: None,
@@ -109,6 +143,16 @@ impl Favorite {
             created_at: Utc::now(),
             last_used: None,
             use_count: 0,
+            pinned: false,
+            sort_position: 0,
+            phash: None,
+            palette: Vec::new(),
+            frame_count: None,
+            duration_ms: None,
+            fps: None,
+            loop_count: None,
+            has_alpha: None,
+            deleted_at: None,
         }
     }
 
@@ -139,4 +183,21 @@ impl Favorite {
         self.gif_url = Some(url);
         self
     }
+
+    /// Take timing and looping from a probe, and the dimensions too if they
+    /// aren't known yet.
+    pub fn with_media_info(mut self, info: &MediaInfo) -> Self {
+        if let (None, Some(width)) = (self.width, info.width) {
+            self.width = Some(width as i32);
+        }
+        if let (None, Some(height)) = (self.height, info.height) {
+            self.height = Some(height as i32);
+        }
+        self.frame_count = info.frame_count.map(i64::from);
+        self.duration_ms = info.duration_ms.map(|ms| ms as i64);
+        self.fps = info.fps();
+        self.loop_count = info.loop_count.map(i64::from);
+        self.has_alpha = info.has_alpha;
+        self
+    }
 }
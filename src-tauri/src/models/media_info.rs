@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+/// What a media file's container says about it: size, timing and looping.
+/// Fields the format doesn't record are `None`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct MediaInfo {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// 1 for still images.
+    pub frame_count: Option<u32>,
+    /// Length of one play-through. `None` for still images.
+    pub duration_ms: Option<u64>,
+    /// How many times an animation plays, 0 meaning forever. `None` for
+    /// still images and video, which leave looping to the player.
+    pub loop_count: Option<u32>,
+    pub has_alpha: Option<bool>,
+}
+
+impl MediaInfo {
+    /// Average frames per second, for anything that moves.
+    pub fn fps(&self) -> Option<f64> {
+        let frames = self.frame_count.filter(|&n| n > 1)?;
+        let duration = self.duration_ms.filter(|&ms| ms > 0)?;
+        Some(frames as f64 * 1000.0 / duration as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fps() {
+        let info = MediaInfo {
+            frame_count: Some(30),
+            duration_ms: Some(1500),
+            ..Default::default()
+        };
+        assert_eq!(info.fps(), Some(20.0));
+
+        let still = MediaInfo {
+            frame_count: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(still.fps(), None);
+    }
+}
@@ -1,5 +1,19 @@
+pub mod backup;
+pub mod color;
+pub mod config_file;
 pub mod favorite;
+pub mod media_info;
+pub mod profile;
 pub mod settings;
+pub mod sync;
+pub mod tag;
 
+pub use backup::*;
+pub use color::*;
+pub use config_file::*;
 pub use favorite::*;
+pub use media_info::*;
+pub use profile::*;
 pub use settings::*;
+pub use sync::*;
+pub use tag::*;
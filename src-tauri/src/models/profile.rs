@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// A named set of settings to switch between, e.g. work and personal.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SettingsProfile {
+    pub name: String,
+    /// Local search the favorites view opens with while this profile is
+    /// active, e.g. `tag:work`. `None` shows every favorite.
+    pub default_collection: Option<String>,
+    pub active: bool,
+}
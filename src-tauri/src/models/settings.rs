@@ -1,8 +1,13 @@
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
-    pub hotkey: String,
+    /// Global shortcut per action. Actions without an entry (or with an empty
+    /// string) are unbound.
+    pub hotkeys: BTreeMap<HotkeyAction, String>,
     pub window_width: i32,
     pub window_height: i32,
     pub max_item_width: i32,
@@ -17,6 +22,70 @@ pub struct Settings {
     pub show_ads: bool,
     pub tile_size: TileSize,
     pub always_on_top: bool,
+    /// Where the picker appears when opened from the hotkey or tray.
+    pub window_placement: WindowPlacement,
+    /// After copying, synthesize a paste into the app that had focus before
+    /// the picker opened.
+    pub auto_paste: bool,
+    /// How long to wait for focus to return to that app before pasting.
+    pub auto_paste_delay_ms: u32,
+    /// Run this instead of the built-in keystroke, e.g. `xdotool key ctrl+v`.
+    /// Quote arguments containing spaces as in a shell.
+    pub auto_paste_command: Option<String>,
+    /// Apps auto-paste must never fire in — WM_CLASS on X11, process name on
+    /// macOS. Matched case-insensitively.
+    pub auto_paste_denylist: Vec<String>,
+    /// How many recent favorites the tray's "Recent" submenu lists.
+    pub tray_recent_count: u32,
+    /// Days a deleted favorite stays in the trash before it's purged for
+    /// good. 0 keeps it until the trash is emptied by hand.
+    pub trash_retention_days: u32,
+    /// Hours between automatic backups. 0 turns them off.
+    pub backup_interval_hours: u32,
+    /// Most backups kept; older ones are deleted after each new backup.
+    pub backup_keep_count: u32,
+    /// Backups older than this many days are deleted too, except the newest.
+    /// 0 keeps them regardless of age.
+    pub backup_max_age_days: u32,
+    /// Shared folder (Syncthing, network drive, …) to sync favorites through
+    /// with other devices. `None` turns sync off.
+    pub sync_folder: Option<String>,
+    /// How strictly Klipy filters results by content rating.
+    pub content_filter: ContentFilter,
+    /// Language and region sent to Klipy, e.g. `en_US`. `None` follows the
+    /// system locale.
+    pub locale: Option<String>,
+    /// Words and tags to hide from Klipy results and suggestions, matched
+    /// case-insensitively as whole words.
+    pub content_denylist: Vec<String>,
+}
+
+/// What a global hotkey does when pressed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    /// Show the picker, or hide it if it's already up.
+    TogglePicker,
+    /// Open the picker on the favorites view.
+    OpenFavorites,
+    /// Open the picker on Klipy trending.
+    OpenTrending,
+    /// Copy the most recently used favorite without opening the window.
+    PasteRecent,
+    /// Switch to the next settings profile.
+    NextProfile,
+}
+
+impl std::fmt::Display for HotkeyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HotkeyAction::TogglePicker => write!(f, "toggle_picker"),
+            HotkeyAction::OpenFavorites => write!(f, "open_favorites"),
+            HotkeyAction::OpenTrending => write!(f, "open_trending"),
+            HotkeyAction::PasteRecent => write!(f, "paste_recent"),
+            HotkeyAction::NextProfile => write!(f, "next_profile"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -49,13 +118,37 @@ pub enum ClipboardFormat {
     Mp4,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowPlacement {
+    /// Centered on the monitor the mouse cursor is on.
+    Center,
+    /// Top-left corner at the cursor, nudged to stay on-screen.
+    Cursor,
+    /// Wherever the window was last left.
+    Remember,
+}
+
+/// Content rating cutoff for provider results.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFilter {
+    /// Only results rated safe for everyone.
+    Strict,
+    Moderate,
+    /// Whatever the provider returns.
+    Off,
+}
+
 impl Default for Settings {
     fn default() -> Self {
+        #[cfg(target_os = "macos")]
+        let toggle_hotkey = "Option+Cmd+G";
+        #[cfg(not(target_os = "macos"))]
+        let toggle_hotkey = "Ctrl+Shift+G";
+
         Self {
-            #[cfg(target_os = "macos")]
-            hotkey: "Option+Cmd+G".to_string(),
-            #[cfg(not(target_os = "macos"))]
-            hotkey: "Ctrl+Shift+G".to_string(),
+            hotkeys: BTreeMap::from([(HotkeyAction::TogglePicker, toggle_hotkey.to_string())]),
             window_width: 800,
             window_height: 600,
             max_item_width: 400,
@@ -67,8 +160,335 @@ impl Default for Settings {
             show_ads: true,
             tile_size: TileSize::Medium,
             always_on_top: false,
+            window_placement: WindowPlacement::Center,
+            auto_paste: false,
+            auto_paste_delay_ms: 150,
+            auto_paste_command: None,
+            auto_paste_denylist: Vec::new(),
+            tray_recent_count: 5,
+            trash_retention_days: 30,
+            backup_interval_hours: 24,
+            backup_keep_count: 7,
+            backup_max_age_days: 30,
+            sync_folder: None,
+            content_filter: ContentFilter::Moderate,
+            locale: None,
+            content_denylist: Vec::new(),
+        }
+    }
+}
+
+/// Every key of `Settings`, as stored in the settings table and accepted by
+/// `update_setting`. A key's type is its field's type, its default is the
+/// field's value in `Settings::default()`, and `Settings::set` validates it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingKey {
+    Hotkeys,
+    WindowWidth,
+    WindowHeight,
+    MaxItemWidth,
+    CloseAfterSelection,
+    LaunchAtStartup,
+    Theme,
+    ClipboardMode,
+    ClipboardFormat,
+    ShowAds,
+    TileSize,
+    AlwaysOnTop,
+    WindowPlacement,
+    AutoPaste,
+    AutoPasteDelayMs,
+    AutoPasteCommand,
+    AutoPasteDenylist,
+    TrayRecentCount,
+    TrashRetentionDays,
+    BackupIntervalHours,
+    BackupKeepCount,
+    BackupMaxAgeDays,
+    SyncFolder,
+    ContentFilter,
+    Locale,
+    ContentDenylist,
+}
+
+impl SettingKey {
+    pub const ALL: [SettingKey; 26] = [
+        SettingKey::Hotkeys,
+        SettingKey::WindowWidth,
+        SettingKey::WindowHeight,
+        SettingKey::MaxItemWidth,
+        SettingKey::CloseAfterSelection,
+        SettingKey::LaunchAtStartup,
+        SettingKey::Theme,
+        SettingKey::ClipboardMode,
+        SettingKey::ClipboardFormat,
+        SettingKey::ShowAds,
+        SettingKey::TileSize,
+        SettingKey::AlwaysOnTop,
+        SettingKey::WindowPlacement,
+        SettingKey::AutoPaste,
+        SettingKey::AutoPasteDelayMs,
+        SettingKey::AutoPasteCommand,
+        SettingKey::AutoPasteDenylist,
+        SettingKey::TrayRecentCount,
+        SettingKey::TrashRetentionDays,
+        SettingKey::BackupIntervalHours,
+        SettingKey::BackupKeepCount,
+        SettingKey::BackupMaxAgeDays,
+        SettingKey::SyncFolder,
+        SettingKey::ContentFilter,
+        SettingKey::Locale,
+        SettingKey::ContentDenylist,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SettingKey::Hotkeys => "hotkeys",
+            SettingKey::WindowWidth => "window_width",
+            SettingKey::WindowHeight => "window_height",
+            SettingKey::MaxItemWidth => "max_item_width",
+            SettingKey::CloseAfterSelection => "close_after_selection",
+            SettingKey::LaunchAtStartup => "launch_at_startup",
+            SettingKey::Theme => "theme",
+            SettingKey::ClipboardMode => "clipboard_mode",
+            SettingKey::ClipboardFormat => "clipboard_format",
+            SettingKey::ShowAds => "show_ads",
+            SettingKey::TileSize => "tile_size",
+            SettingKey::AlwaysOnTop => "always_on_top",
+            SettingKey::WindowPlacement => "window_placement",
+            SettingKey::AutoPaste => "auto_paste",
+            SettingKey::AutoPasteDelayMs => "auto_paste_delay_ms",
+            SettingKey::AutoPasteCommand => "auto_paste_command",
+            SettingKey::AutoPasteDenylist => "auto_paste_denylist",
+            SettingKey::TrayRecentCount => "tray_recent_count",
+            SettingKey::TrashRetentionDays => "trash_retention_days",
+            SettingKey::BackupIntervalHours => "backup_interval_hours",
+            SettingKey::BackupKeepCount => "backup_keep_count",
+            SettingKey::BackupMaxAgeDays => "backup_max_age_days",
+            SettingKey::SyncFolder => "sync_folder",
+            SettingKey::ContentFilter => "content_filter",
+            SettingKey::Locale => "locale",
+            SettingKey::ContentDenylist => "content_denylist",
         }
     }
+
+    /// Whether a settings profile carries this key. The rest belong to the
+    /// machine and stay put when profiles are switched.
+    pub fn is_per_profile(self) -> bool {
+        !matches!(
+            self,
+            SettingKey::LaunchAtStartup
+                | SettingKey::TrashRetentionDays
+                | SettingKey::BackupIntervalHours
+                | SettingKey::BackupKeepCount
+                | SettingKey::BackupMaxAgeDays
+                | SettingKey::SyncFolder
+        )
+    }
+
+    pub fn default_value(self) -> serde_json::Value {
+        Settings::default().get(self)
+    }
+}
+
+impl std::fmt::Display for SettingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for SettingKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SettingKey::ALL
+            .into_iter()
+            .find(|key| key.as_str() == s)
+            .ok_or_else(|| format!("Unknown setting: {}", s))
+    }
+}
+
+fn parse<T: DeserializeOwned>(key: SettingKey, value: serde_json::Value) -> Result<T, String> {
+    serde_json::from_value(value).map_err(|e| format!("{}: {}", key, e))
+}
+
+fn in_range<T: PartialOrd + Display>(
+    key: SettingKey,
+    value: T,
+    min: T,
+    max: T,
+) -> Result<T, String> {
+    if value < min || value > max {
+        return Err(format!("{} must be between {} and {}", key, min, max));
+    }
+    Ok(value)
+}
+
+/// A locale as Klipy takes it: a language code, optionally followed by a
+/// region (`en`, `en_US`, `pt-br`), normalized to `pt_BR` form. An encoding
+/// or modifier suffix (`de_DE.UTF-8`, `ca_ES@valencia`) is dropped. `None`
+/// if it doesn't look like a locale.
+pub fn normalize_locale(locale: &str) -> Option<String> {
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    let mut parts = locale.split(['_', '-']);
+    let language = parts.next().unwrap_or_default();
+    let region = parts.next();
+    if parts.next().is_some()
+        || !(2..=3).contains(&language.len())
+        || !language.chars().all(|c| c.is_ascii_alphabetic())
+    {
+        return None;
+    }
+    match region {
+        None => Some(language.to_ascii_lowercase()),
+        Some(region) if region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()) => {
+            Some(format!(
+                "{}_{}",
+                language.to_ascii_lowercase(),
+                region.to_ascii_uppercase()
+            ))
+        }
+        Some(_) => None,
+    }
+}
+
+/// Trimmed, with a blank string meaning unset.
+fn non_blank(value: Option<String>) -> Option<String> {
+    value
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+impl Settings {
+    /// The value of `key`, as JSON.
+    pub fn get(&self, key: SettingKey) -> serde_json::Value {
+        let value = match key {
+            SettingKey::Hotkeys => serde_json::to_value(&self.hotkeys),
+            SettingKey::WindowWidth => serde_json::to_value(self.window_width),
+            SettingKey::WindowHeight => serde_json::to_value(self.window_height),
+            SettingKey::MaxItemWidth => serde_json::to_value(self.max_item_width),
+            SettingKey::CloseAfterSelection => serde_json::to_value(self.close_after_selection),
+            SettingKey::LaunchAtStartup => serde_json::to_value(self.launch_at_startup),
+            SettingKey::Theme => serde_json::to_value(&self.theme),
+            SettingKey::ClipboardMode => serde_json::to_value(&self.clipboard_mode),
+            SettingKey::ClipboardFormat => serde_json::to_value(&self.clipboard_format),
+            SettingKey::ShowAds => serde_json::to_value(self.show_ads),
+            SettingKey::TileSize => serde_json::to_value(&self.tile_size),
+            SettingKey::AlwaysOnTop => serde_json::to_value(self.always_on_top),
+            SettingKey::WindowPlacement => serde_json::to_value(&self.window_placement),
+            SettingKey::AutoPaste => serde_json::to_value(self.auto_paste),
+            SettingKey::AutoPasteDelayMs => serde_json::to_value(self.auto_paste_delay_ms),
+            SettingKey::AutoPasteCommand => serde_json::to_value(&self.auto_paste_command),
+            SettingKey::AutoPasteDenylist => serde_json::to_value(&self.auto_paste_denylist),
+            SettingKey::TrayRecentCount => serde_json::to_value(self.tray_recent_count),
+            SettingKey::TrashRetentionDays => serde_json::to_value(self.trash_retention_days),
+            SettingKey::BackupIntervalHours => serde_json::to_value(self.backup_interval_hours),
+            SettingKey::BackupKeepCount => serde_json::to_value(self.backup_keep_count),
+            SettingKey::BackupMaxAgeDays => serde_json::to_value(self.backup_max_age_days),
+            SettingKey::SyncFolder => serde_json::to_value(&self.sync_folder),
+            SettingKey::ContentFilter => serde_json::to_value(self.content_filter),
+            SettingKey::Locale => serde_json::to_value(&self.locale),
+            SettingKey::ContentDenylist => serde_json::to_value(&self.content_denylist),
+        };
+        // Every field is plain data with string map keys.
+        value.expect("settings serialize to JSON")
+    }
+
+    /// Set `key` from JSON, rejecting a value of the wrong type or out of
+    /// range. Strings are trimmed and blank optional strings become `None`.
+    /// On error nothing changes.
+    pub fn set(&mut self, key: SettingKey, value: serde_json::Value) -> Result<(), String> {
+        match key {
+            SettingKey::Hotkeys => {
+                let hotkeys: BTreeMap<HotkeyAction, String> = parse(key, value)?;
+                self.hotkeys = hotkeys
+                    .into_iter()
+                    .map(|(action, hotkey)| (action, hotkey.trim().to_string()))
+                    .filter(|(_, hotkey)| !hotkey.is_empty())
+                    .collect();
+            }
+            SettingKey::WindowWidth => {
+                self.window_width = in_range(key, parse(key, value)?, 320, 10_000)?
+            }
+            SettingKey::WindowHeight => {
+                self.window_height = in_range(key, parse(key, value)?, 240, 10_000)?
+            }
+            SettingKey::MaxItemWidth => {
+                self.max_item_width = in_range(key, parse(key, value)?, 50, 4_000)?
+            }
+            SettingKey::CloseAfterSelection => self.close_after_selection = parse(key, value)?,
+            SettingKey::LaunchAtStartup => self.launch_at_startup = parse(key, value)?,
+            SettingKey::Theme => self.theme = parse(key, value)?,
+            SettingKey::ClipboardMode => self.clipboard_mode = parse(key, value)?,
+            SettingKey::ClipboardFormat => self.clipboard_format = parse(key, value)?,
+            SettingKey::ShowAds => self.show_ads = parse(key, value)?,
+            SettingKey::TileSize => self.tile_size = parse(key, value)?,
+            SettingKey::AlwaysOnTop => self.always_on_top = parse(key, value)?,
+            SettingKey::WindowPlacement => self.window_placement = parse(key, value)?,
+            SettingKey::AutoPaste => self.auto_paste = parse(key, value)?,
+            SettingKey::AutoPasteDelayMs => {
+                self.auto_paste_delay_ms = in_range(key, parse(key, value)?, 0, 5_000)?
+            }
+            SettingKey::AutoPasteCommand => {
+                let command = non_blank(parse(key, value)?);
+                if command
+                    .as_deref()
+                    .is_some_and(|c| shlex::split(c).is_none())
+                {
+                    return Err(format!("{} has an unbalanced quote", key));
+                }
+                self.auto_paste_command = command;
+            }
+            SettingKey::AutoPasteDenylist => {
+                let apps: Vec<String> = parse(key, value)?;
+                self.auto_paste_denylist = apps
+                    .into_iter()
+                    .filter_map(|app| non_blank(Some(app)))
+                    .collect();
+            }
+            SettingKey::TrayRecentCount => {
+                self.tray_recent_count = in_range(key, parse(key, value)?, 0, 20)?
+            }
+            SettingKey::TrashRetentionDays => self.trash_retention_days = parse(key, value)?,
+            SettingKey::BackupIntervalHours => self.backup_interval_hours = parse(key, value)?,
+            SettingKey::BackupKeepCount => {
+                let count: u32 = parse(key, value)?;
+                if count == 0 {
+                    return Err(format!("{} must be at least 1", key));
+                }
+                self.backup_keep_count = count;
+            }
+            SettingKey::BackupMaxAgeDays => self.backup_max_age_days = parse(key, value)?,
+            SettingKey::SyncFolder => {
+                let folder = non_blank(parse(key, value)?);
+                if let Some(folder) = &folder {
+                    if !std::path::Path::new(folder).is_absolute() {
+                        return Err(format!("{} must be an absolute path", key));
+                    }
+                }
+                self.sync_folder = folder;
+            }
+            SettingKey::ContentFilter => self.content_filter = parse(key, value)?,
+            SettingKey::Locale => {
+                self.locale = match non_blank(parse(key, value)?) {
+                    Some(locale) => Some(
+                        normalize_locale(&locale)
+                            .ok_or_else(|| format!("{} must look like en or en_US", key))?,
+                    ),
+                    None => None,
+                }
+            }
+            SettingKey::ContentDenylist => {
+                let terms: Vec<String> = parse(key, value)?;
+                self.content_denylist = terms
+                    .into_iter()
+                    .filter_map(|term| non_blank(Some(term)))
+                    .collect();
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -88,5 +508,85 @@ mod tests {
         let json = serde_json::to_string(&settings).unwrap();
         let deserialized: Settings = serde_json::from_str(&json).unwrap();
         assert_eq!(settings.window_width, deserialized.window_width);
+        assert_eq!(settings.hotkeys, deserialized.hotkeys);
+    }
+
+    #[test]
+    fn test_hotkeys_serialize_as_action_keyed_object() {
+        let mut settings = Settings::default();
+        settings
+            .hotkeys
+            .insert(HotkeyAction::PasteRecent, "Ctrl+Shift+V".to_string());
+
+        let json = serde_json::to_value(&settings).unwrap();
+        assert_eq!(json["hotkeys"]["paste_recent"], "Ctrl+Shift+V");
+        assert!(json["hotkeys"]["toggle_picker"].is_string());
+    }
+
+    #[test]
+    fn test_every_key_round_trips_its_default() {
+        let defaults = Settings::default();
+        let mut settings = Settings::default();
+        for key in SettingKey::ALL {
+            assert_eq!(key.as_str().parse::<SettingKey>(), Ok(key));
+            assert_eq!(serde_json::to_value(key).unwrap(), key.as_str());
+            settings.set(key, key.default_value()).unwrap();
+            assert_eq!(settings.get(key), defaults.get(key));
+        }
+        assert!("hotkey".parse::<SettingKey>().is_err());
+    }
+
+    #[test]
+    fn test_set_rejects_bad_values() {
+        let mut settings = Settings::default();
+        let bad = [
+            (SettingKey::WindowWidth, serde_json::json!("wide")),
+            (SettingKey::WindowWidth, serde_json::json!(10)),
+            (SettingKey::Theme, serde_json::json!("sepia")),
+            (SettingKey::TrayRecentCount, serde_json::json!(-1)),
+            (SettingKey::BackupKeepCount, serde_json::json!(0)),
+            (SettingKey::SyncFolder, serde_json::json!("relative/dir")),
+            (SettingKey::ContentFilter, serde_json::json!("nsfw")),
+            (SettingKey::Locale, serde_json::json!("english")),
+            (SettingKey::Locale, serde_json::json!("en_US_x")),
+            (
+                SettingKey::AutoPasteCommand,
+                serde_json::json!("paste 'ctrl+v"),
+            ),
+            (
+                SettingKey::Hotkeys,
+                serde_json::json!({"explode": "Ctrl+E"}),
+            ),
+        ];
+        for (key, value) in bad {
+            assert!(
+                settings.set(key, value.clone()).is_err(),
+                "{} = {}",
+                key,
+                value
+            );
+        }
+        assert_eq!(settings.get(SettingKey::WindowWidth), 800);
+
+        settings
+            .set(SettingKey::SyncFolder, serde_json::json!("  "))
+            .unwrap();
+        assert_eq!(settings.sync_folder, None);
+        settings
+            .set(SettingKey::Locale, serde_json::json!(" pt-br "))
+            .unwrap();
+        assert_eq!(settings.locale.as_deref(), Some("pt_BR"));
+        assert_eq!(normalize_locale("de_DE.UTF-8").as_deref(), Some("de_DE"));
+        assert_eq!(normalize_locale("C"), None);
+        settings
+            .set(
+                SettingKey::Hotkeys,
+                serde_json::json!({"toggle_picker": " Ctrl+G ", "paste_recent": ""}),
+            )
+            .unwrap();
+        assert_eq!(
+            settings.hotkeys,
+            BTreeMap::from([(HotkeyAction::TogglePicker, "Ctrl+G".to_string())])
+        );
     }
 }
@@ -0,0 +1,211 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::Favorite;
+
+/// One line of a device's change log in the sync folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeRecord {
+    /// Position in the writing device's log, starting at 1.
+    pub seq: u64,
+    pub device_id: String,
+    pub at: DateTime<Utc>,
+    pub sync_id: String,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Change {
+    /// A favorite the other devices haven't seen. `favorite` carries every
+    /// field; its id and file paths are the sender's and get replaced.
+    Create {
+        favorite: Box<Favorite>,
+        media: Vec<SyncedMedia>,
+    },
+    /// One field set to a new value. The newest write of each field wins.
+    Update {
+        field: SyncField,
+        value: serde_json::Value,
+    },
+    /// `count` more uses since the last record. Uses add up rather than
+    /// overwrite, so copies made on different devices all count.
+    Use {
+        count: i64,
+        last_used: Option<DateTime<Utc>>,
+    },
+    /// Deleted for good, trash emptied. Wins over any other change.
+    Delete,
+}
+
+/// The fields of a favorite that can change after it's created, and so are
+/// merged field by field.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncField {
+    Description,
+    Tags,
+    CustomTags,
+    Pinned,
+    SourceQuery,
+    /// Whether it's in the trash. A flag rather than `deleted_at`, which
+    /// each device stamps with its own clock.
+    Trashed,
+}
+
+impl SyncField {
+    pub const ALL: [SyncField; 6] = [
+        SyncField::Description,
+        SyncField::Tags,
+        SyncField::CustomTags,
+        SyncField::Pinned,
+        SyncField::SourceQuery,
+        SyncField::Trashed,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SyncField::Description => "description",
+            SyncField::Tags => "tags",
+            SyncField::CustomTags => "custom_tags",
+            SyncField::Pinned => "pinned",
+            SyncField::SourceQuery => "source_query",
+            SyncField::Trashed => "trashed",
+        }
+    }
+
+    /// The field's current value on `favorite`. Tags are sorted so the same
+    /// set always compares equal.
+    pub fn value_of(self, favorite: &Favorite) -> serde_json::Value {
+        let sorted = |tags: &[String]| {
+            let mut tags = tags.to_vec();
+            tags.sort();
+            serde_json::json!(tags)
+        };
+        match self {
+            SyncField::Description => serde_json::json!(favorite.description),
+            SyncField::Tags => sorted(&favorite.tags),
+            SyncField::CustomTags => sorted(&favorite.custom_tags),
+            SyncField::Pinned => serde_json::json!(favorite.pinned),
+            SyncField::SourceQuery => serde_json::json!(favorite.source_query),
+            SyncField::Trashed => serde_json::json!(favorite.deleted_at.is_some()),
+        }
+    }
+
+    /// Set the field on `favorite`. `Trashed` only sets the flag; moving the
+    /// files is up to the caller.
+    pub fn apply_to(
+        self,
+        favorite: &mut Favorite,
+        value: serde_json::Value,
+    ) -> serde_json::Result<()> {
+        match self {
+            SyncField::Description => favorite.description = serde_json::from_value(value)?,
+            SyncField::Tags => favorite.tags = serde_json::from_value(value)?,
+            SyncField::CustomTags => favorite.custom_tags = serde_json::from_value(value)?,
+            SyncField::Pinned => favorite.pinned = serde_json::from_value(value)?,
+            SyncField::SourceQuery => favorite.source_query = serde_json::from_value(value)?,
+            SyncField::Trashed => {
+                let trashed: bool = serde_json::from_value(value)?;
+                favorite.deleted_at = match (trashed, favorite.deleted_at) {
+                    (true, Some(at)) => Some(at),
+                    (true, None) => Some(Utc::now()),
+                    (false, _) => None,
+                };
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Which of a favorite's files a synced media file is.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaRole {
+    File,
+    Mp4,
+}
+
+/// A media file in the sync folder, stored as `media/<hash>.<extension>`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SyncedMedia {
+    pub role: MediaRole,
+    /// SHA-256 of the contents, hex.
+    pub hash: String,
+    pub extension: String,
+    /// The file's name on the sending device, reused where it's free.
+    pub name: String,
+}
+
+/// The last write of one synced field, used to settle conflicts: the later
+/// `at` wins, and the device id breaks ties.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldState {
+    pub value: serde_json::Value,
+    pub at: DateTime<Utc>,
+    pub device_id: String,
+}
+
+impl FieldState {
+    pub fn loses_to(&self, at: DateTime<Utc>, device_id: &str) -> bool {
+        (self.at, self.device_id.as_str()) < (at, device_id)
+    }
+}
+
+/// What a sync did.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Local changes written to this device's log.
+    pub exported: usize,
+    /// Other devices' changes applied here.
+    pub imported: usize,
+    /// Other devices' changes waiting on media that hasn't arrived in the
+    /// folder yet. They're retried on the next sync.
+    pub pending: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_record_round_trips() {
+        let record = ChangeRecord {
+            seq: 3,
+            device_id: "laptop".to_string(),
+            at: Utc::now(),
+            sync_id: "klipy:abc".to_string(),
+            change: Change::Update {
+                field: SyncField::CustomTags,
+                value: serde_json::json!(["cat"]),
+            },
+        };
+        let line = serde_json::to_string(&record).unwrap();
+        assert!(line.contains(r#""op":"update""#));
+        assert!(line.contains(r#""field":"custom_tags""#));
+        let parsed: ChangeRecord = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed.seq, 3);
+        assert!(matches!(
+            parsed.change,
+            Change::Update {
+                field: SyncField::CustomTags,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_later_write_wins_and_device_breaks_ties() {
+        let at = Utc::now();
+        let state = FieldState {
+            value: serde_json::json!(true),
+            at,
+            device_id: "b".to_string(),
+        };
+        assert!(state.loses_to(at + chrono::Duration::seconds(1), "a"));
+        assert!(!state.loses_to(at - chrono::Duration::seconds(1), "z"));
+        assert!(state.loses_to(at, "c"));
+        assert!(!state.loses_to(at, "a"));
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+/// A tag and how many favorites carry it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TagCount {
+    pub name: String,
+    pub count: i64,
+}
+
+/// Canonical form of a tag: trimmed, lowercased, runs of whitespace collapsed
+/// to a single space. `None` if nothing is left.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let normalized = tag
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    (!normalized.is_empty()).then_some(normalized)
+}
+
+/// Normalize a list of tags, dropping empties and duplicates but keeping the
+/// first-seen order.
+pub fn normalize_tags<S: AsRef<str>>(tags: &[S]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags.iter().filter_map(|t| normalize_tag(t.as_ref())) {
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(
+            normalize_tag("  Funny   Cat\t"),
+            Some("funny cat".to_string())
+        );
+        assert_eq!(normalize_tag("ÜBER"), Some("über".to_string()));
+        assert_eq!(normalize_tag(" \n "), None);
+    }
+
+    #[test]
+    fn test_normalize_tags_dedupes_in_order() {
+        let tags = normalize_tags(&["Dog", "cat", " dog ", "", "CAT", "bird"]);
+        assert_eq!(tags, vec!["dog", "cat", "bird"]);
+    }
+}
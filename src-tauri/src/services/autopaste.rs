@@ -0,0 +1,280 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+use std::time::Duration;
+
+/// Synthesizes the paste keystroke into whatever app has focus once the picker
+/// hides. Everything here is best-effort: if we can't tell which app is
+/// focused and a denylist is configured, we don't paste at all.
+pub struct AutoPaster {
+    delay: Duration,
+    command: Option<String>,
+    denylist: Vec<String>,
+}
+
+impl AutoPaster {
+    pub fn new(delay_ms: u32, command: Option<String>, denylist: Vec<String>) -> Self {
+        Self {
+            delay: Duration::from_millis(delay_ms as u64),
+            command: command.filter(|c| !c.trim().is_empty()),
+            denylist,
+        }
+    }
+
+    /// Wait for focus to settle on the previous app, then paste into it.
+    /// Returns `false` when the paste was skipped because of the denylist.
+    pub async fn paste(&self) -> Result<bool> {
+        tokio::time::sleep(self.delay).await;
+
+        let command = self.command.clone();
+        let denylist = self.denylist.clone();
+        tokio::task::spawn_blocking(move || {
+            if !denylist.is_empty() {
+                let app = focused_app();
+                if is_denied(app.as_deref(), &denylist) {
+                    return Ok(false);
+                }
+            }
+
+            match command {
+                Some(command) => run_command(&command)?,
+                None => send_paste_keystroke()?,
+            }
+            Ok(true)
+        })
+        .await
+        .context("Auto-paste task panicked")?
+    }
+}
+
+/// Whether auto-paste must not fire for `app`. An unidentified app counts as
+/// denied — the denylist exists for password managers and terminals, so we'd
+/// rather skip a paste than send one somewhere it's unwanted.
+pub fn is_denied(app: Option<&str>, denylist: &[String]) -> bool {
+    let Some(app) = app else {
+        return !denylist.is_empty();
+    };
+    denylist
+        .iter()
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| entry.eq_ignore_ascii_case(app))
+}
+
+/// Split a paste command into program and arguments the way a POSIX shell
+/// would, so quoted arguments and paths with spaces survive.
+fn split_command(command: &str) -> Result<(String, Vec<String>)> {
+    let mut parts = shlex::split(command)
+        .with_context(|| format!("Auto-paste command has unbalanced quotes: {}", command))?
+        .into_iter();
+    let program = parts.next().context("Auto-paste command is empty")?;
+    Ok((program, parts.collect()))
+}
+
+/// Run a user-configured paste command. Split with shell quoting rules but
+/// executed directly — no shell, so the setting can't smuggle in pipelines.
+fn run_command(command: &str) -> Result<()> {
+    let (program, args) = split_command(command)?;
+
+    let status = Command::new(&program)
+        .args(&args)
+        .status()
+        .with_context(|| format!("Failed to run auto-paste command '{}'", program))?;
+
+    if !status.success() {
+        anyhow::bail!("Auto-paste command exited with {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn is_wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+        || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t.eq_ignore_ascii_case("wayland"))
+}
+
+#[cfg(target_os = "linux")]
+fn send_paste_keystroke() -> Result<()> {
+    if is_wayland() {
+        // XTest only reaches XWayland clients; ydotool drives /dev/uinput and
+        // works for native Wayland apps too. Keycodes 29/47 are KEY_LEFTCTRL/KEY_V.
+        return run_command("ydotool key 29:1 47:1 47:0 29:0");
+    }
+    x11::paste()
+}
+
+#[cfg(target_os = "linux")]
+fn focused_app() -> Option<String> {
+    if is_wayland() {
+        // No portable way to ask a Wayland compositor who has focus.
+        return None;
+    }
+    x11::focused_class().ok().flatten()
+}
+
+#[cfg(target_os = "macos")]
+fn send_paste_keystroke() -> Result<()> {
+    run_osascript(r#"tell application "System Events" to keystroke "v" using command down"#)
+        .map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+fn focused_app() -> Option<String> {
+    run_osascript(
+        r#"tell application "System Events" to get name of first application process whose frontmost is true"#,
+    )
+    .ok()
+}
+
+#[cfg(target_os = "macos")]
+fn run_osascript(script: &str) -> Result<String> {
+    let output = Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .output()
+        .context("Failed to run osascript")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("osascript failed: {}", error);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(target_os = "windows")]
+fn send_paste_keystroke() -> Result<()> {
+    run_command(
+        "powershell -NoProfile -NonInteractive -Command (New-Object -ComObject WScript.Shell).SendKeys('^v')",
+    )
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn focused_app() -> Option<String> {
+    None
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn send_paste_keystroke() -> Result<()> {
+    anyhow::bail!("Auto-paste needs a configured command on this platform")
+}
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use anyhow::{Context, Result};
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, KEY_PRESS_EVENT, KEY_RELEASE_EVENT};
+    use x11rb::protocol::xtest::ConnectionExt as _;
+
+    const XK_CONTROL_L: u32 = 0xffe3;
+    const XK_V: u32 = 0x0076;
+
+    /// Press and release Ctrl+V through the XTest extension.
+    pub fn paste() -> Result<()> {
+        let (conn, screen) = x11rb::connect(None).context("Failed to connect to X server")?;
+        let root = conn.setup().roots[screen].root;
+
+        let ctrl = keycode_for(&conn, XK_CONTROL_L)?;
+        let v = keycode_for(&conn, XK_V)?;
+
+        for (kind, code) in [
+            (KEY_PRESS_EVENT, ctrl),
+            (KEY_PRESS_EVENT, v),
+            (KEY_RELEASE_EVENT, v),
+            (KEY_RELEASE_EVENT, ctrl),
+        ] {
+            conn.xtest_fake_input(kind, code, x11rb::CURRENT_TIME, root, 0, 0, 0)
+                .context("XTest fake input failed")?;
+        }
+        conn.flush().context("Failed to flush X connection")?;
+        Ok(())
+    }
+
+    /// WM_CLASS class name of the `_NET_ACTIVE_WINDOW`, e.g. "firefox".
+    pub fn focused_class() -> Result<Option<String>> {
+        let (conn, screen) = x11rb::connect(None).context("Failed to connect to X server")?;
+        let root = conn.setup().roots[screen].root;
+
+        let active_atom = conn
+            .intern_atom(false, b"_NET_ACTIVE_WINDOW")?
+            .reply()?
+            .atom;
+        let active = conn
+            .get_property(false, root, active_atom, AtomEnum::WINDOW, 0, 1)?
+            .reply()?;
+        let Some(window) = active.value32().and_then(|mut v| v.next()) else {
+            return Ok(None);
+        };
+        if window == 0 {
+            return Ok(None);
+        }
+
+        let class = conn
+            .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 256)?
+            .reply()?;
+
+        // WM_CLASS is "instance\0class\0"; the class is the stable app name.
+        let class = class
+            .value
+            .split(|&b| b == 0)
+            .rfind(|part| !part.is_empty())
+            .map(|part| String::from_utf8_lossy(part).into_owned());
+        Ok(class)
+    }
+
+    fn keycode_for(conn: &impl Connection, keysym: u32) -> Result<u8> {
+        let setup = conn.setup();
+        let min = setup.min_keycode;
+        let count = setup.max_keycode - min + 1;
+        let mapping = conn.get_keyboard_mapping(min, count)?.reply()?;
+
+        let per = mapping.keysyms_per_keycode as usize;
+        mapping
+            .keysyms
+            .chunks(per.max(1))
+            .position(|syms| syms.contains(&keysym))
+            .map(|i| min + i as u8)
+            .with_context(|| format!("No keycode for keysym {:#x}", keysym))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn test_is_denied_matches_case_insensitively() {
+        let denylist = list(&["KeePassXC", "  org.gnome.Terminal "]);
+        assert!(is_denied(Some("keepassxc"), &denylist));
+        assert!(is_denied(Some("org.gnome.terminal"), &denylist));
+        assert!(!is_denied(Some("discord"), &denylist));
+    }
+
+    #[test]
+    fn test_unknown_app_is_denied_only_with_a_denylist() {
+        assert!(is_denied(None, &list(&["1Password"])));
+        assert!(!is_denied(None, &[]));
+    }
+
+    #[test]
+    fn test_blank_command_is_ignored() {
+        let paster = AutoPaster::new(0, Some("   ".to_string()), vec![]);
+        assert!(paster.command.is_none());
+    }
+
+    #[test]
+    fn test_command_is_split_with_shell_quoting() {
+        let (program, args) =
+            split_command(r#""/opt/My Tools/paste" --keys 'ctrl+v' a\ b"#).unwrap();
+        assert_eq!(program, "/opt/My Tools/paste");
+        assert_eq!(args, vec!["--keys", "ctrl+v", "a b"]);
+        assert!(split_command("paste 'unterminated").is_err());
+    }
+
+    #[test]
+    fn test_failing_command_reports_error() {
+        assert!(run_command("definitely-not-a-real-paste-binary").is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::models::normalize_tags;
+
+/// Upper bound on derived tags — titles can be long and we don't want a
+/// favorite to drown in single-word tags.
+const MAX_TAGS: usize = 12;
+
+/// Words that carry no meaning on their own as a tag.
+const STOP_WORDS: &[&str] = &[
+    "a", "about", "after", "all", "am", "an", "and", "are", "as", "at", "be", "been", "but", "by",
+    "can", "did", "do", "does", "for", "from", "gif", "gifs", "had", "has", "have", "he", "her",
+    "his", "how", "i", "if", "in", "into", "is", "it", "its", "just", "me", "my", "no", "not",
+    "of", "on", "or", "our", "out", "she", "so", "than", "that", "the", "their", "them", "then",
+    "there", "they", "this", "to", "too", "up", "us", "was", "we", "were", "what", "when", "who",
+    "why", "will", "with", "you", "your",
+];
+
+/// Where a Klipy GIF was found, used to seed its tags.
+#[derive(Debug, Default)]
+pub struct KlipyOrigin<'a> {
+    /// What the user searched for, if it came from a search.
+    pub query: Option<&'a str>,
+    /// The category being browsed, if it came from one.
+    pub category: Option<&'a str>,
+    pub slug: &'a str,
+    pub title: &'a str,
+}
+
+/// Initial tags for a Klipy favorite, most specific first: the whole query and
+/// category as phrases, then individual words from the query, category, title
+/// and slug with stop-words removed.
+pub fn derive_tags(origin: &KlipyOrigin) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+
+    let phrases = [origin.query, origin.category];
+    candidates.extend(phrases.iter().flatten().map(|p| p.to_string()));
+
+    for text in phrases
+        .into_iter()
+        .flatten()
+        .chain([origin.title, origin.slug])
+    {
+        candidates.extend(keywords(text));
+    }
+
+    let mut tags = normalize_tags(&candidates);
+    tags.truncate(MAX_TAGS);
+    tags
+}
+
+/// Meaningful single words in `text`. Tokens with digits are dropped — in
+/// slugs they're the random id suffix (`happy-cat-f7u`), elsewhere noise.
+fn keywords(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .map(|word| word.trim_matches('\'').to_lowercase())
+        .filter(|word| word.chars().count() > 1)
+        .filter(|word| !word.chars().any(|c| c.is_ascii_digit()))
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_tags_from_search() {
+        let tags = derive_tags(&KlipyOrigin {
+            query: Some("  Happy Dance "),
+            category: None,
+            slug: "happy-cat-dance-f7u",
+            title: "The Happy Cat is Dancing!",
+        });
+        assert_eq!(
+            tags,
+            vec!["happy dance", "happy", "dance", "cat", "dancing"]
+        );
+    }
+
+    #[test]
+    fn test_derive_tags_from_category_drops_stop_words() {
+        let tags = derive_tags(&KlipyOrigin {
+            query: None,
+            category: Some("Thank You"),
+            slug: "thanks-a-lot-x9",
+            title: "Thanks a lot",
+        });
+        assert_eq!(tags, vec!["thank you", "thank", "thanks", "lot"]);
+    }
+
+    #[test]
+    fn test_derive_tags_is_capped() {
+        let title = (0..30)
+            .map(|i| format!("word{}", char::from(b'a' + (i % 26) as u8)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let tags = derive_tags(&KlipyOrigin {
+            title: &title,
+            ..Default::default()
+        });
+        assert_eq!(tags.len(), MAX_TAGS);
+    }
+}
@@ -0,0 +1,346 @@
+use anyhow::{bail, ensure, Context, Result};
+use chrono::{Duration, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::db::Database;
+use crate::models::{BackupInfo, BackupManifest, MediaEntry};
+
+const SNAPSHOT_FILE: &str = "gifpicker.db";
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Backups in progress are written here and renamed into place when
+/// complete, so a crash never leaves something that looks restorable.
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/// Snapshots of the database, one directory per backup:
+/// `<dir>/<id>/gifpicker.db` plus a `manifest.json` describing it.
+pub struct BackupStore {
+    dir: PathBuf,
+}
+
+impl BackupStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Snapshot the database and record the media that's on disk.
+    pub async fn create(&self, db: &Database, media_dir: &Path) -> Result<BackupInfo> {
+        fs::create_dir_all(&self.dir).context("Failed to create backup directory")?;
+
+        let id = Utc::now().format("%Y%m%d-%H%M%S-%3f").to_string();
+        let partial = self.dir.join(format!("{}{}", id, PARTIAL_SUFFIX));
+        let result = async {
+            fs::create_dir(&partial)
+                .with_context(|| format!("Failed to create {}", partial.display()))?;
+
+            let snapshot = partial.join(SNAPSHOT_FILE);
+            db.snapshot_to(&snapshot).await?;
+            let info = Database::inspect_snapshot(&snapshot).await?;
+
+            let media_dir = media_dir.to_path_buf();
+            let media = tokio::task::spawn_blocking(move || list_media(&media_dir))
+                .await
+                .context("Media listing task failed")??;
+
+            let manifest = BackupManifest {
+                id: id.clone(),
+                created_at: Utc::now(),
+                schema_version: info.schema_version,
+                favorite_count: info.favorite_count,
+                db_size: fs::metadata(&snapshot)?.len(),
+                media,
+            };
+            fs::write(
+                partial.join(MANIFEST_FILE),
+                serde_json::to_vec_pretty(&manifest)?,
+            )
+            .context("Failed to write backup manifest")?;
+
+            fs::rename(&partial, self.dir.join(&id)).context("Failed to finish backup")?;
+            Ok(BackupInfo::from(&manifest))
+        }
+        .await;
+
+        if result.is_err() {
+            let _ = fs::remove_dir_all(&partial);
+        }
+        result
+    }
+
+    /// Complete backups, newest first. Directories without a readable
+    /// manifest are skipped.
+    pub fn list(&self) -> Result<Vec<BackupManifest>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context("Failed to read backup directory"),
+        };
+
+        let mut manifests = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.to_string_lossy().ends_with(PARTIAL_SUFFIX) {
+                continue;
+            }
+            let manifest = fs::read(path.join(MANIFEST_FILE))
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<BackupManifest>(&bytes).ok());
+            match manifest {
+                Some(manifest) => manifests.push(manifest),
+                None => eprintln!("Warning: skipping unreadable backup {}", path.display()),
+            }
+        }
+        manifests.sort_by_key(|m| std::cmp::Reverse(m.created_at));
+        Ok(manifests)
+    }
+
+    /// Delete backups beyond the newest `keep`, and any older than
+    /// `max_age_days` (0 for no limit). The newest backup always stays.
+    /// Returns the ids deleted.
+    pub fn rotate(&self, keep: u32, max_age_days: u32) -> Result<Vec<String>> {
+        let cutoff = (max_age_days > 0).then(|| Utc::now() - Duration::days(max_age_days.into()));
+        let keep = (keep as usize).max(1);
+
+        let mut removed = Vec::new();
+        for (i, manifest) in self.list()?.into_iter().enumerate() {
+            let expired = cutoff.is_some_and(|cutoff| manifest.created_at < cutoff);
+            if i == 0 || (i < keep && !expired) {
+                continue;
+            }
+            fs::remove_dir_all(self.dir.join(&manifest.id))
+                .with_context(|| format!("Failed to delete backup {}", manifest.id))?;
+            removed.push(manifest.id);
+        }
+        Ok(removed)
+    }
+
+    /// Check a backup can be restored: its manifest exists and its snapshot
+    /// opens, passes an integrity check and matches the manifest.
+    pub async fn validate(&self, id: &str) -> Result<BackupManifest> {
+        let Some(manifest) = self.list()?.into_iter().find(|m| m.id == id) else {
+            bail!("No backup {}", id);
+        };
+        let info = Database::inspect_snapshot(&self.snapshot_path(id)).await?;
+        ensure!(
+            info.schema_version == manifest.schema_version
+                && info.favorite_count == manifest.favorite_count,
+            "Backup {} doesn't match its manifest",
+            id
+        );
+        Ok(manifest)
+    }
+
+    /// Copy a backup's snapshot next to the live database, to be swapped in
+    /// by `apply_pending_restore` on the next start. The live file can't be
+    /// replaced while the pool has it open.
+    pub fn stage_restore(&self, id: &str, db_path: &Path) -> Result<()> {
+        let staged = pending_restore_path(db_path);
+        fs::copy(self.snapshot_path(id), &staged)
+            .with_context(|| format!("Failed to stage backup {}", id))?;
+        Ok(())
+    }
+
+    fn snapshot_path(&self, id: &str) -> PathBuf {
+        self.dir.join(id).join(SNAPSHOT_FILE)
+    }
+}
+
+/// Media files in the manifest that aren't on disk any more.
+pub fn missing_media(manifest: &BackupManifest, media_dir: &Path) -> Vec<String> {
+    manifest
+        .media
+        .iter()
+        .filter(|entry| !media_dir.join(&entry.path).exists())
+        .map(|entry| entry.path.clone())
+        .collect()
+}
+
+fn pending_restore_path(db_path: &Path) -> PathBuf {
+    with_suffix(db_path, ".restore")
+}
+
+/// The database file and its journal, which move together.
+const DATABASE_SUFFIXES: [&str; 3] = ["", "-wal", "-shm"];
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Swap a staged restore in for the database. Call before opening it.
+/// Returns whether there was one.
+///
+/// The live database and its journal are moved aside first and only
+/// deleted once the staged file is in place, so a failed swap leaves the
+/// old database as it was.
+pub fn apply_pending_restore(db_path: &Path) -> Result<bool> {
+    let staged = pending_restore_path(db_path);
+    if !staged.exists() {
+        return Ok(false);
+    }
+
+    let mut moved = Vec::new();
+    for suffix in DATABASE_SUFFIXES {
+        let live = with_suffix(db_path, suffix);
+        let aside = with_suffix(&live, ".replaced");
+        match fs::rename(&live, &aside) {
+            Ok(()) => moved.push((live, aside)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                put_back(&moved);
+                return Err(e).with_context(|| format!("Failed to move {} aside", live.display()));
+            }
+        }
+    }
+
+    if let Err(e) = fs::rename(&staged, db_path) {
+        put_back(&moved);
+        return Err(e).context("Failed to swap in restored database");
+    }
+
+    // The old journal belongs to the old database.
+    for (_, aside) in &moved {
+        fs::remove_file(aside).with_context(|| format!("Failed to delete {}", aside.display()))?;
+    }
+    Ok(true)
+}
+
+/// Undo the moves of a failed `apply_pending_restore`.
+fn put_back(moved: &[(PathBuf, PathBuf)]) {
+    for (live, aside) in moved {
+        if let Err(e) = fs::rename(aside, live) {
+            eprintln!("Warning: failed to put back {}: {}", live.display(), e);
+        }
+    }
+}
+
+fn list_media(media_dir: &Path) -> Result<Vec<MediaEntry>> {
+    let mut media = Vec::new();
+    let mut pending = vec![media_dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else if let Ok(relative) = entry.path().strip_prefix(media_dir) {
+                media.push(MediaEntry {
+                    path: relative.to_string_lossy().replace('\\', "/"),
+                    size: metadata.len(),
+                });
+            }
+        }
+    }
+    media.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(media)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn setup() -> (Database, BackupStore, PathBuf, TempDir) {
+        let temp = TempDir::new().unwrap();
+        let db = Database::new(temp.path().join("data").join("gifpicker.db"))
+            .await
+            .unwrap();
+        db.run_migrations().await.unwrap();
+        let media_dir = temp.path().join("media");
+        fs::create_dir_all(media_dir.join("gifs")).unwrap();
+        fs::write(media_dir.join("gifs").join("a.gif"), b"GIF89a").unwrap();
+        let store = BackupStore::new(temp.path().join("backups"));
+        (db, store, media_dir, temp)
+    }
+
+    #[tokio::test]
+    async fn test_create_lists_and_validates() {
+        let (db, store, media_dir, _temp) = setup().await;
+
+        let info = store.create(&db, &media_dir).await.unwrap();
+        assert_eq!(info.media_count, 1);
+        assert_eq!(info.media_size, 6);
+        assert_eq!(info.schema_version, crate::db::latest_schema_version());
+
+        let listed = store.list().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].media[0].path, "gifs/a.gif");
+
+        let manifest = store.validate(&info.id).await.unwrap();
+        assert!(missing_media(&manifest, &media_dir).is_empty());
+        fs::remove_file(media_dir.join("gifs").join("a.gif")).unwrap();
+        assert_eq!(missing_media(&manifest, &media_dir), vec!["gifs/a.gif"]);
+
+        assert!(store.validate("nope").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_snapshot_fails_validation() {
+        let (db, store, media_dir, _temp) = setup().await;
+        let info = store.create(&db, &media_dir).await.unwrap();
+
+        fs::write(store.snapshot_path(&info.id), b"not a database").unwrap();
+        assert!(store.validate(&info.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_by_count_and_age() {
+        let (db, store, media_dir, _temp) = setup().await;
+        let mut ids = Vec::new();
+        for _ in 0..4 {
+            ids.push(store.create(&db, &media_dir).await.unwrap().id);
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+
+        let removed = store.rotate(2, 0).unwrap();
+        assert_eq!(removed, vec![ids[1].clone(), ids[0].clone()]);
+
+        // Age out the older survivor; the newest stays even when expired.
+        for id in &ids[2..] {
+            let path = store.dir.join(id).join(MANIFEST_FILE);
+            let mut manifest: BackupManifest =
+                serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+            manifest.created_at -= Duration::days(60);
+            if id == &ids[3] {
+                manifest.created_at += Duration::seconds(1);
+            }
+            fs::write(&path, serde_json::to_vec(&manifest).unwrap()).unwrap();
+        }
+        let removed = store.rotate(10, 30).unwrap();
+        assert_eq!(removed, vec![ids[2].clone()]);
+        assert_eq!(store.list().unwrap()[0].id, ids[3]);
+    }
+
+    #[tokio::test]
+    async fn test_staged_restore_replaces_database() {
+        let (db, store, media_dir, temp) = setup().await;
+        let info = store.create(&db, &media_dir).await.unwrap();
+
+        let target = temp.path().join("other.db");
+        fs::write(&target, b"old").unwrap();
+        fs::write(with_suffix(&target, "-wal"), b"old journal").unwrap();
+        assert!(!apply_pending_restore(&target).unwrap());
+
+        store.stage_restore(&info.id, &target).unwrap();
+        assert!(apply_pending_restore(&target).unwrap());
+        let restored = Database::inspect_snapshot(&target).await.unwrap();
+        assert_eq!(restored.favorite_count, 0);
+        assert!(!pending_restore_path(&target).exists());
+        assert!(!with_suffix(&target, "-wal").exists());
+        let leftovers = fs::read_dir(temp.path())
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().ends_with(".replaced")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+}
@@ -3,6 +3,8 @@ use arboard::Clipboard;
 use std::path::Path;
 use std::fs;
 
+use crate::models::{ClipboardFormat, ClipboardMode, Favorite};
+
 pub struct ClipboardManager {
     clipboard: Clipboard,
 }
@@ -129,6 +131,35 @@ impl ClipboardManager {
         Ok(())
     }
 
+    /// Copy a favorite the same way the grid does: its URL in `Url` mode,
+    /// otherwise the file in the preferred format, falling back to the other
+    /// format and finally to the URL.
+    pub fn copy_favorite(
+        &mut self,
+        favorite: &Favorite,
+        mode: &ClipboardMode,
+        format: &ClipboardFormat,
+    ) -> Result<()> {
+        if *mode == ClipboardMode::Url {
+            let url = favorite
+                .gif_url
+                .as_deref()
+                .context("No URL available for this favorite")?;
+            return self.copy_text(url);
+        }
+
+        let path = match format {
+            ClipboardFormat::Mp4 => favorite.mp4_filepath.as_ref().or(favorite.filepath.as_ref()),
+            ClipboardFormat::Gif => favorite.filepath.as_ref().or(favorite.mp4_filepath.as_ref()),
+        };
+
+        match (path, favorite.gif_url.as_deref()) {
+            (Some(path), _) => self.copy_file_path(Path::new(path)),
+            (None, Some(url)) => self.copy_text(url),
+            (None, None) => anyhow::bail!("No file or URL available for this favorite"),
+        }
+    }
+
     /// Get text from clipboard
     pub fn get_text(&mut self) -> Result<String> {
         self.clipboard
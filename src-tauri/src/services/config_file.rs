@@ -0,0 +1,439 @@
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use crate::db::Database;
+use crate::models::{ConfigFileIssue, ConfigFileStatus, SettingKey, Settings};
+
+/// Looked for in the config dir in this order; the first that exists is used.
+const FILE_NAMES: [&str; 2] = ["config.toml", "config.json"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    fn of(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        }
+    }
+}
+
+/// The config file, checked against the settings registry. The layout is
+/// the same in TOML and JSON:
+///
+/// ```toml
+/// locked = ["show_ads"]
+///
+/// [settings]
+/// theme = "dark"
+/// show_ads = false
+/// ```
+///
+/// `settings` fill in for keys this install never saved; `locked` keys
+/// always take the file's value (or, without one, keep the stored value)
+/// and can't be changed from the app. Saving from the app only stores the
+/// keys that changed, so the file keeps filling in the rest.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverlay {
+    path: Option<PathBuf>,
+    /// The valid entries, as normalized by `Settings::set`.
+    values: BTreeMap<SettingKey, serde_json::Value>,
+    locked: BTreeSet<SettingKey>,
+    issues: Vec<ConfigFileIssue>,
+    error: Option<String>,
+}
+
+impl ConfigOverlay {
+    /// Read the config file in `dir`. No file is an empty overlay, and so is
+    /// one that can't be read or parsed, apart from carrying the error.
+    pub fn load(dir: &Path) -> Self {
+        let Some(path) = find(dir) else {
+            return Self::default();
+        };
+        let parsed = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))
+            .and_then(|text| Self::parse(&text, ConfigFormat::of(&path)));
+        let mut overlay = parsed.unwrap_or_else(|e| Self {
+            error: Some(format!("{:#}", e)),
+            ..Self::default()
+        });
+        overlay.path = Some(path);
+        overlay
+    }
+
+    /// Fails only when the text isn't TOML or JSON at all; bad entries are
+    /// skipped and recorded as issues.
+    pub fn parse(text: &str, format: ConfigFormat) -> Result<Self> {
+        let entries: BTreeMap<String, serde_json::Value> = match format {
+            ConfigFormat::Toml => toml::from_str(text).context("Not valid TOML")?,
+            ConfigFormat::Json => serde_json::from_str(text).context("Not valid JSON")?,
+        };
+
+        let mut overlay = Self::default();
+        for (name, value) in entries {
+            match name.as_str() {
+                "settings" => overlay.read_settings(value),
+                "locked" => overlay.read_locked(value),
+                _ => overlay.issue(&name, "unknown entry; expected `settings` or `locked`"),
+            }
+        }
+        Ok(overlay)
+    }
+
+    fn read_settings(&mut self, value: serde_json::Value) {
+        let serde_json::Value::Object(entries) = value else {
+            return self.issue("settings", "must be a table of setting names to values");
+        };
+        let mut scratch = Settings::default();
+        for (name, value) in entries {
+            let entry = format!("settings.{}", name);
+            let key = match name.parse::<SettingKey>() {
+                Ok(key) => key,
+                Err(e) => {
+                    self.issue(&entry, e);
+                    continue;
+                }
+            };
+            match scratch.set(key, value) {
+                Ok(()) => {
+                    self.values.insert(key, scratch.get(key));
+                }
+                Err(e) => self.issue(&entry, e),
+            }
+        }
+    }
+
+    fn read_locked(&mut self, value: serde_json::Value) {
+        let serde_json::Value::Array(names) = value else {
+            return self.issue("locked", "must be a list of setting names");
+        };
+        for name in names {
+            match name.as_str().map(str::parse::<SettingKey>) {
+                Some(Ok(key)) => {
+                    self.locked.insert(key);
+                }
+                Some(Err(e)) => self.issue("locked", e),
+                None => self.issue("locked", format!("{} isn't a setting name", name)),
+            }
+        }
+    }
+
+    fn issue(&mut self, key: &str, message: impl Into<String>) {
+        self.issues.push(ConfigFileIssue {
+            key: key.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// What an install that never saved a setting gets.
+    pub fn defaults(&self) -> Settings {
+        let mut settings = Settings::default();
+        self.apply(&mut settings, |_| true);
+        settings
+    }
+
+    /// Put the locked keys back to the file's values.
+    pub fn enforce(&self, settings: &mut Settings) {
+        self.apply(settings, |key| self.locked.contains(&key));
+    }
+
+    /// Take every value in the file, locked or not.
+    pub fn apply_all(&self, settings: &mut Settings) {
+        self.apply(settings, |_| true);
+    }
+
+    fn apply(&self, settings: &mut Settings, include: impl Fn(SettingKey) -> bool) {
+        for (&key, value) in &self.values {
+            if include(key) {
+                // Already validated when the file was read.
+                let _ = settings.set(key, value.clone());
+            }
+        }
+    }
+
+    pub fn is_locked(&self, key: SettingKey) -> bool {
+        self.locked.contains(&key)
+    }
+
+    pub fn locked(&self) -> &BTreeSet<SettingKey> {
+        &self.locked
+    }
+
+    pub fn status(&self) -> ConfigFileStatus {
+        ConfigFileStatus {
+            path: self.path.as_ref().map(|path| path.display().to_string()),
+            locked: self.locked.iter().copied().collect(),
+            issues: self.issues.clone(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+/// When a config file was last modified, and its size: changes whenever the
+/// file is created, edited, replaced or removed.
+pub type ConfigStamp = Option<(PathBuf, SystemTime, u64)>;
+
+/// The optional config file in the app config dir, for provisioning every
+/// install the same way. Holds the overlay as last read.
+pub struct ConfigFile {
+    dir: PathBuf,
+    overlay: RwLock<ConfigOverlay>,
+}
+
+impl ConfigFile {
+    pub fn new(dir: PathBuf) -> Self {
+        let overlay = ConfigOverlay::load(&dir);
+        Self {
+            dir,
+            overlay: RwLock::new(overlay),
+        }
+    }
+
+    pub fn overlay(&self) -> ConfigOverlay {
+        self.overlay
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Re-read the file.
+    pub fn reload(&self) -> ConfigOverlay {
+        let overlay = ConfigOverlay::load(&self.dir);
+        *self.overlay.write().unwrap_or_else(|e| e.into_inner()) = overlay.clone();
+        overlay
+    }
+
+    pub fn stamp(&self) -> ConfigStamp {
+        let path = find(&self.dir)?;
+        let metadata = fs::metadata(&path).ok()?;
+        Some((path, metadata.modified().ok()?, metadata.len()))
+    }
+
+    /// The stored settings with the file on top.
+    pub async fn effective_settings(&self, db: &Database) -> Result<Settings> {
+        let overlay = self.overlay();
+        let mut settings = db.settings().get_with_defaults(overlay.defaults()).await?;
+        overlay.enforce(&mut settings);
+        Ok(settings)
+    }
+
+    /// Write `settings` to the config file, or to a new `config.toml`,
+    /// keeping the file's locked keys. Returns where it went.
+    pub fn export(&self, settings: &Settings) -> Result<PathBuf> {
+        let overlay = self.reload();
+        if let Some(error) = overlay.error {
+            bail!("Fix or remove the config file first: {}", error);
+        }
+        let path = overlay.path.unwrap_or_else(|| self.dir.join(FILE_NAMES[0]));
+        write_config(&path, settings, &overlay.locked)?;
+        self.reload();
+        Ok(path)
+    }
+}
+
+fn find(dir: &Path) -> Option<PathBuf> {
+    FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Write every setting to `path` in the format its extension names.
+/// Unset optional values are left out, since TOML has no null.
+pub fn write_config(path: &Path, settings: &Settings, locked: &BTreeSet<SettingKey>) -> Result<()> {
+    let values: serde_json::Map<String, serde_json::Value> = SettingKey::ALL
+        .into_iter()
+        .map(|key| (key.to_string(), settings.get(key)))
+        .filter(|(_, value)| !value.is_null())
+        .collect();
+
+    let mut file = serde_json::Map::new();
+    if !locked.is_empty() {
+        file.insert("locked".to_string(), serde_json::json!(locked));
+    }
+    file.insert("settings".to_string(), serde_json::Value::Object(values));
+
+    let text = match ConfigFormat::of(path) {
+        ConfigFormat::Toml => toml::to_string_pretty(&file).context("Failed to write TOML")?,
+        ConfigFormat::Json => serde_json::to_string_pretty(&file)? + "\n",
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context("Failed to create config directory")?;
+    }
+    // Written aside and renamed, so the watcher never reads half a file.
+    let partial = path.with_extension("partial");
+    fs::write(&partial, text).with_context(|| format!("Failed to write {}", partial.display()))?;
+    fs::rename(&partial, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::settings::store_settings;
+    use crate::models::{HotkeyAction, Theme};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bad_entries_are_reported_and_the_rest_applies() {
+        let overlay = ConfigOverlay::parse(
+            r#"
+            locked = ["show_ads", "volume"]
+            colour = "blue"
+
+            [settings]
+            theme = "dark"
+            show_ads = false
+            window_width = "wide"
+            volume = 11
+            "#,
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+
+        let keys: Vec<&str> = overlay
+            .issues
+            .iter()
+            .map(|issue| issue.key.as_str())
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "colour",
+                "locked",
+                "settings.volume",
+                "settings.window_width"
+            ]
+        );
+        assert!(overlay.is_locked(SettingKey::ShowAds));
+
+        let defaults = overlay.defaults();
+        assert_eq!(defaults.theme, Theme::Dark);
+        assert!(!defaults.show_ads);
+        assert_eq!(defaults.window_width, Settings::default().window_width);
+    }
+
+    #[test]
+    fn test_invalid_file_is_an_error() {
+        assert!(ConfigOverlay::parse("theme = ", ConfigFormat::Toml).is_err());
+        assert!(ConfigOverlay::parse("{", ConfigFormat::Json).is_err());
+    }
+
+    #[test]
+    fn test_only_locked_keys_are_enforced() {
+        let overlay = ConfigOverlay::parse(
+            r#"{"locked": ["show_ads"], "settings": {"theme": "dark", "show_ads": false}}"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+
+        let mut settings = Settings {
+            theme: Theme::Light,
+            ..Settings::default()
+        };
+        overlay.enforce(&mut settings);
+        assert_eq!(settings.theme, Theme::Light);
+        assert!(!settings.show_ads);
+
+        overlay.apply_all(&mut settings);
+        assert_eq!(settings.theme, Theme::Dark);
+    }
+
+    #[test]
+    fn test_export_round_trips_in_both_formats() {
+        let temp = TempDir::new().unwrap();
+        let mut settings = Settings {
+            theme: Theme::Dark,
+            sync_folder: None,
+            ..Settings::default()
+        };
+        settings
+            .hotkeys
+            .insert(HotkeyAction::PasteRecent, "Ctrl+Shift+V".to_string());
+        let locked = BTreeSet::from([SettingKey::Theme]);
+
+        for name in FILE_NAMES {
+            let path = temp.path().join(name);
+            write_config(&path, &settings, &locked).unwrap();
+            let overlay = ConfigOverlay::load(temp.path());
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!(overlay.path, Some(path));
+            assert!(overlay.issues.is_empty(), "{:?}", overlay.issues);
+            assert_eq!(overlay.locked, locked);
+            let loaded = overlay.defaults();
+            for key in SettingKey::ALL {
+                assert_eq!(loaded.get(key), settings.get(key), "{} in {}", key, name);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_fills_in_unsaved_keys_and_overrides_locked_ones() {
+        let temp = TempDir::new().unwrap();
+        let db = Database::new(temp.path().join("test.db")).await.unwrap();
+        db.run_migrations().await.unwrap();
+        let stored = Settings {
+            window_width: 1024,
+            show_ads: true,
+            ..Settings::default()
+        };
+        db.settings().save(&stored).await.unwrap();
+        sqlx::query("DELETE FROM settings WHERE key = 'theme'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        fs::write(
+            temp.path().join("config.toml"),
+            "locked = [\"show_ads\"]\n[settings]\ntheme = \"dark\"\nshow_ads = false\nwindow_width = 900\n",
+        )
+        .unwrap();
+        let config = ConfigFile::new(temp.path().to_path_buf());
+        assert!(config.stamp().is_some());
+
+        let settings = config.effective_settings(&db).await.unwrap();
+        assert_eq!(settings.theme, Theme::Dark);
+        assert!(!settings.show_ads);
+        assert_eq!(settings.window_width, 1024);
+    }
+
+    #[tokio::test]
+    async fn test_saving_keeps_following_the_file_for_untouched_keys() {
+        let temp = TempDir::new().unwrap();
+        let db = Database::new(temp.path().join("test.db")).await.unwrap();
+        db.run_migrations().await.unwrap();
+        let file = temp.path().join("config.toml");
+        fs::write(
+            &file,
+            "[settings]\ntheme = \"dark\"\ntray_recent_count = 3\n",
+        )
+        .unwrap();
+        let config = ConfigFile::new(temp.path().to_path_buf());
+
+        let mut settings = config.effective_settings(&db).await.unwrap();
+        settings.tray_recent_count = 8;
+        store_settings(&db, &config, settings, |_| Ok(()))
+            .await
+            .unwrap();
+
+        fs::write(
+            &file,
+            "[settings]\ntheme = \"light\"\ntray_recent_count = 1\n",
+        )
+        .unwrap();
+        config.reload();
+        let settings = config.effective_settings(&db).await.unwrap();
+        assert_eq!(settings.theme, Theme::Light);
+        assert_eq!(settings.tray_recent_count, 8);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::services::klipy::KlipyItem;
+
+/// The `content_denylist` setting, ready to match. A term hides any title,
+/// slug, tag or suggestion that contains it as whole words, so `gore` hides
+/// "gore fest" but not "gorilla"; multi-word terms must appear in order.
+#[derive(Debug, Clone, Default)]
+pub struct ContentDenylist {
+    terms: Vec<Vec<String>>,
+}
+
+impl ContentDenylist {
+    pub fn new(terms: &[String]) -> Self {
+        let terms = terms
+            .iter()
+            .map(|term| words(term))
+            .filter(|words| !words.is_empty())
+            .collect();
+        Self { terms }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Whether `text` contains a denied term.
+    pub fn is_denied(&self, text: &str) -> bool {
+        if self.terms.is_empty() {
+            return false;
+        }
+        let words = words(text);
+        self.terms.iter().any(|term| {
+            words
+                .windows(term.len())
+                .any(|window| window == term.as_slice())
+        })
+    }
+
+    /// Whether `item` should be hidden: its title, slug or a tag is denied.
+    /// Ads are never hidden.
+    pub fn denies_item(&self, item: &KlipyItem) -> bool {
+        let Some((title, slug, tags)) = item.labels() else {
+            return false;
+        };
+        self.is_denied(title) || self.is_denied(slug) || tags.iter().any(|tag| self.is_denied(tag))
+    }
+
+    /// `suggestions` without the denied ones.
+    pub fn filter_suggestions(&self, suggestions: Vec<String>) -> Vec<String> {
+        suggestions
+            .into_iter()
+            .filter(|suggestion| !self.is_denied(suggestion))
+            .collect()
+    }
+}
+
+/// Lowercased words, splitting on anything that isn't a letter or digit so
+/// slugs (`happy-cat-f7u`) and hashtags match like titles.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn denylist(terms: &[&str]) -> ContentDenylist {
+        ContentDenylist::new(&terms.iter().map(|t| t.to_string()).collect::<Vec<_>>())
+    }
+
+    fn gif(title: &str, slug: &str, tags: &[&str]) -> KlipyItem {
+        serde_json::from_value(serde_json::json!({
+            "type": "gif",
+            "id": 1,
+            "slug": slug,
+            "title": title,
+            "tags": tags,
+            "file": {
+                "hd": { "gif": {"url": "u", "width": 1, "height": 1} },
+                "md": { "gif": {"url": "u", "width": 1, "height": 1} },
+                "sm": { "gif": {"url": "u", "width": 1, "height": 1} },
+                "xs": { "gif": {"url": "u", "width": 1, "height": 1} }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_terms_match_whole_words_case_insensitively() {
+        let denylist = denylist(&["Gore", "  ", "spider man"]);
+        assert!(denylist.is_denied("GORE fest"));
+        assert!(!denylist.is_denied("gorilla"));
+        assert!(denylist.is_denied("the Spider-Man swing"));
+        assert!(!denylist.is_denied("man spider"));
+        assert!(!ContentDenylist::default().is_denied("gore"));
+    }
+
+    #[test]
+    fn test_items_are_denied_by_title_slug_or_tag() {
+        let denylist = denylist(&["blood"]);
+        assert!(denylist.denies_item(&gif("Oops", "blood-splatter-x1", &[])));
+        assert!(denylist.denies_item(&gif("Oops", "oops-x1", &["#Blood"])));
+        assert!(!denylist.denies_item(&gif("Bloodhound", "bloodhound-x1", &["dog"])));
+        assert!(!denylist.denies_item(&KlipyItem::Unknown));
+
+        let suggestions = vec!["blood moon".to_string(), "moon".to_string()];
+        assert_eq!(denylist.filter_suggestions(suggestions), vec!["moon"]);
+    }
+}
@@ -18,6 +18,11 @@ impl Downloader {
         })
     }
 
+    /// Root of the downloaded and imported media.
+    pub fn media_dir(&self) -> &Path {
+        &self.media_dir
+    }
+
     /// Ensure media directory structure exists
     pub async fn ensure_directories(&self) -> Result<()> {
         fs::create_dir_all(&self.media_dir)
@@ -167,6 +172,37 @@ impl Downloader {
         Ok(file_path)
     }
 
+    /// Where a file named `filename` of `media_type` lives in the media
+    /// directory.
+    pub fn media_path(&self, filename: &str, media_type: &str) -> PathBuf {
+        let subdir = match media_type {
+            "gif" => "gifs",
+            "image" => "images",
+            "video" => "videos",
+            _ => "gifs",
+        };
+        self.media_dir.join(subdir).join(filename)
+    }
+
+    /// Copy `source` into the media directory as `filename`. Like `download`,
+    /// an existing file of that name is kept.
+    pub async fn store_file(
+        &self,
+        source: &Path,
+        filename: &str,
+        media_type: &str,
+    ) -> Result<PathBuf> {
+        self.ensure_directories().await?;
+
+        let dest_path = self.media_path(filename, media_type);
+        if !dest_path.exists() {
+            fs::copy(source, &dest_path)
+                .await
+                .context("Failed to copy file")?;
+        }
+        Ok(dest_path)
+    }
+
     /// Copy a local file to the media directory
     pub async fn import_local_file(&self, source_path: &Path) -> Result<PathBuf> {
         self.ensure_directories().await?;
@@ -231,6 +267,77 @@ impl Downloader {
             .context("Failed to delete file")?;
         Ok(())
     }
+
+    /// Where a trashed favorite's file waits. Prefixed with the favorite's id
+    /// so files with the same name from different folders can't collide.
+    pub fn trash_path(&self, favorite_id: i64, original: &Path) -> PathBuf {
+        let name = original
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.media_dir
+            .join("trash")
+            .join(format!("{}-{}", favorite_id, name))
+    }
+
+    /// Move a favorite's file into the trash. Returns where it went.
+    pub async fn move_to_trash(&self, favorite_id: i64, original: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(self.media_dir.join("trash"))
+            .await
+            .context("Failed to create trash directory")?;
+        let trashed = self.trash_path(favorite_id, original);
+        move_file(original, &trashed).await?;
+        Ok(trashed)
+    }
+
+    /// Put a trashed file back where it was. If something else has taken
+    /// its place since, the file is restored next to it under a free name
+    /// instead. Returns where it went.
+    pub async fn restore_from_trash(&self, favorite_id: i64, original: &Path) -> Result<PathBuf> {
+        if let Some(parent) = original.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .context("Failed to recreate media directory")?;
+        }
+        let restored = free_path(original);
+        move_file(&self.trash_path(favorite_id, original), &restored).await?;
+        Ok(restored)
+    }
+}
+
+/// `path` if nothing is there, else the first of `name-1.ext`, `name-2.ext`,
+/// … that is free.
+fn free_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{}-{}{}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .expect("ran out of file names")
+}
+
+/// Rename, falling back to copy-and-delete across filesystems (imported
+/// files can live anywhere).
+async fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)
+        .await
+        .with_context(|| format!("Failed to move {}", from.display()))?;
+    fs::remove_file(from)
+        .await
+        .with_context(|| format!("Failed to remove {}", from.display()))?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -256,6 +363,47 @@ mod tests {
         assert!(downloader.media_dir.join("videos").exists());
     }
 
+    #[tokio::test]
+    async fn test_trash_round_trip() {
+        let (downloader, _temp) = create_test_downloader().await;
+        downloader.ensure_directories().await.unwrap();
+
+        let original = downloader.media_dir.join("gifs").join("cat.gif");
+        fs::write(&original, b"GIF89a").await.unwrap();
+
+        let trashed = downloader.move_to_trash(7, &original).await.unwrap();
+        assert_eq!(
+            trashed,
+            downloader.media_dir.join("trash").join("7-cat.gif")
+        );
+        assert!(!original.exists());
+        assert!(trashed.exists());
+
+        let restored = downloader.restore_from_trash(7, &original).await.unwrap();
+        assert_eq!(restored, original);
+        assert!(original.exists());
+        assert!(!trashed.exists());
+    }
+
+    #[tokio::test]
+    async fn test_restore_does_not_overwrite() {
+        let (downloader, _temp) = create_test_downloader().await;
+        downloader.ensure_directories().await.unwrap();
+
+        let original = downloader.media_dir.join("gifs").join("cat.gif");
+        fs::write(&original, b"trashed").await.unwrap();
+        downloader.move_to_trash(7, &original).await.unwrap();
+        fs::write(&original, b"newcomer").await.unwrap();
+        fs::write(original.with_file_name("cat-1.gif"), b"another")
+            .await
+            .unwrap();
+
+        let restored = downloader.restore_from_trash(7, &original).await.unwrap();
+        assert_eq!(restored, original.with_file_name("cat-2.gif"));
+        assert_eq!(fs::read(&restored).await.unwrap(), b"trashed");
+        assert_eq!(fs::read(&original).await.unwrap(), b"newcomer");
+    }
+
     #[tokio::test]
     #[ignore] // Requires internet connection
     async fn test_download_file() {
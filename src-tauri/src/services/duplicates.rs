@@ -0,0 +1,258 @@
+use anyhow::{Context, Result};
+use image::imageops::{self, FilterType};
+use image::GrayImage;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::models::Favorite;
+use crate::services::for_each_frame;
+
+/// How alike two favorites must be to count as the same reaction. Allows
+/// about 20 of 192 bits to differ — enough for resizes and re-encodes, not
+/// for two different cuts of the same clip.
+pub const DEFAULT_DUPLICATE_THRESHOLD: f64 = 0.9;
+
+/// Where in the animation frames are sampled, as fractions of its length.
+/// Static images use their one frame for every sample.
+const SAMPLE_POINTS: [f64; 3] = [0.25, 0.5, 0.75];
+
+/// dHashes of a few frames taken at the same relative points of the
+/// animation, so the same GIF at a different size or frame rate still lines
+/// up frame for frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PerceptualHash([u64; SAMPLE_POINTS.len()]);
+
+impl PerceptualHash {
+    /// 1.0 for identical hashes, falling with each differing bit.
+    pub fn similarity(&self, other: &PerceptualHash) -> f64 {
+        let differing: u32 = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum();
+        1.0 - differing as f64 / (64 * self.0.len()) as f64
+    }
+}
+
+impl fmt::Display for PerceptualHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for hash in self.0 {
+            write!(f, "{:016x}", hash)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PerceptualHash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut hashes = [0u64; SAMPLE_POINTS.len()];
+        if s.len() != hashes.len() * 16 || !s.is_ascii() {
+            return Err(format!("Malformed perceptual hash: {}", s));
+        }
+        for (i, hash) in hashes.iter_mut().enumerate() {
+            *hash = u64::from_str_radix(&s[i * 16..(i + 1) * 16], 16)
+                .map_err(|_| format!("Malformed perceptual hash: {}", s))?;
+        }
+        Ok(PerceptualHash(hashes))
+    }
+}
+
+/// Difference hash of one frame: shrink to 9×8 grey and record whether each
+/// pixel is darker than its right-hand neighbour.
+fn dhash(frame: &GrayImage) -> u64 {
+    let small = imageops::resize(frame, 9, 8, FilterType::Triangle);
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y).0[0];
+            let right = small.get_pixel(x + 1, y).0[0];
+            hash = (hash << 1) | u64::from(left < right);
+        }
+    }
+    hash
+}
+
+fn sample(frame_hashes: &[u64]) -> PerceptualHash {
+    let last = frame_hashes.len() - 1;
+    PerceptualHash(SAMPLE_POINTS.map(|point| frame_hashes[(last as f64 * point).round() as usize]))
+}
+
+/// Hash an image or GIF file.
+pub fn hash_file(path: &Path) -> Result<PerceptualHash> {
+    let mut frame_hashes = Vec::new();
+    for_each_frame(path, |frame| {
+        frame_hashes.push(dhash(&imageops::grayscale(frame)))
+    })?;
+    Ok(sample(&frame_hashes))
+}
+
+/// `hash_file` off the async runtime, logging rather than failing — a missing
+/// hash only means the favorite is left out of duplicate detection.
+pub async fn perceptual_hash(path: PathBuf) -> Option<PerceptualHash> {
+    let result = tokio::task::spawn_blocking(move || {
+        hash_file(&path).with_context(|| format!("Failed to hash {}", path.display()))
+    })
+    .await;
+    match result {
+        Ok(Ok(hash)) => Some(hash),
+        Ok(Err(e)) => {
+            eprintln!("Warning: {:#}", e);
+            None
+        }
+        Err(e) => {
+            eprintln!("Warning: perceptual hash task failed: {}", e);
+            None
+        }
+    }
+}
+
+/// Groups of ids whose hashes are at least `threshold` alike, directly or
+/// through a chain of near-identical copies. Singletons are left out; ids
+/// keep their input order within a group, and groups are ordered by their
+/// first member.
+pub fn group_duplicates(hashes: &[(i64, PerceptualHash)], threshold: f64) -> Vec<Vec<i64>> {
+    let mut parent: Vec<usize> = (0..hashes.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for i in 0..hashes.len() {
+        for j in i + 1..hashes.len() {
+            if hashes[i].1.similarity(&hashes[j].1) >= threshold {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<i64>> = Vec::new();
+    let mut group_of_root = std::collections::HashMap::new();
+    for (i, (id, _)) in hashes.iter().enumerate() {
+        let r = root(&mut parent, i);
+        let index = *group_of_root.entry(r).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[index].push(*id);
+    }
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+/// The copy worth keeping: most pixels, then the biggest file (less
+/// compression), then the oldest.
+pub fn best_quality(favorites: &[Favorite]) -> Option<&Favorite> {
+    favorites.iter().max_by(|a, b| {
+        let pixels =
+            |f: &Favorite| i64::from(f.width.unwrap_or(0)) * i64::from(f.height.unwrap_or(0));
+        pixels(a)
+            .cmp(&pixels(b))
+            .then(a.file_size.unwrap_or(0).cmp(&b.file_size.unwrap_or(0)))
+            .then(b.created_at.cmp(&a.created_at))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MediaType;
+    use image::{Delay, Frame, Rgba, RgbaImage};
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    fn gradient(width: u32, height: u32, flip: bool) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let v = ((x * 255 / width + y * 64 / height) % 256) as u8;
+            let v = if flip { 255 - v } else { v };
+            Rgba([v, v / 2, 255 - v, 255])
+        })
+    }
+
+    #[test]
+    fn test_hash_round_trips_as_hex() {
+        let hash = PerceptualHash([1, u64::MAX, 0xdead_beef]);
+        let hex = hash.to_string();
+        assert_eq!(hex.len(), 48);
+        assert_eq!(hex.parse::<PerceptualHash>(), Ok(hash));
+        assert!("abc".parse::<PerceptualHash>().is_err());
+    }
+
+    #[test]
+    fn test_resized_copy_is_a_duplicate() {
+        let temp = TempDir::new().unwrap();
+        let big = temp.path().join("big.png");
+        let small = temp.path().join("small.png");
+        let other = temp.path().join("other.png");
+        gradient(400, 300, false).save(&big).unwrap();
+        imageops::resize(&gradient(400, 300, false), 120, 90, FilterType::Lanczos3)
+            .save(&small)
+            .unwrap();
+        gradient(400, 300, true).save(&other).unwrap();
+
+        let big = hash_file(&big).unwrap();
+        let small = hash_file(&small).unwrap();
+        let other = hash_file(&other).unwrap();
+        assert!(big.similarity(&small) >= DEFAULT_DUPLICATE_THRESHOLD);
+        assert!(big.similarity(&other) < DEFAULT_DUPLICATE_THRESHOLD);
+    }
+
+    #[test]
+    fn test_hashes_gif_frames() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("anim.gif");
+        {
+            let file = File::create(&path).unwrap();
+            let mut encoder = image::codecs::gif::GifEncoder::new(file);
+            let frames = (0..4).map(|i| {
+                Frame::from_parts(
+                    gradient(64, 48, i >= 2),
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(100, 1),
+                )
+            });
+            encoder.encode_frames(frames).unwrap();
+        }
+
+        let hash = hash_file(&path).unwrap();
+        // The first sample falls in the unflipped half, the last in the flipped.
+        assert_ne!(hash.0[0], hash.0[2]);
+    }
+
+    #[test]
+    fn test_group_duplicates_is_transitive() {
+        let a = PerceptualHash([0, 0, 0]);
+        let b = PerceptualHash([0b1111, 0, 0]);
+        let c = PerceptualHash([0b1111_1111, 0, 0]);
+        let far = PerceptualHash([u64::MAX, u64::MAX, u64::MAX]);
+
+        // a~b and b~c at 4 bits apart; a and c are 8 bits apart.
+        let threshold = 1.0 - 4.0 / 192.0;
+        let groups = group_duplicates(&[(1, a), (2, far), (3, c), (4, b)], threshold);
+        assert_eq!(groups, vec![vec![1, 3, 4]]);
+    }
+
+    #[test]
+    fn test_best_quality_prefers_resolution_then_size() {
+        let mut small =
+            Favorite::new("small.gif".into(), None, MediaType::Gif).with_dimensions(100, 100);
+        small.file_size = Some(10_000);
+        let mut big =
+            Favorite::new("big.gif".into(), None, MediaType::Gif).with_dimensions(400, 300);
+        big.file_size = Some(5_000);
+        let mut big_heavier = big.clone();
+        big_heavier.filename = "big2.gif".into();
+        big_heavier.file_size = Some(8_000);
+
+        let favorites = [small, big, big_heavier];
+        assert_eq!(best_quality(&favorites).unwrap().filename, "big2.gif");
+    }
+}
@@ -1,14 +1,79 @@
+use crate::models::{ContentFilter, MediaType};
 use anyhow::{Context, Result};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 const KLIPY_API_BASE_URL: &str = "https://api.klipy.co/api/v1";
 
+/// Categories change a few times a day at most.
+const CATEGORIES_TTL: Duration = Duration::from_secs(60 * 60);
+/// Autocomplete and related searches, per query.
+const SUGGESTIONS_TTL: Duration = Duration::from_secs(10 * 60);
+/// Most responses a cache holds before it starts over.
+const MAX_CACHE_ENTRIES: usize = 256;
+
+/// Locale asked for again when the chosen one has no results.
+const FALLBACK_LOCALE: &str = "en";
+
+/// What a search or trending request asks Klipy for. Each type has its own
+/// endpoints and item shape.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KlipyContentType {
+    #[default]
+    Gif,
+    /// Cut-out GIFs with a transparent background.
+    Sticker,
+    /// Short videos with sound.
+    Clip,
+    /// Static images.
+    Meme,
+}
+
+impl KlipyContentType {
+    /// Path segment of the API endpoints.
+    fn api_path(self) -> &'static str {
+        match self {
+            KlipyContentType::Gif => "gifs",
+            KlipyContentType::Sticker => "stickers",
+            KlipyContentType::Clip => "clips",
+            KlipyContentType::Meme => "static-memes",
+        }
+    }
+
+    /// What a favorite saved from this type holds. Stickers are kept as
+    /// WebP or PNG, since GIF can't do partial transparency.
+    pub fn media_type(self) -> MediaType {
+        match self {
+            KlipyContentType::Gif => MediaType::Gif,
+            KlipyContentType::Sticker | KlipyContentType::Meme => MediaType::Image,
+            KlipyContentType::Clip => MediaType::Video,
+        }
+    }
+
+    /// The item's page on klipy.com.
+    pub fn page_url(self, slug: &str) -> String {
+        let section = match self {
+            KlipyContentType::Gif => "gifs",
+            KlipyContentType::Sticker => "stickers",
+            KlipyContentType::Clip => "clips",
+            KlipyContentType::Meme => "memes",
+        };
+        format!("https://klipy.com/{}/{}", section, slug)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KlipyGif {
     pub id: i64,
     pub slug: String,
     pub title: String,
+    /// Not on every response.
+    #[serde(default)]
+    pub tags: Vec<String>,
     pub file: KlipyFileFormats,
 }
 
@@ -27,6 +92,77 @@ pub struct KlipySizeFormat {
     pub mp4: Option<KlipyMediaFile>,
     pub webm: Option<KlipyMediaFile>,
     pub jpg: Option<KlipyMediaFile>,
+    /// Stickers only.
+    #[serde(default)]
+    pub png: Option<KlipyMediaFile>,
+}
+
+/// Same shape as a GIF; the WebP and PNG renditions keep the alpha channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KlipySticker {
+    pub id: i64,
+    pub slug: String,
+    pub title: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub file: KlipyFileFormats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KlipyClip {
+    pub id: i64,
+    pub slug: String,
+    pub title: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Seconds.
+    pub duration: Option<f64>,
+    pub file: KlipyClipFiles,
+}
+
+/// A clip's MP4 carries the audio; the rest are silent previews.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KlipyClipFiles {
+    pub mp4: KlipyMediaFile,
+    pub gif: Option<KlipyMediaFile>,
+    pub webp: Option<KlipyMediaFile>,
+    pub jpg: Option<KlipyMediaFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KlipyMeme {
+    pub id: i64,
+    pub slug: String,
+    pub title: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub file: KlipyImageFormats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KlipyImageFormats {
+    pub hd: KlipyImageSizeFormat,
+    pub md: KlipyImageSizeFormat,
+    pub sm: KlipyImageSizeFormat,
+    pub xs: KlipyImageSizeFormat,
+}
+
+/// Renditions of a static image; which ones are present varies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KlipyImageSizeFormat {
+    pub png: Option<KlipyMediaFile>,
+    pub jpg: Option<KlipyMediaFile>,
+    pub webp: Option<KlipyMediaFile>,
+}
+
+impl KlipyImageSizeFormat {
+    /// The best rendition there is, lossless first.
+    pub fn best(&self) -> Option<&KlipyMediaFile> {
+        self.png
+            .as_ref()
+            .or(self.webp.as_ref())
+            .or(self.jpg.as_ref())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,18 +184,34 @@ pub struct KlipyAd {
     pub content: String,
 }
 
-/// A single item in `data.data` — either a GIF or an ad. New variants Klipy
-/// may add in the future are swallowed as `Unknown` so we never blow up the
-/// whole search response on an unrecognized `type`.
+/// A single item in `data.data` — a piece of content or an ad. New variants
+/// Klipy may add in the future are swallowed as `Unknown` so we never blow
+/// up the whole search response on an unrecognized `type`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum KlipyItem {
     Gif(KlipyGif),
+    Sticker(KlipySticker),
+    Clip(KlipyClip),
+    Meme(KlipyMeme),
     Ad(KlipyAd),
     #[serde(other)]
     Unknown,
 }
 
+impl KlipyItem {
+    /// Title, slug and tags, for content items.
+    pub fn labels(&self) -> Option<(&str, &str, &[String])> {
+        match self {
+            KlipyItem::Gif(gif) => Some((&gif.title, &gif.slug, &gif.tags)),
+            KlipyItem::Sticker(sticker) => Some((&sticker.title, &sticker.slug, &sticker.tags)),
+            KlipyItem::Clip(clip) => Some((&clip.title, &clip.slug, &clip.tags)),
+            KlipyItem::Meme(meme) => Some((&meme.title, &meme.slug, &meme.tags)),
+            KlipyItem::Ad(_) | KlipyItem::Unknown => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KlipySearchResponse {
     pub result: bool,
@@ -134,9 +286,96 @@ impl AdContext {
     }
 }
 
+/// Content rating and locale sent with Klipy requests. Every call takes the
+/// locale; searches and trending take the rating too.
+#[derive(Debug, Clone)]
+pub struct ContentContext {
+    pub filter: ContentFilter,
+    /// `None` leaves the locale to the server.
+    pub locale: Option<String>,
+}
+
+impl ContentContext {
+    /// The same request in English, unless it already is.
+    pub fn english_fallback(&self) -> Option<ContentContext> {
+        let locale = self.locale.as_deref()?;
+        let language = locale.split('_').next().unwrap_or_default();
+        (language != FALLBACK_LOCALE).then(|| ContentContext {
+            filter: self.filter,
+            locale: Some(FALLBACK_LOCALE.to_string()),
+        })
+    }
+
+    fn locale_params(&self) -> Vec<(&'static str, String)> {
+        self.locale
+            .iter()
+            .map(|locale| ("locale", locale.clone()))
+            .collect()
+    }
+
+    fn query_params(&self) -> Vec<(&'static str, String)> {
+        let filter = match self.filter {
+            ContentFilter::Strict => "high",
+            ContentFilter::Moderate => "medium",
+            ContentFilter::Off => "off",
+        };
+        let mut params = vec![("content_filter", filter.to_string())];
+        params.extend(self.locale_params());
+        params
+    }
+
+    /// Cache key for `parts` of a request in this locale.
+    fn cache_key(&self, parts: &[&str]) -> String {
+        let mut key = self.locale.clone().unwrap_or_default();
+        for part in parts {
+            key.push('\n');
+            key.push_str(part);
+        }
+        key
+    }
+}
+
+/// Responses kept for `ttl`, by request. Holds at most `MAX_CACHE_ENTRIES`;
+/// when full, expired entries go first and then everything.
+struct ResponseCache<V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, V)>>,
+}
+
+impl<V: Clone> ResponseCache<V> {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<V> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(key)
+            .filter(|(stored, _)| stored.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn insert(&self, key: String, value: V) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= MAX_CACHE_ENTRIES {
+            entries.retain(|_, (stored, _)| stored.elapsed() < self.ttl);
+            if entries.len() >= MAX_CACHE_ENTRIES {
+                entries.clear();
+            }
+        }
+        entries.insert(key, (Instant::now(), value));
+    }
+}
+
 pub struct KlipyClient {
     client: Client,
     api_key: String,
+    categories_cache: ResponseCache<KlipyCategoriesResponse>,
+    autocomplete_cache: ResponseCache<Vec<String>>,
+    suggestions_cache: ResponseCache<Vec<String>>,
 }
 
 impl KlipyClient {
@@ -149,24 +388,41 @@ impl KlipyClient {
             .build()
             .unwrap_or_else(|_| Client::new());
 
-        Self { client, api_key }
+        Self {
+            client,
+            api_key,
+            categories_cache: ResponseCache::new(CATEGORIES_TTL),
+            autocomplete_cache: ResponseCache::new(SUGGESTIONS_TTL),
+            suggestions_cache: ResponseCache::new(SUGGESTIONS_TTL),
+        }
     }
 
-    /// Search for GIFs on Klipy
+    /// Search Klipy for `content_type`
     pub async fn search(
         &self,
+        content_type: KlipyContentType,
         query: &str,
         per_page: u32,
         page: u32,
+        content: &ContentContext,
         ad_context: Option<&AdContext>,
     ) -> Result<KlipySearchResponse> {
-        let url = format!("{}/{}/gifs/search", KLIPY_API_BASE_URL, self.api_key);
+        let url = format!(
+            "{}/{}/{}/search",
+            KLIPY_API_BASE_URL,
+            self.api_key,
+            content_type.api_path()
+        );
 
-        let mut req = self.client.get(&url).query(&[
-            ("q", query),
-            ("per_page", &per_page.to_string()),
-            ("page", &page.to_string()),
-        ]);
+        let mut req = self
+            .client
+            .get(&url)
+            .query(&[
+                ("q", query),
+                ("per_page", &per_page.to_string()),
+                ("page", &page.to_string()),
+            ])
+            .query(&content.query_params());
         if let Some(ctx) = ad_context {
             req = req.query(&ctx.query_params());
         }
@@ -186,19 +442,30 @@ impl KlipyClient {
             .context("Failed to parse Klipy API response")
     }
 
-    /// Get trending GIFs
+    /// Get trending items of `content_type`
     pub async fn trending(
         &self,
+        content_type: KlipyContentType,
         per_page: u32,
         page: u32,
+        content: &ContentContext,
         ad_context: Option<&AdContext>,
     ) -> Result<KlipySearchResponse> {
-        let url = format!("{}/{}/gifs/trending", KLIPY_API_BASE_URL, self.api_key);
+        let url = format!(
+            "{}/{}/{}/trending",
+            KLIPY_API_BASE_URL,
+            self.api_key,
+            content_type.api_path()
+        );
 
         let mut req = self
             .client
             .get(&url)
-            .query(&[("per_page", &per_page.to_string()), ("page", &page.to_string())]);
+            .query(&[
+                ("per_page", &per_page.to_string()),
+                ("page", &page.to_string()),
+            ])
+            .query(&content.query_params());
         if let Some(ctx) = ad_context {
             req = req.query(&ctx.query_params());
         }
@@ -251,12 +518,18 @@ impl KlipyClient {
     }
 
     /// Get GIF categories
-    pub async fn categories(&self) -> Result<KlipyCategoriesResponse> {
+    pub async fn categories(&self, content: &ContentContext) -> Result<KlipyCategoriesResponse> {
+        let key = content.cache_key(&[]);
+        if let Some(cached) = self.categories_cache.get(&key) {
+            return Ok(cached);
+        }
+
         let url = format!("{}/{}/gifs/categories", KLIPY_API_BASE_URL, self.api_key);
 
         let response = self
             .client
             .get(&url)
+            .query(&content.locale_params())
             .send()
             .await
             .context("Failed to send request to Klipy API")?;
@@ -265,14 +538,26 @@ impl KlipyClient {
             anyhow::bail!("Klipy API returned error status: {}", response.status());
         }
 
-        response
+        let categories = response
             .json::<KlipyCategoriesResponse>()
             .await
-            .context("Failed to parse Klipy API categories response")
+            .context("Failed to parse Klipy API categories response")?;
+        self.categories_cache.insert(key, categories.clone());
+        Ok(categories)
     }
 
     /// Get autocomplete suggestions for a query
-    pub async fn autocomplete(&self, query: &str, limit: u32) -> Result<Vec<String>> {
+    pub async fn autocomplete(
+        &self,
+        query: &str,
+        limit: u32,
+        content: &ContentContext,
+    ) -> Result<Vec<String>> {
+        let key = content.cache_key(&[query, &limit.to_string()]);
+        if let Some(cached) = self.autocomplete_cache.get(&key) {
+            return Ok(cached);
+        }
+
         let url = format!(
             "{}/{}/autocomplete/{}",
             KLIPY_API_BASE_URL, self.api_key, query
@@ -282,6 +567,7 @@ impl KlipyClient {
             .client
             .get(&url)
             .query(&[("limit", &limit.to_string())])
+            .query(&content.locale_params())
             .send()
             .await
             .context("Failed to send request to Klipy API")?;
@@ -295,11 +581,22 @@ impl KlipyClient {
             .await
             .context("Failed to parse Klipy API autocomplete response")?;
 
+        self.autocomplete_cache.insert(key, result.data.clone());
         Ok(result.data)
     }
 
     /// Get search suggestions for a query
-    pub async fn search_suggestions(&self, query: &str, limit: u32) -> Result<Vec<String>> {
+    pub async fn search_suggestions(
+        &self,
+        query: &str,
+        limit: u32,
+        content: &ContentContext,
+    ) -> Result<Vec<String>> {
+        let key = content.cache_key(&[query, &limit.to_string()]);
+        if let Some(cached) = self.suggestions_cache.get(&key) {
+            return Ok(cached);
+        }
+
         let url = format!(
             "{}/{}/search-suggestions/{}",
             KLIPY_API_BASE_URL, self.api_key, query
@@ -309,6 +606,7 @@ impl KlipyClient {
             .client
             .get(&url)
             .query(&[("limit", &limit.to_string())])
+            .query(&content.locale_params())
             .send()
             .await
             .context("Failed to send request to Klipy API")?;
@@ -322,6 +620,7 @@ impl KlipyClient {
             .await
             .context("Failed to parse Klipy API search suggestions response")?;
 
+        self.suggestions_cache.insert(key, result.data.clone());
         Ok(result.data)
     }
 }
@@ -336,6 +635,35 @@ mod tests {
         assert_eq!(client.api_key, "test_key");
     }
 
+    #[test]
+    fn test_cache_keys_and_fallback_depend_on_locale() {
+        let content = |locale: Option<&str>| ContentContext {
+            filter: ContentFilter::Moderate,
+            locale: locale.map(str::to_string),
+        };
+        let german = content(Some("de_DE"));
+        let cache = ResponseCache::new(Duration::from_secs(60));
+        cache.insert(german.cache_key(&["cat"]), vec!["katze".to_string()]);
+        assert_eq!(
+            cache.get(&german.cache_key(&["cat"])),
+            Some(vec!["katze".to_string()])
+        );
+        assert_eq!(cache.get(&content(Some("en")).cache_key(&["cat"])), None);
+        assert_eq!(cache.get(&content(None).cache_key(&["cat"])), None);
+
+        let fallback = german.english_fallback().unwrap();
+        assert_eq!(fallback.locale.as_deref(), Some("en"));
+        assert!(content(Some("en_GB")).english_fallback().is_none());
+        assert!(content(None).english_fallback().is_none());
+    }
+
+    #[test]
+    fn test_expired_responses_are_not_served() {
+        let cache = ResponseCache::new(Duration::ZERO);
+        cache.insert("key".to_string(), 1);
+        assert_eq!(cache.get("key"), None);
+    }
+
     #[test]
     fn test_klipy_response_deserialization() {
         let json = r#"{
@@ -395,7 +723,7 @@ mod tests {
                           "xs": { "gif": {"url":"u","width":1,"height":1} }
                       }
                     },
-                    { "type": "sticker", "id": 2 }
+                    { "type": "hologram", "id": 2 }
                 ]
             }
         }"#;
@@ -404,7 +732,75 @@ mod tests {
         assert_eq!(response.data.data.len(), 3);
         assert!(matches!(response.data.data[0], KlipyItem::Ad(_)));
         assert!(matches!(response.data.data[1], KlipyItem::Gif(_)));
-        // Unknown types (e.g. future "sticker") are tolerated, not fatal.
+        // Unknown types (e.g. a future "hologram") are tolerated, not fatal.
         assert!(matches!(response.data.data[2], KlipyItem::Unknown));
     }
+
+    #[test]
+    fn test_stickers_clips_and_memes_deserialize() {
+        let json = r#"{
+            "result": true,
+            "data": {
+                "data": [
+                    { "type": "sticker", "id": 2, "slug": "wave-s1", "title": "Wave",
+                      "file": {
+                          "hd": {
+                              "gif": {"url":"u.gif","width":1,"height":1},
+                              "webp": {"url":"u.webp","width":1,"height":1},
+                              "png": {"url":"u.png","width":1,"height":1}
+                          },
+                          "md": { "gif": {"url":"u","width":1,"height":1} },
+                          "sm": { "gif": {"url":"u","width":1,"height":1} },
+                          "xs": { "gif": {"url":"u","width":1,"height":1} }
+                      }
+                    },
+                    { "type": "clip", "id": 3, "slug": "nope-c1", "title": "Nope",
+                      "duration": 2.5,
+                      "file": {
+                          "mp4": {"url":"c.mp4","width":640,"height":360},
+                          "gif": {"url":"c.gif","width":320,"height":180}
+                      }
+                    },
+                    { "type": "meme", "id": 4, "slug": "drake-m1", "title": "Drake",
+                      "tags": ["drake"],
+                      "file": {
+                          "hd": { "jpg": {"url":"m.jpg","width":800,"height":800} },
+                          "md": { "png": {"url":"m.png","width":400,"height":400},
+                                  "jpg": {"url":"m_md.jpg","width":400,"height":400} },
+                          "sm": {},
+                          "xs": {}
+                      }
+                    }
+                ]
+            }
+        }"#;
+
+        let response: KlipySearchResponse = serde_json::from_str(json).unwrap();
+        match &response.data.data[0] {
+            KlipyItem::Sticker(sticker) => {
+                assert_eq!(sticker.file.hd.png.as_ref().unwrap().url, "u.png");
+            }
+            other => panic!("expected Sticker, got {:?}", other),
+        }
+        match &response.data.data[1] {
+            KlipyItem::Clip(clip) => {
+                assert_eq!(clip.file.mp4.url, "c.mp4");
+                assert_eq!(clip.duration, Some(2.5));
+            }
+            other => panic!("expected Clip, got {:?}", other),
+        }
+        match &response.data.data[2] {
+            KlipyItem::Meme(meme) => {
+                assert_eq!(meme.file.md.best().unwrap().url, "m.png");
+                assert!(meme.file.sm.best().is_none());
+            }
+            other => panic!("expected Meme, got {:?}", other),
+        }
+        assert_eq!(
+            response.data.data[2]
+                .labels()
+                .map(|(_, _, tags)| tags.len()),
+            Some(1)
+        );
+    }
 }
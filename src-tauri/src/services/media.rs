@@ -0,0 +1,826 @@
+use anyhow::{Context, Result};
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, ImageDecoder, ImageFormat, ImageReader, RgbaImage};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::models::MediaInfo;
+
+/// Decode every frame of a GIF or still image, handing each to `f` in order.
+/// A still image is a single frame. Videos aren't decodable here; Klipy
+/// favorites are analysed through their GIF.
+pub fn for_each_frame(path: &Path, mut f: impl FnMut(&RgbaImage)) -> Result<()> {
+    let reader = ImageReader::open(path)
+        .context("Failed to open media file")?
+        .with_guessed_format()
+        .context("Failed to read media file")?;
+
+    if reader.format() == Some(ImageFormat::Gif) {
+        let file = BufReader::new(File::open(path).context("Failed to open media file")?);
+        let decoder = GifDecoder::new(file).context("Failed to decode GIF")?;
+        let mut frames = 0;
+        for frame in decoder.into_frames() {
+            f(frame.context("Failed to decode GIF frame")?.buffer());
+            frames += 1;
+        }
+        anyhow::ensure!(frames > 0, "GIF has no frames");
+        return Ok(());
+    }
+
+    let image = reader.decode().context("Failed to decode image")?;
+    f(&image.to_rgba8());
+    Ok(())
+}
+
+/// Read size, timing and looping from a file's container without decoding
+/// any pixels. GIF, PNG/APNG, WebP, MP4/MOV and WebM/MKV are parsed
+/// directly; other still formats go through `image` for their dimensions.
+pub fn probe(path: &Path) -> Result<MediaInfo> {
+    let data = std::fs::read(path).context("Failed to read media file")?;
+
+    let info = if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        probe_gif(&data)
+    } else if data.starts_with(PNG_SIGNATURE) {
+        probe_png(&data)
+    } else if data.get(0..4) == Some(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        probe_webp(&data)
+    } else if data.get(4..8) == Some(b"ftyp") {
+        probe_mp4(&data)
+    } else if data.starts_with(&EBML_MAGIC) {
+        probe_webm(&data)
+    } else {
+        return probe_still(path);
+    };
+
+    info.context("Malformed or truncated media file")
+}
+
+/// `probe` off the async runtime. Failures are logged and give `None`; the
+/// favorite is saved without the extra metadata.
+pub async fn media_info(path: PathBuf) -> Option<MediaInfo> {
+    let result = tokio::task::spawn_blocking(move || {
+        probe(&path).with_context(|| format!("Failed to probe {}", path.display()))
+    })
+    .await;
+    match result {
+        Ok(Ok(info)) => Some(info),
+        Ok(Err(e)) => {
+            eprintln!("Warning: {:#}", e);
+            None
+        }
+        Err(e) => {
+            eprintln!("Warning: media probe task failed: {}", e);
+            None
+        }
+    }
+}
+
+fn probe_still(path: &Path) -> Result<MediaInfo> {
+    let decoder = ImageReader::open(path)
+        .context("Failed to open media file")?
+        .with_guessed_format()
+        .context("Failed to read media file")?
+        .into_decoder()
+        .context("Unsupported media format")?;
+    let (width, height) = decoder.dimensions();
+    Ok(MediaInfo {
+        width: Some(width),
+        height: Some(height),
+        frame_count: Some(1),
+        has_alpha: Some(decoder.color_type().has_alpha()),
+        ..Default::default()
+    })
+}
+
+/// Turn what a container counted into `MediaInfo`, treating one frame as a
+/// still image whatever the format claimed about timing.
+fn animation(
+    width: Option<u32>,
+    height: Option<u32>,
+    frames: u32,
+    duration_ms: u64,
+    loop_count: Option<u32>,
+    has_alpha: Option<bool>,
+) -> MediaInfo {
+    let animated = frames > 1;
+    MediaInfo {
+        width,
+        height,
+        frame_count: Some(frames.max(1)),
+        duration_ms: animated.then_some(duration_ms),
+        loop_count: loop_count.filter(|_| animated),
+        has_alpha,
+    }
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn le_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le_u24(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 3)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]))
+}
+
+fn le_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+// --- GIF ---
+
+/// Browsers play GIF frames with a delay under 20ms at 100ms, and so do we
+/// when adding up the duration.
+const MIN_GIF_DELAY_CS: u16 = 2;
+const DEFAULT_GIF_DELAY_CS: u16 = 10;
+
+fn probe_gif(data: &[u8]) -> Option<MediaInfo> {
+    let width = le_u16(data, 6)?;
+    let height = le_u16(data, 8)?;
+    let mut pos = 13 + gif_color_table_len(*data.get(10)?);
+
+    let mut frames = 0;
+    let mut duration_cs = 0u64;
+    let mut pending_delay = None;
+    let mut repeats = None;
+    let mut has_alpha = false;
+
+    loop {
+        match *data.get(pos)? {
+            0x21 => {
+                let label = *data.get(pos + 1)?;
+                let body = pos + 2;
+                match label {
+                    // Graphic control: transparency flag and the next frame's delay.
+                    0xF9 => {
+                        has_alpha |= data.get(body + 1)? & 1 != 0;
+                        pending_delay = Some(le_u16(data, body + 2)?);
+                    }
+                    0xFF if data.get(body + 1..body + 12) == Some(b"NETSCAPE2.0")
+                        && data.get(body + 13) == Some(&1) =>
+                    {
+                        repeats = Some(le_u16(data, body + 14)?);
+                    }
+                    _ => {}
+                }
+                pos = skip_gif_sub_blocks(data, body)?;
+            }
+            0x2C => {
+                frames += 1;
+                let delay = pending_delay.take().unwrap_or(0);
+                duration_cs += u64::from(if delay < MIN_GIF_DELAY_CS {
+                    DEFAULT_GIF_DELAY_CS
+                } else {
+                    delay
+                });
+                let packed = *data.get(pos + 9)?;
+                // Descriptor, local color table, LZW code size, then the data.
+                pos = skip_gif_sub_blocks(data, pos + 10 + gif_color_table_len(packed) + 1)?;
+            }
+            0x3B => break,
+            _ => return None,
+        }
+    }
+
+    // The NETSCAPE extension counts repeats after the first play; without
+    // it a GIF plays once.
+    let loop_count = match repeats {
+        Some(0) => 0,
+        Some(n) => u32::from(n) + 1,
+        None => 1,
+    };
+    Some(animation(
+        Some(width.into()),
+        Some(height.into()),
+        frames,
+        duration_cs * 10,
+        Some(loop_count),
+        Some(has_alpha),
+    ))
+}
+
+fn gif_color_table_len(packed: u8) -> usize {
+    if packed & 0x80 != 0 {
+        3 << ((packed & 0x07) + 1)
+    } else {
+        0
+    }
+}
+
+/// Skip a chain of length-prefixed sub-blocks, returning the position after
+/// its terminator.
+fn skip_gif_sub_blocks(data: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *data.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            return Some(pos);
+        }
+        pos += len;
+    }
+}
+
+// --- PNG / APNG ---
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn probe_png(data: &[u8]) -> Option<MediaInfo> {
+    let mut pos = PNG_SIGNATURE.len();
+    let (mut width, mut height) = (None, None);
+    let mut has_alpha = false;
+    let mut frames = 1;
+    let mut plays = None;
+    let mut duration_ms = 0.0;
+
+    while let (Some(len), Some(kind)) = (be_u32(data, pos), data.get(pos + 4..pos + 8)) {
+        let body = pos + 8;
+        match kind {
+            b"IHDR" => {
+                width = Some(be_u32(data, body)?);
+                height = Some(be_u32(data, body + 4)?);
+                // Grayscale + alpha, RGBA.
+                has_alpha |= matches!(data.get(body + 9)?, 4 | 6);
+            }
+            b"tRNS" => has_alpha = true,
+            b"acTL" => {
+                frames = be_u32(data, body)?;
+                plays = Some(be_u32(data, body + 4)?);
+            }
+            b"fcTL" => {
+                let numerator = be_u16(data, body + 20)?;
+                let denominator = match be_u16(data, body + 22)? {
+                    0 => 100,
+                    d => d,
+                };
+                duration_ms += f64::from(numerator) * 1000.0 / f64::from(denominator);
+            }
+            b"IEND" => break,
+            _ => {}
+        }
+        // Length, type, data, CRC.
+        pos = body + len as usize + 4;
+    }
+
+    Some(animation(
+        width,
+        height,
+        frames,
+        duration_ms.round() as u64,
+        plays,
+        Some(has_alpha),
+    ))
+}
+
+// --- WebP ---
+
+fn probe_webp(data: &[u8]) -> Option<MediaInfo> {
+    let mut pos = 12;
+    let (mut width, mut height) = (None, None);
+    let mut has_alpha = false;
+    let mut frames = 0;
+    let mut loops = None;
+    let mut duration_ms = 0u64;
+
+    while let (Some(kind), Some(len)) = (data.get(pos..pos + 4), le_u32(data, pos + 4)) {
+        let body = pos + 8;
+        match kind {
+            b"VP8X" => {
+                has_alpha |= data.get(body)? & 0x10 != 0;
+                width = Some(le_u24(data, body + 4)? + 1);
+                height = Some(le_u24(data, body + 7)? + 1);
+            }
+            b"ANIM" => loops = Some(u32::from(le_u16(data, body + 4)?)),
+            b"ANMF" => {
+                frames += 1;
+                duration_ms += u64::from(le_u24(data, body + 12)?);
+            }
+            b"ALPH" => has_alpha = true,
+            b"VP8 " if width.is_none() => {
+                width = Some(u32::from(le_u16(data, body + 6)? & 0x3FFF));
+                height = Some(u32::from(le_u16(data, body + 8)? & 0x3FFF));
+            }
+            b"VP8L" if width.is_none() => {
+                let bits = le_u32(data, body + 1)?;
+                width = Some((bits & 0x3FFF) + 1);
+                height = Some(((bits >> 14) & 0x3FFF) + 1);
+                has_alpha |= (bits >> 28) & 1 != 0;
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even length.
+        let len = len as usize;
+        pos = body + len + (len & 1);
+    }
+
+    Some(animation(
+        width,
+        height,
+        frames,
+        duration_ms,
+        loops,
+        Some(has_alpha),
+    ))
+}
+
+// --- MP4 / MOV ---
+
+/// Boxes on the path from the file down to a track's sample table.
+const MP4_CONTAINERS: [&[u8; 4]; 5] = [b"moov", b"trak", b"mdia", b"minf", b"stbl"];
+
+#[derive(Default)]
+struct Mp4Track {
+    handler: Option<[u8; 4]>,
+    width: u32,
+    height: u32,
+    timescale: u32,
+    duration: u64,
+    samples: u64,
+}
+
+fn probe_mp4(data: &[u8]) -> Option<MediaInfo> {
+    let mut tracks: Vec<Mp4Track> = Vec::new();
+    let mut pos = 0;
+
+    while let Some(size) = be_u32(data, pos) {
+        let kind: &[u8; 4] = data.get(pos + 4..pos + 8)?.try_into().ok()?;
+        let (header, size) = match size {
+            1 => (16, usize::try_from(be_u64(data, pos + 8)?).ok()?),
+            0 => (8, data.len() - pos),
+            n => (8, n as usize),
+        };
+        if size < header {
+            return None;
+        }
+        let body = pos + header;
+
+        // Walk into containers instead of over them; every other box is a
+        // leaf as far as we care.
+        if MP4_CONTAINERS.contains(&kind) {
+            if kind == b"trak" {
+                tracks.push(Mp4Track::default());
+            }
+            pos = body;
+            continue;
+        }
+
+        if let Some(track) = tracks.last_mut() {
+            let version = data.get(body).copied();
+            match kind {
+                b"tkhd" => {
+                    // 16.16 fixed-point display size at the end of the box.
+                    let at = if version == Some(1) {
+                        body + 88
+                    } else {
+                        body + 76
+                    };
+                    track.width = be_u32(data, at)? >> 16;
+                    track.height = be_u32(data, at + 4)? >> 16;
+                }
+                b"mdhd" => {
+                    if version == Some(1) {
+                        track.timescale = be_u32(data, body + 20)?;
+                        track.duration = be_u64(data, body + 24)?;
+                    } else {
+                        track.timescale = be_u32(data, body + 12)?;
+                        track.duration = u64::from(be_u32(data, body + 16)?);
+                    }
+                }
+                b"hdlr" => track.handler = data.get(body + 8..body + 12)?.try_into().ok(),
+                b"stts" => {
+                    let entries = be_u32(data, body + 4)? as usize;
+                    track.samples = (0..entries)
+                        .map(|i| be_u32(data, body + 8 + i * 8).map(u64::from))
+                        .sum::<Option<u64>>()?;
+                }
+                _ => {}
+            }
+        }
+
+        pos = pos.checked_add(size)?;
+    }
+
+    let video = tracks.iter().find(|t| t.handler == Some(*b"vide"))?;
+    // A hostile `mdhd` can claim any duration; leave it unknown rather than overflow.
+    let duration_ms = match video.timescale {
+        0 => Some(0),
+        scale => video
+            .duration
+            .checked_mul(1000)
+            .map(|ms| ms / u64::from(scale)),
+    };
+    Some(MediaInfo {
+        width: (video.width > 0).then_some(video.width),
+        height: (video.height > 0).then_some(video.height),
+        frame_count: Some(video.samples.try_into().unwrap_or(u32::MAX)),
+        duration_ms,
+        loop_count: None,
+        // H.264, what MP4 reactions are encoded with, has no alpha.
+        has_alpha: Some(false),
+    })
+}
+
+// --- WebM / Matroska ---
+
+const EBML_MAGIC: [u8; 4] = [0x1A, 0x45, 0xDF, 0xA3];
+
+/// Master elements holding what we read; their children are walked in place.
+const EBML_CONTAINERS: [u32; 7] = [
+    0x1853_8067, // Segment
+    0x1549_A966, // Info
+    0x1654_AE6B, // Tracks
+    0xAE,        // TrackEntry
+    0xE0,        // Video
+    0x1F43_B675, // Cluster
+    0xA0,        // BlockGroup
+];
+
+#[derive(Default)]
+struct WebmTrack {
+    number: u64,
+    kind: u64,
+    width: Option<u32>,
+    height: Option<u32>,
+    alpha: bool,
+}
+
+/// An EBML variable-length integer at `pos`: its value with the length
+/// marker stripped, and its length in bytes.
+fn ebml_vint(data: &[u8], pos: usize) -> Option<(u64, usize)> {
+    let first = *data.get(pos)?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return None;
+    }
+    let mut value = u64::from(first) & (0xFF >> len);
+    for i in 1..len {
+        value = (value << 8) | u64::from(*data.get(pos + i)?);
+    }
+    Some((value, len))
+}
+
+fn ebml_uint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |n, &b| (n << 8) | u64::from(b))
+}
+
+fn probe_webm(data: &[u8]) -> Option<MediaInfo> {
+    let mut timecode_scale = 1_000_000u64;
+    let mut duration = None;
+    let mut tracks: Vec<WebmTrack> = Vec::new();
+    let mut cluster_time = 0u64;
+    // Per track: blocks seen and the latest timestamp, in timecode units.
+    let mut blocks: HashMap<u64, (u32, u64)> = HashMap::new();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        // IDs keep their length marker; sizes don't.
+        let (_, id_len) = ebml_vint(data, pos)?;
+        let id = ebml_uint(data.get(pos..pos + id_len)?) as u32;
+        let (size, size_len) = ebml_vint(data, pos + id_len)?;
+        let unknown_size = size == (1 << (7 * size_len)) - 1;
+        let body = pos + id_len + size_len;
+
+        if EBML_CONTAINERS.contains(&id) {
+            if id == 0xAE {
+                tracks.push(WebmTrack::default());
+            }
+            pos = body;
+            continue;
+        }
+        if unknown_size {
+            return None;
+        }
+        let end = body.checked_add(usize::try_from(size).ok()?)?;
+        let Some(value) = data.get(body..end) else {
+            // Truncated inside an element — keep what was read so far.
+            break;
+        };
+
+        match id {
+            0x2A_D7B1 => timecode_scale = ebml_uint(value),
+            0x4489 => {
+                duration = match value.len() {
+                    4 => Some(f64::from(f32::from_be_bytes(value.try_into().ok()?))),
+                    8 => Some(f64::from_be_bytes(value.try_into().ok()?)),
+                    _ => None,
+                }
+            }
+            0xE7 => cluster_time = ebml_uint(value),
+            // SimpleBlock, Block: track number, then a signed 16-bit offset
+            // from the cluster's timecode.
+            0xA3 | 0xA1 => {
+                let (track, len) = ebml_vint(value, 0)?;
+                let offset = i16::from_be_bytes(value.get(len..len + 2)?.try_into().ok()?);
+                let time = cluster_time.saturating_add_signed(i64::from(offset));
+                let entry = blocks.entry(track).or_default();
+                entry.0 += 1;
+                entry.1 = entry.1.max(time);
+            }
+            _ => {
+                if let Some(track) = tracks.last_mut() {
+                    match id {
+                        0xD7 => track.number = ebml_uint(value),
+                        0x83 => track.kind = ebml_uint(value),
+                        0xB0 => track.width = u32::try_from(ebml_uint(value)).ok(),
+                        0xBA => track.height = u32::try_from(ebml_uint(value)).ok(),
+                        0x53C0 => track.alpha = ebml_uint(value) != 0,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        pos = end;
+    }
+
+    // Track type 1 is video.
+    let video = tracks.iter().find(|t| t.kind == 1)?;
+    let (frames, last_time) = blocks.get(&video.number).copied().unwrap_or_default();
+    // Recordings often leave Duration out; the last frame's time is close.
+    let duration = duration.unwrap_or(last_time as f64);
+    let duration_ms = duration * timecode_scale as f64 / 1_000_000.0;
+
+    Some(MediaInfo {
+        width: video.width,
+        height: video.height,
+        frame_count: Some(frames),
+        duration_ms: Some(duration_ms.round() as u64),
+        loop_count: None,
+        has_alpha: Some(video.alpha),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::{GifEncoder, Repeat};
+    use image::{Delay, Frame, Rgba};
+    use tempfile::TempDir;
+
+    fn frame(color: [u8; 4], delay_ms: u32) -> Frame {
+        Frame::from_parts(
+            RgbaImage::from_pixel(8, 6, Rgba(color)),
+            0,
+            0,
+            Delay::from_numer_denom_ms(delay_ms, 1),
+        )
+    }
+
+    fn write_gif(dir: &TempDir, frames: Vec<Frame>, repeat: Repeat) -> PathBuf {
+        let path = dir.path().join("test.gif");
+        let mut encoder = GifEncoder::new(File::create(&path).unwrap());
+        encoder.set_repeat(repeat).unwrap();
+        encoder.encode_frames(frames).unwrap();
+        drop(encoder);
+        path
+    }
+
+    #[test]
+    fn test_probes_gif_timing_and_loops() {
+        let dir = TempDir::new().unwrap();
+        let path = write_gif(
+            &dir,
+            vec![
+                frame([255, 0, 0, 255], 40),
+                frame([0, 255, 0, 255], 60),
+                // Too fast: played at 100ms.
+                frame([0, 0, 255, 255], 0),
+            ],
+            Repeat::Infinite,
+        );
+
+        let info = probe(&path).unwrap();
+        assert_eq!(info.width, Some(8));
+        assert_eq!(info.height, Some(6));
+        assert_eq!(info.frame_count, Some(3));
+        assert_eq!(info.duration_ms, Some(200));
+        assert_eq!(info.loop_count, Some(0));
+        assert_eq!(info.has_alpha, Some(false));
+
+        let path = write_gif(
+            &dir,
+            vec![frame([0, 0, 0, 0], 100), frame([0, 0, 0, 255], 100)],
+            Repeat::Finite(2),
+        );
+        let info = probe(&path).unwrap();
+        assert_eq!(info.loop_count, Some(3));
+        assert_eq!(info.has_alpha, Some(true));
+    }
+
+    #[test]
+    fn test_probes_still_images() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("still.png");
+        RgbaImage::from_pixel(5, 4, Rgba([1, 2, 3, 128]))
+            .save(&path)
+            .unwrap();
+
+        let info = probe(&path).unwrap();
+        assert_eq!((info.width, info.height), (Some(5), Some(4)));
+        assert_eq!(info.frame_count, Some(1));
+        assert_eq!(info.duration_ms, None);
+        assert_eq!(info.loop_count, None);
+        assert_eq!(info.has_alpha, Some(true));
+    }
+
+    fn png_chunk(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut chunk = (body.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(body);
+        chunk.extend_from_slice(&[0; 4]); // CRC, not checked
+        chunk
+    }
+
+    fn fctl(delay_num: u16, delay_den: u16) -> Vec<u8> {
+        let mut body = vec![0; 20];
+        body.extend_from_slice(&delay_num.to_be_bytes());
+        body.extend_from_slice(&delay_den.to_be_bytes());
+        body.extend_from_slice(&[0, 0]);
+        body
+    }
+
+    #[test]
+    fn test_probes_apng() {
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&16u32.to_be_bytes());
+        ihdr.extend_from_slice(&9u32.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+        let mut actl = 2u32.to_be_bytes().to_vec();
+        actl.extend_from_slice(&0u32.to_be_bytes());
+
+        let mut data = PNG_SIGNATURE.to_vec();
+        data.extend(png_chunk(b"IHDR", &ihdr));
+        data.extend(png_chunk(b"acTL", &actl));
+        data.extend(png_chunk(b"fcTL", &fctl(1, 4)));
+        data.extend(png_chunk(b"IDAT", &[]));
+        data.extend(png_chunk(b"fcTL", &fctl(50, 0)));
+        data.extend(png_chunk(b"IEND", &[]));
+
+        let info = probe_png(&data).unwrap();
+        assert_eq!((info.width, info.height), (Some(16), Some(9)));
+        assert_eq!(info.frame_count, Some(2));
+        assert_eq!(info.duration_ms, Some(750));
+        assert_eq!(info.loop_count, Some(0));
+        assert_eq!(info.has_alpha, Some(false));
+    }
+
+    fn riff_chunk(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut chunk = kind.to_vec();
+        chunk.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    #[test]
+    fn test_probes_animated_webp() {
+        // Alpha + animation flags, 100×50 canvas.
+        let mut vp8x = vec![0x12, 0, 0, 0];
+        vp8x.extend_from_slice(&99u32.to_le_bytes()[..3]);
+        vp8x.extend_from_slice(&49u32.to_le_bytes()[..3]);
+        let anim = [0, 0, 0, 0, 3, 0];
+        let anmf = |duration: u32| {
+            let mut body = vec![0; 12];
+            body.extend_from_slice(&duration.to_le_bytes()[..3]);
+            body.push(0);
+            body
+        };
+
+        let mut chunks = riff_chunk(b"VP8X", &vp8x);
+        chunks.extend(riff_chunk(b"ANIM", &anim));
+        chunks.extend(riff_chunk(b"ANMF", &anmf(80)));
+        chunks.extend(riff_chunk(b"ANMF", &anmf(120)));
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+        data.extend_from_slice(b"WEBP");
+        data.extend(chunks);
+
+        let info = probe_webp(&data).unwrap();
+        assert_eq!((info.width, info.height), (Some(100), Some(50)));
+        assert_eq!(info.frame_count, Some(2));
+        assert_eq!(info.duration_ms, Some(200));
+        assert_eq!(info.loop_count, Some(3));
+        assert_eq!(info.has_alpha, Some(true));
+    }
+
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut b = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        b.extend_from_slice(kind);
+        b.extend_from_slice(body);
+        b
+    }
+
+    #[test]
+    fn test_probes_mp4_video_track() {
+        let mut tkhd = vec![0; 76];
+        tkhd.extend_from_slice(&(320u32 << 16).to_be_bytes());
+        tkhd.extend_from_slice(&(240u32 << 16).to_be_bytes());
+        let mut mdhd = vec![0; 12];
+        mdhd.extend_from_slice(&600u32.to_be_bytes());
+        mdhd.extend_from_slice(&1500u32.to_be_bytes());
+        let mut hdlr = vec![0; 8];
+        hdlr.extend_from_slice(b"vide");
+        let mut stts = vec![0, 0, 0, 0];
+        stts.extend_from_slice(&2u32.to_be_bytes());
+        for (count, delta) in [(40u32, 20u32), (10, 30)] {
+            stts.extend_from_slice(&count.to_be_bytes());
+            stts.extend_from_slice(&delta.to_be_bytes());
+        }
+
+        let stbl = mp4_box(b"stbl", &mp4_box(b"stts", &stts));
+        let minf = mp4_box(b"minf", &stbl);
+        let mut mdia = mp4_box(b"mdhd", &mdhd);
+        mdia.extend(mp4_box(b"hdlr", &hdlr));
+        mdia.extend(minf);
+        let mut trak = mp4_box(b"tkhd", &tkhd);
+        trak.extend(mp4_box(b"mdia", &mdia));
+
+        let mut data = mp4_box(b"ftyp", b"isom\0\0\0\0");
+        data.extend(mp4_box(b"moov", &mp4_box(b"trak", &trak)));
+        data.extend(mp4_box(b"mdat", &[0; 16]));
+
+        let info = probe_mp4(&data).unwrap();
+        assert_eq!((info.width, info.height), (Some(320), Some(240)));
+        assert_eq!(info.frame_count, Some(50));
+        assert_eq!(info.duration_ms, Some(2500));
+        assert_eq!(info.fps(), Some(20.0));
+        assert_eq!(info.loop_count, None);
+    }
+
+    #[test]
+    fn test_mp4_with_oversized_duration_does_not_panic() {
+        let mut mdhd = vec![1, 0, 0, 0];
+        mdhd.extend_from_slice(&[0; 16]);
+        mdhd.extend_from_slice(&1u32.to_be_bytes());
+        mdhd.extend_from_slice(&u64::MAX.to_be_bytes());
+        let mut hdlr = vec![0; 8];
+        hdlr.extend_from_slice(b"vide");
+        let mut mdia = mp4_box(b"mdhd", &mdhd);
+        mdia.extend(mp4_box(b"hdlr", &hdlr));
+        let trak = mp4_box(b"mdia", &mdia);
+
+        let mut data = mp4_box(b"ftyp", b"isom\0\0\0\0");
+        data.extend(mp4_box(b"moov", &mp4_box(b"trak", &trak)));
+
+        let info = probe_mp4(&data).unwrap();
+        assert_eq!(info.duration_ms, None);
+    }
+
+    fn ebml(id: u32, body: &[u8]) -> Vec<u8> {
+        let id_bytes = id.to_be_bytes();
+        let skip = id_bytes.iter().position(|&b| b != 0).unwrap();
+        let mut element = id_bytes[skip..].to_vec();
+        // Eight-byte size for simplicity.
+        element.push(0x01);
+        element.extend_from_slice(&(body.len() as u64).to_be_bytes()[1..]);
+        element.extend_from_slice(body);
+        element
+    }
+
+    #[test]
+    fn test_probes_webm_without_duration() {
+        let video = [ebml(0xB0, &[200]), ebml(0xBA, &[100]), ebml(0x53C0, &[1])].concat();
+        let track = [ebml(0xD7, &[1]), ebml(0x83, &[1]), ebml(0xE0, &video)].concat();
+        let block = |offset: i16| {
+            let mut body = vec![0x81];
+            body.extend_from_slice(&offset.to_be_bytes());
+            body.push(0);
+            ebml(0xA3, &body)
+        };
+        let cluster = |time: u8, offsets: &[i16]| {
+            let mut body = ebml(0xE7, &[time]);
+            for &offset in offsets {
+                body.extend(block(offset));
+            }
+            ebml(0x1F43_B675, &body)
+        };
+        let segment = [
+            ebml(0x1549_A966, &ebml(0x2A_D7B1, &[0x0F, 0x42, 0x40])),
+            ebml(0x1654_AE6B, &ebml(0xAE, &track)),
+            cluster(0, &[0, 40, 80]),
+            cluster(120, &[0, 40]),
+        ]
+        .concat();
+        let mut data = ebml(0x1A45_DFA3, &[]);
+        data.extend(ebml(0x1853_8067, &segment));
+
+        let info = probe_webm(&data).unwrap();
+        assert_eq!((info.width, info.height), (Some(200), Some(100)));
+        assert_eq!(info.frame_count, Some(5));
+        assert_eq!(info.duration_ms, Some(160));
+        assert_eq!(info.has_alpha, Some(true));
+    }
+}
@@ -1,7 +1,26 @@
+pub mod autopaste;
+pub mod autotag;
+pub mod backup;
 pub mod clipboard;
+pub mod config_file;
+pub mod content_filter;
 pub mod downloader;
+pub mod duplicates;
 pub mod klipy;
+pub mod media;
+pub mod palette;
+pub mod similar;
+pub mod sync;
 
+pub use autopaste::*;
+pub use autotag::*;
+pub use backup::*;
 pub use clipboard::*;
+pub use config_file::*;
+pub use content_filter::*;
 pub use downloader::*;
+pub use duplicates::*;
 pub use klipy::*;
+pub use media::*;
+pub use palette::*;
+pub use sync::*;
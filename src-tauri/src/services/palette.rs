@@ -0,0 +1,212 @@
+use anyhow::{Context, Result};
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+
+use crate::models::PaletteColor;
+use crate::services::for_each_frame;
+
+/// Colors kept per favorite. A reaction GIF is rarely remembered by more.
+pub const MAX_COLORS: usize = 5;
+
+/// Colors covering less of the image than this are noise, not "the color of
+/// the GIF".
+const MIN_WEIGHT: f32 = 0.05;
+
+/// Buckets whose mean colors are closer than this (RGB distance) count as
+/// one color, so a gradient doesn't fill the whole palette.
+const MERGE_DISTANCE: f32 = 48.0;
+
+/// Frames are shrunk to this before counting; the palette doesn't need more.
+const THUMBNAIL_SIZE: u32 = 32;
+
+/// Frames sampled from an animation, spread evenly across it.
+const MAX_FRAMES: usize = 8;
+
+/// Mostly transparent pixels don't contribute a color.
+const MIN_ALPHA: u8 = 128;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    r: u64,
+    g: u64,
+    b: u64,
+    count: u64,
+}
+
+impl Bucket {
+    fn mean(&self) -> [f32; 3] {
+        let n = self.count as f32;
+        [self.r as f32 / n, self.g as f32 / n, self.b as f32 / n]
+    }
+
+    fn absorb(&mut self, other: &Bucket) {
+        self.r += other.r;
+        self.g += other.g;
+        self.b += other.b;
+        self.count += other.count;
+    }
+}
+
+/// The dominant colors of a set of frames, most common first. Pixels are
+/// counted in coarse RGB buckets (3 bits per channel) and neighbouring
+/// buckets merged.
+pub fn palette_of(frames: &[RgbaImage]) -> Vec<PaletteColor> {
+    let mut buckets = vec![Bucket::default(); 512];
+    for frame in frames {
+        for pixel in frame.pixels() {
+            let [r, g, b, a] = pixel.0;
+            if a < MIN_ALPHA {
+                continue;
+            }
+            let index = ((r >> 5) as usize) << 6 | ((g >> 5) as usize) << 3 | (b >> 5) as usize;
+            let bucket = &mut buckets[index];
+            bucket.r += r as u64;
+            bucket.g += g as u64;
+            bucket.b += b as u64;
+            bucket.count += 1;
+        }
+    }
+
+    let total: u64 = buckets.iter().map(|b| b.count).sum();
+    if total == 0 {
+        return Vec::new();
+    }
+
+    buckets.retain(|b| b.count > 0);
+    buckets.sort_by_key(|b| std::cmp::Reverse(b.count));
+
+    let mut merged: Vec<Bucket> = Vec::new();
+    for bucket in &buckets {
+        let mean = bucket.mean();
+        let near = merged
+            .iter_mut()
+            .find(|m| distance(m.mean(), mean) < MERGE_DISTANCE);
+        match near {
+            Some(m) => m.absorb(bucket),
+            None => merged.push(*bucket),
+        }
+    }
+    merged.sort_by_key(|b| std::cmp::Reverse(b.count));
+
+    merged
+        .iter()
+        .map(|bucket| {
+            let [r, g, b] = bucket.mean();
+            PaletteColor {
+                r: r.round() as u8,
+                g: g.round() as u8,
+                b: b.round() as u8,
+                weight: bucket.count as f32 / total as f32,
+            }
+        })
+        .filter(|color| color.weight >= MIN_WEIGHT)
+        .take(MAX_COLORS)
+        .collect()
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
+
+/// Palette of an image or GIF file.
+pub fn extract_palette(path: &Path) -> Result<Vec<PaletteColor>> {
+    let mut thumbnails = Vec::new();
+    for_each_frame(path, |frame| {
+        thumbnails.push(imageops::resize(
+            frame,
+            THUMBNAIL_SIZE,
+            THUMBNAIL_SIZE,
+            FilterType::Triangle,
+        ))
+    })?;
+
+    let step = thumbnails.len().div_ceil(MAX_FRAMES).max(1);
+    let sampled: Vec<RgbaImage> = thumbnails.into_iter().step_by(step).collect();
+    Ok(palette_of(&sampled))
+}
+
+/// `extract_palette` off the async runtime. Failures are logged and give an
+/// empty palette; a favorite without colors is still a favorite.
+pub async fn dominant_colors(path: PathBuf) -> Vec<PaletteColor> {
+    let result = tokio::task::spawn_blocking(move || {
+        extract_palette(&path)
+            .with_context(|| format!("Failed to read colors of {}", path.display()))
+    })
+    .await;
+    match result {
+        Ok(Ok(palette)) => palette,
+        Ok(Err(e)) => {
+            eprintln!("Warning: {:#}", e);
+            Vec::new()
+        }
+        Err(e) => {
+            eprintln!("Warning: palette task failed: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn split_image(left: [u8; 4], right: [u8; 4], left_width: u32) -> RgbaImage {
+        RgbaImage::from_fn(10, 10, |x, _| {
+            Rgba(if x < left_width { left } else { right })
+        })
+    }
+
+    #[test]
+    fn test_palette_orders_by_coverage() {
+        let image = split_image([250, 10, 10, 255], [10, 10, 240, 255], 7);
+        let palette = palette_of(&[image]);
+
+        assert_eq!(palette.len(), 2);
+        assert_eq!(palette[0].hex(), "#fa0a0a");
+        assert!((palette[0].weight - 0.7).abs() < 1e-6);
+        assert_eq!(palette[1].hex(), "#0a0af0");
+    }
+
+    #[test]
+    fn test_palette_merges_shades_and_skips_transparency() {
+        let shades = split_image([200, 0, 0, 255], [225, 10, 10, 255], 5);
+        let transparent = split_image([0, 255, 0, 0], [0, 255, 0, 0], 5);
+        let palette = palette_of(&[shades, transparent]);
+
+        assert_eq!(palette.len(), 1);
+        assert!((palette[0].weight - 1.0).abs() < 1e-6);
+        assert!(palette[0].r > 200 && palette[0].g < 10);
+    }
+
+    #[test]
+    fn test_palette_drops_specks() {
+        let image = RgbaImage::from_fn(10, 10, |x, y| {
+            Rgba(if (x, y) == (0, 0) {
+                [255, 255, 0, 255]
+            } else {
+                [0, 0, 0, 255]
+            })
+        });
+        let palette = palette_of(&[image]);
+        assert_eq!(palette.len(), 1);
+        assert_eq!(palette[0].hex(), "#000000");
+    }
+
+    #[test]
+    fn test_extract_palette_reads_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("red.png");
+        split_image([255, 0, 0, 255], [255, 0, 0, 255], 10)
+            .save(&path)
+            .unwrap();
+
+        let palette = extract_palette(&path).unwrap();
+        assert_eq!(palette[0].hex(), "#ff0000");
+    }
+}
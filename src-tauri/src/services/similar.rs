@@ -0,0 +1,141 @@
+use std::cmp::Ordering;
+
+use crate::models::{normalize_tag, Favorite};
+use crate::services::PerceptualHash;
+
+/// How much looks count against shared tags when both are known.
+const VISUAL_WEIGHT: f64 = 0.6;
+
+/// Below this a favorite isn't "like" the other in any useful sense.
+const MIN_SCORE: f64 = 0.25;
+
+/// Words in a provider query seeded from a favorite — enough to carry the
+/// gist, few enough that the search still returns something.
+const SEED_WORDS: usize = 6;
+
+/// How alike two favorites are, 0–1, from their perceptual hashes and tag
+/// overlap. `None` when there's nothing to compare.
+pub fn similarity(a: &Favorite, b: &Favorite) -> Option<f64> {
+    let hash = |f: &Favorite| f.phash.as_deref()?.parse::<PerceptualHash>().ok();
+    // Unrelated images still agree on about half their bits, so only the
+    // part above chance counts.
+    let visual = match (hash(a), hash(b)) {
+        (Some(x), Some(y)) => Some(((x.similarity(&y) - 0.5) * 2.0).max(0.0)),
+        _ => None,
+    };
+
+    let tags_a = all_tags(a);
+    let tags_b = all_tags(b);
+    let tags = if tags_a.is_empty() && tags_b.is_empty() {
+        None
+    } else {
+        let shared = tags_a.iter().filter(|t| tags_b.contains(t)).count();
+        let union = tags_a.len() + tags_b.len() - shared;
+        Some(shared as f64 / union as f64)
+    };
+
+    match (visual, tags) {
+        (Some(v), Some(t)) => Some(VISUAL_WEIGHT * v + (1.0 - VISUAL_WEIGHT) * t),
+        (v, t) => v.or(t),
+    }
+}
+
+fn all_tags(favorite: &Favorite) -> Vec<&String> {
+    let mut tags: Vec<&String> = favorite.custom_tags.iter().collect();
+    for tag in &favorite.tags {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// The `limit` favorites most like `target`, best first, leaving out
+/// `target` itself and anything below the relevance floor.
+pub fn rank_similar(target: &Favorite, candidates: Vec<Favorite>, limit: usize) -> Vec<Favorite> {
+    let mut scored: Vec<(f64, Favorite)> = candidates
+        .into_iter()
+        .filter(|f| f.id != target.id)
+        .filter_map(|f| Some((similarity(target, &f)?, f)))
+        .filter(|(score, _)| *score >= MIN_SCORE)
+        .collect();
+    scored.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+    scored.truncate(limit);
+    scored.into_iter().map(|(_, f)| f).collect()
+}
+
+/// A provider search for more GIFs like `favorite`: the words of its title
+/// (or the search that found it), then its tags.
+pub fn seed_query(favorite: &Favorite) -> Option<String> {
+    let title = favorite
+        .description
+        .as_deref()
+        .or(favorite.source_query.as_deref());
+
+    let mut words: Vec<String> = Vec::new();
+    let title_words = title.into_iter().flat_map(str::split_whitespace);
+    let tag_words = all_tags(favorite)
+        .into_iter()
+        .flat_map(|t| t.split_whitespace());
+    for word in title_words.chain(tag_words) {
+        let Some(word) = normalize_tag(word.trim_matches(|c: char| !c.is_alphanumeric())) else {
+            continue;
+        };
+        if !words.contains(&word) {
+            words.push(word);
+        }
+        if words.len() == SEED_WORDS {
+            break;
+        }
+    }
+
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MediaType;
+
+    fn favorite(id: i64, tags: &[&str], phash: Option<&str>) -> Favorite {
+        let mut favorite = Favorite::new(format!("{}.gif", id), None, MediaType::Gif)
+            .with_tags(tags.iter().map(|t| t.to_string()).collect());
+        favorite.id = Some(id);
+        favorite.phash = phash.map(String::from);
+        favorite
+    }
+
+    const BLACK: &str = "000000000000000000000000000000000000000000000000";
+    const NEAR_BLACK: &str = "00000000000000ff000000000000000000000000000000ff";
+    const WHITE: &str = "ffffffffffffffffffffffffffffffffffffffffffffffff";
+
+    #[test]
+    fn test_rank_similar_blends_looks_and_tags() {
+        let target = favorite(1, &["cat", "happy"], Some(BLACK));
+        let lookalike = favorite(2, &["dog"], Some(NEAR_BLACK));
+        let same_tags = favorite(3, &["cat", "happy"], Some(WHITE));
+        let tag_only = favorite(4, &["cat", "grumpy"], None);
+        let unrelated = favorite(5, &["dog"], Some(WHITE));
+
+        let ranked = rank_similar(
+            &target,
+            vec![target.clone(), unrelated, tag_only, same_tags, lookalike],
+            10,
+        );
+        let ids: Vec<_> = ranked.iter().filter_map(|f| f.id).collect();
+        // Looks 0.5, tags 0.4, tags alone 1/3, nothing 0.
+        assert_eq!(ids, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_seed_query_uses_title_then_tags() {
+        let mut gif = favorite(1, &["cat", "dance party", "friday"], None);
+        gif.description = Some("Cat Dancing!".to_string());
+        assert_eq!(
+            seed_query(&gif).as_deref(),
+            Some("cat dancing dance party friday")
+        );
+
+        assert_eq!(seed_query(&favorite(2, &[], None)), None);
+    }
+}
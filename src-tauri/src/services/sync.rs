@@ -0,0 +1,861 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::db::{Database, SyncShadow};
+use crate::models::{
+    Change, ChangeRecord, Favorite, FieldState, MediaRole, SyncField, SyncReport, SyncedMedia,
+};
+use crate::services::Downloader;
+
+const DEVICES_DIR: &str = "devices";
+const MEDIA_DIR: &str = "media";
+const LOG_FILE: &str = "changes.jsonl";
+
+/// What happened to one record from another device's log.
+enum Applied {
+    Done,
+    /// Needs something that isn't here yet: media still being copied into
+    /// the folder, or a favorite whose create is in another device's log.
+    Waiting,
+}
+
+/// Sync through a shared folder, no server involved. The folder holds
+///
+/// - `devices/<device id>/changes.jsonl`: each device's append-only log of
+///   `ChangeRecord`s, written only by that device;
+/// - `media/<sha256>.<ext>`: every synced media file, once per content.
+///
+/// Local changes are found by comparing favorites to the `sync_shadow`
+/// table, and stamped with when the field was edited (see the `sync_edits`
+/// table) so a late sync doesn't make an old edit look new. Records from
+/// other devices are merged field by field, the newest write winning.
+pub struct FolderSync<'a> {
+    db: &'a Database,
+    downloader: &'a Downloader,
+    folder: &'a Path,
+    device_id: &'a str,
+}
+
+impl<'a> FolderSync<'a> {
+    pub fn new(
+        db: &'a Database,
+        downloader: &'a Downloader,
+        folder: &'a Path,
+        device_id: &'a str,
+    ) -> Self {
+        Self {
+            db,
+            downloader,
+            folder,
+            device_id,
+        }
+    }
+
+    /// Write local changes to this device's log, then apply everything new
+    /// in the other devices' logs.
+    pub async fn run(&self) -> Result<SyncReport> {
+        fs::create_dir_all(self.folder.join(DEVICES_DIR).join(self.device_id))
+            .await
+            .context("Failed to create sync folder")?;
+        fs::create_dir_all(self.folder.join(MEDIA_DIR))
+            .await
+            .context("Failed to create sync media folder")?;
+
+        let exported = self.export().await?;
+        let (imported, pending) = self.import().await?;
+        Ok(SyncReport {
+            exported,
+            imported,
+            pending,
+        })
+    }
+
+    async fn export(&self) -> Result<usize> {
+        let favorites_db = self.db.favorites();
+        let sync_db = self.db.sync();
+        let mut favorites = favorites_db.get_all().await?;
+        favorites.extend(favorites_db.get_trash().await?);
+        let sync_ids = sync_db.sync_ids().await?;
+        let mut shadows = sync_db.shadows().await?;
+        let edit_times = sync_db.edit_times().await?;
+        let now = Utc::now();
+
+        let mut changes = Vec::new();
+        let mut updated = Vec::new();
+        for favorite in favorites {
+            let Some(id) = favorite.id else {
+                continue;
+            };
+            let Some(sync_id) = sync_ids.get(&id) else {
+                continue;
+            };
+
+            let Some(mut shadow) = shadows.remove(sync_id) else {
+                let media = self.upload_media(&favorite).await?;
+                updated.push((
+                    sync_id.clone(),
+                    self.shadow_of(&favorite, now, self.device_id),
+                ));
+                changes.push((
+                    sync_id.clone(),
+                    now,
+                    Change::Create {
+                        favorite: Box::new(favorite),
+                        media,
+                    },
+                ));
+                continue;
+            };
+
+            let mut changed = false;
+            for field in SyncField::ALL {
+                let value = field.value_of(&favorite);
+                if shadow.fields.get(&field).map(|state| &state.value) == Some(&value) {
+                    continue;
+                }
+                // Unstamped edits predate edit tracking; they're as new as
+                // anything this device knows of.
+                let at = edit_times.get(&(id, field)).copied().unwrap_or(now);
+                shadow.fields.insert(
+                    field,
+                    FieldState {
+                        value: value.clone(),
+                        at,
+                        device_id: self.device_id.to_string(),
+                    },
+                );
+                changes.push((sync_id.clone(), at, Change::Update { field, value }));
+                changed = true;
+            }
+
+            let uses = i64::from(favorite.use_count) - shadow.use_count;
+            if uses > 0 {
+                changes.push((
+                    sync_id.clone(),
+                    now,
+                    Change::Use {
+                        count: uses,
+                        last_used: favorite.last_used,
+                    },
+                ));
+            }
+            if uses != 0 {
+                shadow.use_count = favorite.use_count.into();
+                changed = true;
+            }
+
+            if changed {
+                updated.push((sync_id.clone(), shadow));
+            }
+        }
+
+        // Synced before but gone now: deleted for good on this device.
+        let deleted: Vec<String> = shadows.into_keys().collect();
+        for sync_id in &deleted {
+            changes.push((sync_id.clone(), now, Change::Delete));
+        }
+
+        // The log goes first. If writing it fails the shadow still holds the
+        // old values and the same changes are found next time.
+        let exported = changes.len();
+        self.append(changes).await?;
+        for (sync_id, shadow) in &updated {
+            sync_db.save_shadow(sync_id, shadow).await?;
+        }
+        for sync_id in &deleted {
+            sync_db.bury(sync_id).await?;
+        }
+        Ok(exported)
+    }
+
+    async fn append(&self, changes: Vec<(String, DateTime<Utc>, Change)>) -> Result<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let path = self.log_path(self.device_id);
+        // This device's own row in `sync_cursors` holds the last seq it
+        // wrote, so a damaged log can't make numbering start over.
+        let sync_db = self.db.sync();
+        let mut seq = sync_db
+            .cursor(self.device_id)
+            .await?
+            .max(last_seq(&path).await?);
+
+        let mut lines = String::new();
+        for (sync_id, at, change) in changes {
+            seq += 1;
+            let record = ChangeRecord {
+                seq,
+                device_id: self.device_id.to_string(),
+                at,
+                sync_id,
+                change,
+            };
+            lines.push_str(&serde_json::to_string(&record)?);
+            lines.push('\n');
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        file.write_all(lines.as_bytes())
+            .await
+            .context("Failed to write sync log")?;
+        file.flush().await.context("Failed to flush sync log")?;
+        sync_db.set_cursor(self.device_id, seq).await?;
+        Ok(())
+    }
+
+    async fn import(&self) -> Result<(usize, usize)> {
+        let sync_db = self.db.sync();
+        let mut devices = Vec::new();
+        let mut entries = fs::read_dir(self.folder.join(DEVICES_DIR))
+            .await
+            .context("Failed to read sync folder")?;
+        while let Some(entry) = entries.next_entry().await? {
+            let device_id = entry.file_name().to_string_lossy().into_owned();
+            if device_id != self.device_id {
+                devices.push(device_id);
+            }
+        }
+        devices.sort();
+
+        let (mut imported, mut pending) = (0, 0);
+        for device_id in devices {
+            let cursor = sync_db.cursor(&device_id).await?;
+            let records: Vec<ChangeRecord> = read_log(&self.log_path(&device_id))
+                .await?
+                .into_iter()
+                .filter(|record| record.seq > cursor)
+                .collect();
+
+            for (i, record) in records.iter().enumerate() {
+                match self.apply(record).await? {
+                    Applied::Done => {
+                        sync_db.set_cursor(&device_id, record.seq).await?;
+                        imported += 1;
+                    }
+                    Applied::Waiting => {
+                        pending += records.len() - i;
+                        break;
+                    }
+                }
+            }
+        }
+        Ok((imported, pending))
+    }
+
+    async fn apply(&self, record: &ChangeRecord) -> Result<Applied> {
+        let sync_db = self.db.sync();
+        if sync_db.is_buried(&record.sync_id).await? {
+            return Ok(Applied::Done);
+        }
+
+        let Some(id) = sync_db.find(&record.sync_id).await? else {
+            return match &record.change {
+                Change::Create { favorite, media } => {
+                    self.create_local(record, favorite, media).await
+                }
+                Change::Delete => {
+                    sync_db.bury(&record.sync_id).await?;
+                    Ok(Applied::Done)
+                }
+                Change::Update { .. } | Change::Use { .. } => Ok(Applied::Waiting),
+            };
+        };
+
+        match &record.change {
+            // Saved on both devices, e.g. the same Klipy GIF. Merge the two.
+            Change::Create { favorite, .. } => {
+                for field in SyncField::ALL {
+                    self.merge_field(id, record, field, field.value_of(favorite))
+                        .await?;
+                }
+                self.add_uses(id, record, favorite.use_count.into(), favorite.last_used)
+                    .await?;
+            }
+            Change::Update { field, value } => {
+                self.merge_field(id, record, *field, value.clone()).await?;
+            }
+            Change::Use { count, last_used } => {
+                self.add_uses(id, record, *count, *last_used).await?;
+            }
+            Change::Delete => {
+                self.delete_local(id).await?;
+                sync_db.bury(&record.sync_id).await?;
+            }
+        }
+        Ok(Applied::Done)
+    }
+
+    async fn create_local(
+        &self,
+        record: &ChangeRecord,
+        remote: &Favorite,
+        media: &[SyncedMedia],
+    ) -> Result<Applied> {
+        if !media.iter().all(|m| self.media_path(m).exists()) {
+            return Ok(Applied::Waiting);
+        }
+
+        let mut favorite = remote.clone();
+        favorite.id = None;
+        favorite.filepath = None;
+        favorite.mp4_filepath = None;
+        let trashed = favorite.deleted_at.take().is_some();
+
+        let media_type = favorite.media_type.to_string();
+        for m in media {
+            // Keep the sender's file name unless something else has it here.
+            let mut name = m.name.clone();
+            let existing = self.downloader.media_path(&name, &media_type);
+            if existing.exists() && file_hash(&existing).await? != m.hash {
+                name = format!("{}.{}", m.hash, m.extension);
+            }
+            let path = self
+                .downloader
+                .store_file(&self.media_path(m), &name, &media_type)
+                .await?;
+            let path = Some(path.to_string_lossy().into_owned());
+            match m.role {
+                MediaRole::File => favorite.filepath = path,
+                MediaRole::Mp4 => favorite.mp4_filepath = path,
+            }
+        }
+
+        let favorites_db = self.db.favorites();
+        let id = favorites_db
+            .create_with_sync_id(&favorite, &record.sync_id)
+            .await?;
+        if trashed {
+            favorite.id = Some(id);
+            self.move_files_to_trash(&favorite).await;
+            favorites_db.trash(id).await?;
+        }
+
+        let shadow = self.shadow_of(remote, record.at, &record.device_id);
+        self.db.sync().save_shadow(&record.sync_id, &shadow).await?;
+        Ok(Applied::Done)
+    }
+
+    /// Apply another device's write of `field` if it's newer than the one
+    /// this device has.
+    async fn merge_field(
+        &self,
+        id: i64,
+        record: &ChangeRecord,
+        field: SyncField,
+        value: serde_json::Value,
+    ) -> Result<()> {
+        let sync_db = self.db.sync();
+        let mut shadow = sync_db.shadow(&record.sync_id).await?.unwrap_or_default();
+        if let Some(current) = shadow.fields.get(&field) {
+            if !current.loses_to(record.at, &record.device_id) {
+                return Ok(());
+            }
+        }
+
+        let favorites_db = self.db.favorites();
+        let mut favorite = favorites_db
+            .get_by_id(id)
+            .await?
+            .with_context(|| format!("Favorite {} disappeared", id))?;
+        let was_trashed = favorite.deleted_at.is_some();
+        field
+            .apply_to(&mut favorite, value.clone())
+            .with_context(|| format!("Bad value for {:?} in {}", field, record.sync_id))?;
+
+        match (field, was_trashed, favorite.deleted_at.is_some()) {
+            (SyncField::Trashed, false, true) => {
+                self.move_files_to_trash(&favorite).await;
+                favorites_db.trash(id).await?;
+            }
+            (SyncField::Trashed, true, false) => {
+                self.restore_files(&favorite).await;
+                favorites_db.restore(id).await?;
+            }
+            (SyncField::Trashed, _, _) => {}
+            _ => favorites_db.update(&favorite).await?,
+        }
+
+        shadow.fields.insert(
+            field,
+            FieldState {
+                value,
+                at: record.at,
+                device_id: record.device_id.clone(),
+            },
+        );
+        sync_db.save_shadow(&record.sync_id, &shadow).await
+    }
+
+    async fn add_uses(
+        &self,
+        id: i64,
+        record: &ChangeRecord,
+        count: i64,
+        last_used: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        let sync_db = self.db.sync();
+        sync_db.add_uses(id, count, last_used).await?;
+        // Counted into the shadow too, so they aren't sent straight back.
+        let mut shadow = sync_db.shadow(&record.sync_id).await?.unwrap_or_default();
+        shadow.use_count += count;
+        sync_db.save_shadow(&record.sync_id, &shadow).await
+    }
+
+    async fn delete_local(&self, id: i64) -> Result<()> {
+        let favorites_db = self.db.favorites();
+        if let Some(favorite) = favorites_db.get_by_id(id).await? {
+            for path in files_of(&favorite) {
+                let path = if favorite.deleted_at.is_some() {
+                    self.downloader.trash_path(id, &path)
+                } else {
+                    path
+                };
+                if path.exists() {
+                    if let Err(e) = Downloader::delete_file(&path).await {
+                        eprintln!("Warning: {:#}", e);
+                    }
+                }
+            }
+        }
+        favorites_db.delete(id).await
+    }
+
+    /// Files are moved best-effort, as `delete_favorite` does.
+    async fn move_files_to_trash(&self, favorite: &Favorite) {
+        let Some(id) = favorite.id else {
+            return;
+        };
+        for path in files_of(favorite) {
+            if path.exists() {
+                if let Err(e) = self.downloader.move_to_trash(id, &path).await {
+                    eprintln!("Warning: {:#}", e);
+                }
+            }
+        }
+    }
+
+    async fn restore_files(&self, favorite: &Favorite) {
+        let Some(id) = favorite.id else {
+            return;
+        };
+        for path in files_of(favorite) {
+            if !self.downloader.trash_path(id, &path).exists() {
+                continue;
+            }
+            let restored = match self.downloader.restore_from_trash(id, &path).await {
+                Ok(restored) => restored,
+                Err(e) => {
+                    eprintln!("Warning: {:#}", e);
+                    continue;
+                }
+            };
+            if restored != path {
+                let relocated = self
+                    .db
+                    .favorites()
+                    .relocate_file(id, &path.to_string_lossy(), &restored.to_string_lossy())
+                    .await;
+                if let Err(e) = relocated {
+                    eprintln!("Warning: {:#}", e);
+                }
+            }
+        }
+    }
+
+    /// Copy a favorite's files into the folder's media store, skipping any
+    /// already there and any missing on disk.
+    async fn upload_media(&self, favorite: &Favorite) -> Result<Vec<SyncedMedia>> {
+        let roles = [
+            (MediaRole::File, &favorite.filepath),
+            (MediaRole::Mp4, &favorite.mp4_filepath),
+        ];
+
+        let mut media = Vec::new();
+        for (role, path) in roles {
+            let Some(path) = path.as_deref().map(PathBuf::from) else {
+                continue;
+            };
+            let path = match favorite.id {
+                Some(id) if favorite.deleted_at.is_some() => self.downloader.trash_path(id, &path),
+                _ => path,
+            };
+            if !path.exists() {
+                continue;
+            }
+
+            let synced = SyncedMedia {
+                role,
+                hash: file_hash(&path).await?,
+                extension: path
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_lowercase())
+                    .unwrap_or_else(|| "bin".to_string()),
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            };
+
+            let dest = self.media_path(&synced);
+            if !dest.exists() {
+                // Copied under a temporary name so other devices never see
+                // half a file.
+                let partial = dest.with_extension("partial");
+                fs::copy(&path, &partial)
+                    .await
+                    .with_context(|| format!("Failed to copy {} to sync folder", path.display()))?;
+                fs::rename(&partial, &dest)
+                    .await
+                    .context("Failed to finish copying to sync folder")?;
+            }
+            media.push(synced);
+        }
+        Ok(media)
+    }
+
+    fn shadow_of(&self, favorite: &Favorite, at: DateTime<Utc>, device_id: &str) -> SyncShadow {
+        SyncShadow {
+            fields: SyncField::ALL
+                .into_iter()
+                .map(|field| {
+                    let state = FieldState {
+                        value: field.value_of(favorite),
+                        at,
+                        device_id: device_id.to_string(),
+                    };
+                    (field, state)
+                })
+                .collect(),
+            use_count: favorite.use_count.into(),
+        }
+    }
+
+    fn log_path(&self, device_id: &str) -> PathBuf {
+        self.folder.join(DEVICES_DIR).join(device_id).join(LOG_FILE)
+    }
+
+    fn media_path(&self, media: &SyncedMedia) -> PathBuf {
+        self.folder
+            .join(MEDIA_DIR)
+            .join(format!("{}.{}", media.hash, media.extension))
+    }
+}
+
+fn files_of(favorite: &Favorite) -> impl Iterator<Item = PathBuf> + '_ {
+    [&favorite.filepath, &favorite.mp4_filepath]
+        .into_iter()
+        .flatten()
+        .map(PathBuf::from)
+}
+
+/// Records in a log, in order. Reading stops at the first line that doesn't
+/// parse: the folder sync tool may still be copying the end of the file.
+async fn read_log(path: &Path) -> Result<Vec<ChangeRecord>> {
+    let content = match fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    Ok(content
+        .lines()
+        .map_while(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Highest seq in a log, skipping lines that don't parse rather than
+/// stopping at them.
+async fn last_seq(path: &Path) -> Result<u64> {
+    let content = match fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<ChangeRecord>(line).ok())
+        .map(|record| record.seq)
+        .max()
+        .unwrap_or(0))
+}
+
+async fn file_hash(path: &Path) -> Result<String> {
+    let bytes = fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MediaType;
+    use tempfile::TempDir;
+
+    struct Device {
+        db: Database,
+        downloader: Downloader,
+        id: String,
+        _temp: TempDir,
+    }
+
+    impl Device {
+        async fn new(id: &str) -> Self {
+            let temp = TempDir::new().unwrap();
+            let db = Database::new(temp.path().join("test.db")).await.unwrap();
+            db.run_migrations().await.unwrap();
+            let downloader = Downloader::new(temp.path().join("media")).unwrap();
+            Self {
+                db,
+                downloader,
+                id: id.to_string(),
+                _temp: temp,
+            }
+        }
+
+        async fn sync(&self, folder: &TempDir) -> SyncReport {
+            FolderSync::new(&self.db, &self.downloader, folder.path(), &self.id)
+                .run()
+                .await
+                .unwrap()
+        }
+
+        async fn add(&self, name: &str, contents: &[u8]) -> i64 {
+            self.downloader.ensure_directories().await.unwrap();
+            let path = self.downloader.media_path(name, "gif");
+            std::fs::write(&path, contents).unwrap();
+            let favorite = Favorite::new(
+                name.to_string(),
+                Some(path.to_string_lossy().into_owned()),
+                MediaType::Gif,
+            );
+            self.db.favorites().create(&favorite).await.unwrap()
+        }
+
+        async fn only(&self) -> Favorite {
+            let mut all = self.db.favorites().get_all().await.unwrap();
+            all.extend(self.db.favorites().get_trash().await.unwrap());
+            assert_eq!(all.len(), 1);
+            all.pop().unwrap()
+        }
+
+        async fn edit(&self, edit: impl FnOnce(&mut Favorite)) {
+            let mut favorite = self.only().await;
+            edit(&mut favorite);
+            self.db.favorites().update(&favorite).await.unwrap();
+        }
+    }
+
+    async fn pair() -> (Device, Device, TempDir) {
+        (
+            Device::new("laptop").await,
+            Device::new("desktop").await,
+            TempDir::new().unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_favorite_and_media_reach_other_device() {
+        let (a, b, folder) = pair().await;
+        a.add("cat.gif", b"GIF89a cat").await;
+
+        assert_eq!(a.sync(&folder).await.exported, 1);
+        assert_eq!(b.sync(&folder).await.imported, 1);
+
+        let copy = b.only().await;
+        assert_eq!(copy.filename, "cat.gif");
+        let path = PathBuf::from(copy.filepath.unwrap());
+        assert!(path.starts_with(b.downloader.media_dir()));
+        assert_eq!(std::fs::read(path).unwrap(), b"GIF89a cat");
+
+        b.edit(|f| f.custom_tags = vec!["cat".to_string()]).await;
+        b.sync(&folder).await;
+        a.sync(&folder).await;
+        assert_eq!(a.only().await.custom_tags, vec!["cat"]);
+
+        // Applied changes aren't echoed back.
+        for device in [&a, &b] {
+            let report = device.sync(&folder).await;
+            assert_eq!((report.exported, report.imported), (0, 0));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conflicts_merge_by_field_newest_wins() {
+        let (a, b, folder) = pair().await;
+        a.add("cat.gif", b"GIF89a cat").await;
+        a.sync(&folder).await;
+        b.sync(&folder).await;
+
+        a.edit(|f| {
+            f.description = Some("from laptop".to_string());
+            f.pinned = true;
+        })
+        .await;
+        a.sync(&folder).await;
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        b.edit(|f| f.description = Some("from desktop".to_string()))
+            .await;
+        b.sync(&folder).await;
+        a.sync(&folder).await;
+
+        for device in [&a, &b] {
+            let favorite = device.only().await;
+            assert_eq!(favorite.description.as_deref(), Some("from desktop"));
+            assert!(favorite.pinned);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_newer_edit_wins_even_when_synced_first() {
+        let (a, b, folder) = pair().await;
+        let id = a.add("cat.gif", b"GIF89a cat").await;
+        a.sync(&folder).await;
+        b.sync(&folder).await;
+
+        b.edit(|f| {
+            f.description = Some("from desktop".to_string());
+            f.custom_tags = vec!["desk".to_string()];
+        })
+        .await;
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        a.edit(|f| f.description = Some("from laptop".to_string()))
+            .await;
+        a.db.tags().tag_favorites(&[id], &["lap"]).await.unwrap();
+
+        // The laptop's edits are newer but reach the folder first; the
+        // desktop's older ones, found later, mustn't overwrite them.
+        a.sync(&folder).await;
+        b.sync(&folder).await;
+        a.sync(&folder).await;
+
+        for device in [&a, &b] {
+            let favorite = device.only().await;
+            assert_eq!(favorite.description.as_deref(), Some("from laptop"));
+            assert_eq!(favorite.custom_tags, vec!["lap"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_uses_add_up() {
+        let (a, b, folder) = pair().await;
+        let id = a.add("cat.gif", b"GIF89a cat").await;
+        a.sync(&folder).await;
+        b.sync(&folder).await;
+
+        a.db.favorites().increment_use_count(id).await.unwrap();
+        let b_id = b.only().await.id.unwrap();
+        b.db.favorites().increment_use_count(b_id).await.unwrap();
+        b.db.favorites().increment_use_count(b_id).await.unwrap();
+
+        a.sync(&folder).await;
+        b.sync(&folder).await;
+        a.sync(&folder).await;
+        assert_eq!(a.only().await.use_count, 3);
+        assert_eq!(b.only().await.use_count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_seq_keeps_climbing_past_a_corrupt_log_line() {
+        let (a, _b, folder) = pair().await;
+        a.add("cat.gif", b"GIF89a cat").await;
+        a.sync(&folder).await;
+
+        let log = folder.path().join(DEVICES_DIR).join(&a.id).join(LOG_FILE);
+        let written = std::fs::read_to_string(&log).unwrap();
+        std::fs::write(&log, format!("{{not json\n{}", written)).unwrap();
+
+        a.edit(|f| f.description = Some("cat".to_string())).await;
+        a.sync(&folder).await;
+
+        let seqs: Vec<u64> = std::fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .filter_map(|line| serde_json::from_str::<ChangeRecord>(line).ok())
+            .map(|record| record.seq)
+            .collect();
+        assert!(seqs.len() > 1);
+        assert!(seqs.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[tokio::test]
+    async fn test_trash_and_delete_propagate() {
+        let (a, b, folder) = pair().await;
+        let id = a.add("cat.gif", b"GIF89a cat").await;
+        a.sync(&folder).await;
+        b.sync(&folder).await;
+
+        a.db.favorites().trash(id).await.unwrap();
+        a.sync(&folder).await;
+        b.sync(&folder).await;
+        assert!(b.db.favorites().get_all().await.unwrap().is_empty());
+        let trashed = b.only().await;
+        let b_id = trashed.id.unwrap();
+        let original = PathBuf::from(trashed.filepath.unwrap());
+        assert!(b.downloader.trash_path(b_id, &original).exists());
+
+        a.db.favorites().delete(id).await.unwrap();
+        a.sync(&folder).await;
+        b.sync(&folder).await;
+        assert!(b.db.favorites().get_trash().await.unwrap().is_empty());
+        assert!(!b.downloader.trash_path(b_id, &original).exists());
+
+        // A third device joining later doesn't bring it back from the
+        // original create.
+        let c = Device::new("tablet").await;
+        c.sync(&folder).await;
+        assert!(c.db.favorites().get_all().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_create_waits_for_media() {
+        let (a, b, folder) = pair().await;
+        a.add("cat.gif", b"GIF89a cat").await;
+        a.sync(&folder).await;
+
+        let media_dir = folder.path().join(MEDIA_DIR);
+        let stored = std::fs::read_dir(&media_dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let aside = folder.path().join("aside");
+        std::fs::rename(&stored, &aside).unwrap();
+
+        let report = b.sync(&folder).await;
+        assert_eq!((report.imported, report.pending), (0, 1));
+        assert!(b.db.favorites().get_all().await.unwrap().is_empty());
+
+        std::fs::rename(&aside, &stored).unwrap();
+        let report = b.sync(&folder).await;
+        assert_eq!((report.imported, report.pending), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_same_klipy_gif_on_both_devices_merges() {
+        let (a, b, folder) = pair().await;
+        for device in [&a, &b] {
+            let mut favorite = Favorite::new("wave.gif".to_string(), None, MediaType::Gif);
+            favorite.source = Some(crate::models::Source::Klipy);
+            favorite.source_id = Some("wave-123".to_string());
+            favorite.use_count = 1;
+            device.db.favorites().create(&favorite).await.unwrap();
+        }
+
+        a.sync(&folder).await;
+        b.sync(&folder).await;
+        a.sync(&folder).await;
+        assert_eq!(a.only().await.use_count, 2);
+        assert_eq!(b.only().await.use_count, 2);
+    }
+}
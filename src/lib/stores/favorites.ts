@@ -44,7 +44,7 @@ function createFavoritesStore() {
       }
     },
 
-    // Delete a favorite
+    // Move a favorite to the trash
     async delete(id: number) {
       try {
         await invoke('delete_favorite', { id });
@@ -71,6 +71,50 @@ function createFavoritesStore() {
       }
     },
 
+    // Pin or unpin; pinned favorites sort before everything else
+    async setPinned(id: number, pinned: boolean) {
+      try {
+        await invoke('set_favorite_pinned', { id, pinned });
+        update(favs => favs.map(f => f.id === id ? { ...f, pinned } : f));
+      } catch (error) {
+        console.error('Failed to update pin:', error);
+        throw error;
+      }
+    },
+
+    // Move a favorite directly before another one (or to the end)
+    async move(id: number, beforeId: number | null) {
+      try {
+        await invoke('move_favorite', { id, beforeId });
+        set(await invoke<Favorite[]>('get_all_favorites'));
+      } catch (error) {
+        console.error('Failed to move favorite:', error);
+        throw error;
+      }
+    },
+
+    // Take a favorite back out of the trash
+    async restore(id: number) {
+      try {
+        const favorite = await invoke<Favorite>('restore_favorite', { id });
+        set(await invoke<Favorite[]>('get_all_favorites'));
+        return favorite;
+      } catch (error) {
+        console.error('Failed to restore favorite:', error);
+        throw error;
+      }
+    },
+
+    // Trashed favorites, most recently deleted first
+    async listTrash() {
+      return invoke<Favorite[]>('list_trash');
+    },
+
+    // Delete everything in the trash for good; returns how many went
+    async emptyTrash() {
+      return invoke<number>('empty_trash');
+    },
+
     // Import a local file
     async importFile(filePath: string) {
       try {
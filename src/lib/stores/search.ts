@@ -2,7 +2,7 @@ import { writable, get } from 'svelte/store';
 import { invoke } from '@tauri-apps/api/core';
 import { settings } from '$lib/stores/settings';
 import { recentSearches } from '$lib/stores/recent';
-import type { Favorite, KlipyGifResult, KlipySearchResults, KlipyCategory, KlipyCategoriesResult, SearchResult, ViewMode } from '$lib/types';
+import type { Favorite, KlipyContentType, KlipyGifResult, KlipySearchResults, KlipyCategory, KlipyCategoriesResult, SearchResult, ViewMode } from '$lib/types';
 import { isKlipyGif } from '$lib/types';
 
 // Current view mode
@@ -11,6 +11,9 @@ export const viewMode = writable<ViewMode>('favorites');
 // Current category (when viewing a specific category)
 export const currentCategory = writable<KlipyCategory | null>(null);
 
+// What Klipy searches, categories and trending show: GIFs, stickers, clips or memes
+export const contentType = writable<KlipyContentType>('gif');
+
 // Search query store
 export const searchQuery = writable<string>('');
 
@@ -87,7 +90,8 @@ export async function performSearch(query: string) {
       query,
       limit: 50,
       page: 1,
-      showAds
+      showAds,
+      contentType: get(contentType)
     });
 
     // Set results with empty local array
@@ -142,7 +146,8 @@ export async function loadMoreResults() {
       query: currentQuery,
       limit: 50,
       page: nextPage,
-      showAds
+      showAds,
+      contentType: get(contentType)
     });
 
     if (!result.items || result.items.length === 0) {
@@ -202,6 +207,9 @@ export function cancelPendingSearch() {
 // Single command on the backend; keep this thin wrapper so callers
 // don't have to spell out every kwarg.
 export async function saveKlipyGif(gif: KlipyGifResult): Promise<Favorite> {
+  // Where the GIF was found seeds its tags, and the query is kept so the
+  // favorite can be found again by the words that turned it up.
+  const mode = get(viewMode);
   return await invoke<Favorite>('add_klipy_favorite', {
     gifUrl: gif.gif_url,
     mp4Url: gif.mp4_url ?? null,
@@ -210,9 +218,26 @@ export async function saveKlipyGif(gif: KlipyGifResult): Promise<Favorite> {
     title: gif.title || 'Untitled',
     width: gif.width,
     height: gif.height,
+    query: mode === 'search' ? currentQuery || null : null,
+    category: mode === 'category' ? get(currentCategory)?.name ?? null : null,
+    mediaType: gif.media_type,
   });
 }
 
+// Switch content type and reload the Klipy view on screen in it
+export function setContentType(type: KlipyContentType) {
+  contentType.set(type);
+  const mode = get(viewMode);
+  const category = get(currentCategory);
+  if (mode === 'trending') {
+    loadTrending();
+  } else if (mode === 'search' && currentQuery) {
+    performSearch(currentQuery);
+  } else if (mode === 'category' && category) {
+    loadCategoryGifs(category);
+  }
+}
+
 // Get trending GIFs
 export async function getTrending(limit: number = 50, page: number = 1) {
   try {
@@ -223,7 +248,8 @@ export async function getTrending(limit: number = 50, page: number = 1) {
     const result = await invoke<KlipySearchResults>('get_klipy_trending', {
       limit,
       page,
-      showAds
+      showAds,
+      contentType: get(contentType)
     });
     return result;
   } catch (error) {
@@ -304,7 +330,8 @@ export async function loadCategoryGifs(category: KlipyCategory) {
       query: category.name,
       limit: 50,
       page: 1,
-      showAds
+      showAds,
+      contentType: get(contentType)
     });
 
     searchResults.set({
@@ -321,15 +348,56 @@ export async function loadCategoryGifs(category: KlipyCategory) {
   }
 }
 
+// "More like this" for a favorite: similar favorites first, then (for Klipy
+// favorites) more from Klipy. Not paginated.
+export async function loadSimilar(favorite: Favorite) {
+  if (!favorite.id) return;
+  isSearching.set(true);
+  viewMode.set('similar');
+  currentCategory.set(null);
+  hasMore = false;
+
+  try {
+    const showAds = settings.get()?.show_ads ?? true;
+    const result = await invoke<SearchResult>('find_similar', {
+      favoriteId: favorite.id,
+      showAds,
+      contentType: get(contentType)
+    });
+    searchResults.set(result);
+  } catch (error) {
+    console.error('Failed to find similar GIFs:', error);
+    searchError.set(error as string);
+  } finally {
+    isSearching.set(false);
+  }
+}
+
 // Go back to favorites view
-export async function goHome() {
+// `collection` is a local search (e.g. `tag:work`) to narrow the favorites
+// to — the active profile's default collection.
+export async function goHome(collection: string | null = null) {
   viewMode.set('favorites');
   currentCategory.set(null);
   clearSearch();
   
+  try {
+    const favorites = collection
+      ? await invoke<Favorite[]>('search_local', { query: collection })
+      : await invoke<Favorite[]>('get_all_favorites');
+    searchResults.set({ local: favorites, klipy: undefined });
+  } catch (error) {
+    console.error('Failed to load favorites:', error);
+  }
+}
+
+// Re-fetch the favorites grid after a change that affects its order (pin, move)
+export async function reloadFavorites() {
+  if (get(viewMode) !== 'favorites') return;
+
   try {
     const allFavorites = await invoke<Favorite[]>('get_all_favorites');
-    searchResults.set({ local: allFavorites, klipy: undefined });
+    searchResults.update((current) => ({ ...current, local: allFavorites }));
   } catch (error) {
     console.error('Failed to load favorites:', error);
   }
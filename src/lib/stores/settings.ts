@@ -4,7 +4,7 @@ import type { Settings } from '$lib/types';
 
 // Default settings
 const defaultSettings: Settings = {
-  hotkey: 'Option+Cmd+G',
+  hotkeys: { toggle_picker: 'Option+Cmd+G' },
   window_width: 800,
   window_height: 600,
   max_item_width: 400,
@@ -15,7 +15,21 @@ const defaultSettings: Settings = {
   clipboard_format: 'gif',
   show_ads: true,
   tile_size: 'medium',
-  always_on_top: false
+  always_on_top: false,
+  window_placement: 'center',
+  auto_paste: false,
+  auto_paste_delay_ms: 150,
+  auto_paste_command: null,
+  auto_paste_denylist: [],
+  tray_recent_count: 5,
+  trash_retention_days: 30,
+  backup_interval_hours: 24,
+  backup_keep_count: 7,
+  backup_max_age_days: 30,
+  sync_folder: null,
+  content_filter: 'moderate',
+  locale: null,
+  content_denylist: []
 };
 
 // Settings store
@@ -59,6 +73,11 @@ function createSettingsStore() {
       }
     },
 
+    // Applied from the backend's `settings-changed` event — already persisted.
+    set(settings: Settings) {
+      set(settings);
+    },
+
     reset() {
       set(defaultSettings);
     }
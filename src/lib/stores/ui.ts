@@ -48,19 +48,30 @@ export function closeContextMenu() {
 }
 
 // Toast notifications
+export interface ToastAction {
+  label: string;
+  run: () => void;
+}
+
 export const toast = writable<{
   show: boolean;
   message: string;
   type: 'success' | 'error' | 'info';
+  action?: ToastAction;
 }>({
   show: false,
   message: '',
   type: 'info'
 });
 
-// Show toast notification
-export function showToast(message: string, type: 'success' | 'error' | 'info' = 'info', duration: number = 3000) {
-  toast.set({ show: true, message, type });
+// Show toast notification, optionally with a button (e.g. Undo)
+export function showToast(
+  message: string,
+  type: 'success' | 'error' | 'info' = 'info',
+  duration: number = 3000,
+  action?: ToastAction
+) {
+  toast.set({ show: true, message, type, action });
 
   setTimeout(() => {
     toast.update(t => ({ ...t, show: false }));
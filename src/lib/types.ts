@@ -6,7 +6,11 @@ export type Theme = 'light' | 'dark' | 'system';
 export type ClipboardMode = 'file' | 'url';
 export type ClipboardFormat = 'gif' | 'mp4';
 export type TileSize = 'small' | 'medium' | 'large';
-export type ViewMode = 'favorites' | 'trending' | 'categories' | 'category' | 'search';
+export type WindowPlacement = 'center' | 'cursor' | 'remember';
+export type ContentFilter = 'strict' | 'moderate' | 'off';
+export type KlipyContentType = 'gif' | 'sticker' | 'clip' | 'meme';
+export type HotkeyAction = 'toggle_picker' | 'open_favorites' | 'open_trending' | 'paste_recent' | 'next_profile';
+export type ViewMode = 'favorites' | 'trending' | 'categories' | 'category' | 'search' | 'similar';
 
 export interface Favorite {
   id?: number;
@@ -18,6 +22,7 @@ export interface Favorite {
   source?: Source;
   source_id?: string;
   source_url?: string;
+  source_query?: string | null;
   tags: string[];
   custom_tags: string[];
   description?: string;
@@ -27,10 +32,37 @@ export interface Favorite {
   created_at: string;
   last_used?: string;
   use_count: number;
+  pinned?: boolean;
+  sort_position?: number;
+  phash?: string | null;
+  palette?: PaletteColor[];  // dominant colors, most common first
+  frame_count?: number | null;
+  duration_ms?: number | null;
+  fps?: number | null;
+  loop_count?: number | null;  // times played, 0 = forever
+  has_alpha?: boolean | null;
+  deleted_at?: string | null;  // set while in the trash
+}
+
+export interface PaletteColor {
+  r: number;
+  g: number;
+  b: number;
+  weight: number;  // share of the image, 0-1
+}
+
+export interface TagCount {
+  name: string;
+  count: number;
+}
+
+export interface DuplicateGroup {
+  favorites: Favorite[];  // favorites[0] is the copy merge_duplicates keeps by default
+  similarity: number;
 }
 
 export interface Settings {
-  hotkey: string;
+  hotkeys: Partial<Record<HotkeyAction, string>>;
   window_width: number;
   window_height: number;
   max_item_width: number;
@@ -42,18 +74,87 @@ export interface Settings {
   show_ads: boolean;
   tile_size: TileSize;
   always_on_top: boolean;
+  window_placement: WindowPlacement;
+  auto_paste: boolean;
+  auto_paste_delay_ms: number;
+  auto_paste_command?: string | null;
+  auto_paste_denylist: string[];
+  tray_recent_count: number;
+  trash_retention_days: number;  // 0 = keep until emptied
+  backup_interval_hours: number;  // 0 = no automatic backups
+  backup_keep_count: number;
+  backup_max_age_days: number;  // 0 = no age limit
+  sync_folder?: string | null;  // shared folder to sync through; null = off
+  content_filter: ContentFilter;
+  locale?: string | null;  // e.g. "en_US"; null = the system locale
+  content_denylist: string[];  // words/tags hidden from Klipy results
+}
+
+// An entry of the config file that was skipped; `key` is as written there,
+// e.g. "settings.theme".
+export interface ConfigFileIssue {
+  key: string;
+  message: string;
+}
+
+// The optional config.toml / config.json in the app config dir.
+export interface ConfigFileStatus {
+  path: string | null;  // null = no config file
+  locked: (keyof Settings)[];  // pinned to the file; can't be changed here
+  issues: ConfigFileIssue[];
+  error: string | null;  // the whole file was unusable
+}
+
+// A named set of settings (work, personal, ...). Sent with `profile-changed`
+// when the active one changes; `null` there means none is active.
+export interface SettingsProfile {
+  name: string;
+  default_collection: string | null;  // local search the favorites view opens with
+  active: boolean;
+}
+
+// Result of `sync_now`.
+export interface SyncReport {
+  exported: number;
+  imported: number;
+  pending: number;  // waiting for media to arrive in the folder
 }
 
+// A database snapshot from `list_backups`. Media isn't copied; the counts
+// describe what was on disk when it was taken.
+export interface BackupInfo {
+  id: string;
+  created_at: string;
+  schema_version: number;
+  favorite_count: number;
+  db_size: number;
+  media_count: number;
+  media_size: number;
+}
+
+// Per-binding result of `register_hotkeys`; `error` is set when that one
+// shortcut couldn't be registered (bad syntax, conflict, taken by the OS).
+export interface HotkeyStatus {
+  action: HotkeyAction;
+  hotkey: string;
+  error?: string;
+}
+
+// Every Klipy content type comes through as `kind: 'gif'`; `content_type`
+// says which it is.
 export interface KlipyGifResult {
   kind: 'gif';
   id: string;
   slug: string;
   title: string;
   url: string;
-  gif_url: string;
-  mp4_url?: string;
+  gif_url: string;  // the file saved/copied; WebP or PNG for stickers, the silent preview for clips
+  mp4_url?: string;  // for clips, the video with sound
   width: number;
   height: number;
+  content_type: KlipyContentType;
+  media_type: MediaType;  // what a favorite saved from it holds
+  has_audio: boolean;
 }
 
 // Inline ad item from Klipy. `content` is a self-contained HTML document
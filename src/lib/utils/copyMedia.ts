@@ -18,7 +18,8 @@ function favoriteFilePath(
   fav: Favorite,
   preferred: ClipboardFormat,
 ): { path: string; via: 'file:gif' | 'file:mp4' } | null {
-  if (preferred === 'mp4' && fav.mp4_filepath) {
+  // A video favorite's MP4 is the real thing (a Klipy clip's has sound).
+  if ((preferred === 'mp4' || fav.media_type === 'video') && fav.mp4_filepath) {
     return { path: fav.mp4_filepath, via: 'file:mp4' };
   }
   if (fav.filepath) return { path: fav.filepath, via: 'file:gif' };
@@ -28,21 +29,23 @@ function favoriteFilePath(
 }
 
 /// For Klipy results we haven't favorited yet, pick a URL + filename to
-/// download temp-fashion before copying.
+/// download temp-fashion before copying. Clips always copy their MP4 so the
+/// sound comes along; stickers keep their WebP/PNG extension.
 function klipyDownloadTarget(
   klipy: KlipyGifResult,
   preferred: ClipboardFormat,
 ): { url: string; filename: string; via: 'file:gif' | 'file:mp4' } {
-  if (preferred === 'mp4' && klipy.mp4_url) {
+  if ((preferred === 'mp4' || klipy.has_audio) && klipy.mp4_url) {
     return {
       url: klipy.mp4_url,
       filename: `${klipy.slug}.mp4`,
       via: 'file:mp4',
     };
   }
+  const extension = klipy.gif_url.split('?')[0].split('.').pop() || 'gif';
   return {
     url: klipy.gif_url,
-    filename: `${klipy.slug}.gif`,
+    filename: `${klipy.slug}.${extension}`,
     via: 'file:gif',
   };
 }